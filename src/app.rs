@@ -1,8 +1,42 @@
-use crate::config::AppConfig;
-use crate::psp::Payment;
+use crate::config::{AppConfig, ConfigStore, CustomCurrency, MismatchedCurrency, OnFull, SpawnStyle, SymbolPosition};
+use crate::fx::{Converter, RateTable};
+use crate::ledger;
+use crate::milestones::{self, Milestone, Progress};
+use crate::particles::{self, Particle};
+use crate::poll::{PollEvent, PollSettings};
+use crate::psp::registry::{build_providers, registry};
+use crate::psp::{Payment, RateLimit};
+use crate::secrets::SecretStore;
+use crate::state::{self, Journal, SessionState};
+use crate::stats::{self, Stats};
+use crate::theme::Theme;
+use crate::tz::DisplayTz;
+use crate::money::format_money;
+use ratatui::widgets::ListState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
-#[derive(Debug, Clone)]
-#[allow(dead_code)]
+// What --summary-json prints on the way out
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSummary {
+    pub currency: String,
+    pub total_cents: i64,
+    pub net_cents: i64,
+    pub payment_count: usize,
+    pub duration_secs: u64,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub ended_at: chrono::DateTime<chrono::Utc>,
+    pub providers: Vec<ProviderSummary>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderSummary {
+    pub provider: String,
+    pub total_cents: i64,
+    pub payment_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BillAnimation {
     pub amount_cents: i64,
     pub y_pos: f64,
@@ -10,6 +44,11 @@ pub struct BillAnimation {
     pub settled: bool,
     pub age_ticks: u32,
     pub provider: String,
+    pub count: usize,
+    // Authorised-only bills draw dashed
+    pub unsettled: bool,
+    // Counts down from the tick the bill lands; it draws emphasised until zero
+    pub just_settled_ticks: u32,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -24,41 +63,238 @@ pub struct App {
     pub phase: AppPhase,
     pub bills: Vec<BillAnimation>,
     pub total_cents: i64,
+    // total_cents minus provider fees
+    pub net_cents: i64,
+    // The part of total_cents that has settled; the rest is only authorised so far
+    pub settled_cents: i64,
+    // Header total, counting up toward total_cents
+    pub displayed_total_cents: f64,
+    // The most recent payments, for the ledger; capped by max_session_payments
     pub session_payments: Vec<Payment>,
+    // Every payment this session, on disk; None for ephemeral runs and in tests
+    journal: Option<Journal>,
+    // Everything counted this session, including payments dropped from the list above
+    pub payment_count: usize,
+    // (provider, cents, count) per provider over the whole session
+    provider_totals: Vec<(String, i64, usize)>,
+    // Wall-clock start, for the providers' since window; elapsed time comes from the fields below
     pub start_time: chrono::DateTime<chrono::Utc>,
-    pub seen_ids: std::collections::HashSet<String>,
+    // Set from --since: the first fetch reaches back to here instead of start_time
+    pub backfill_since: Option<chrono::DateTime<chrono::Utc>>,
+    // Set from --compact: one status line, no bills
+    pub compact: bool,
+    // Monotonic, so clock adjustments can't make the session jump or run backwards
+    started: std::time::Instant,
+    // Time already on the clock when a saved session was resumed
+    elapsed_before: std::time::Duration,
+    // "provider:id" → created_at, so equal raw IDs from different providers both count
+    pub seen_ids: HashMap<String, chrono::DateTime<chrono::Utc>>,
+    // Newest created_at per provider; the next poll window starts just before it
+    newest_seen: HashMap<String, chrono::DateTime<chrono::Utc>>,
+    // IDs created before this were pruned from seen_ids, so a payment that old from a
+    // provider already counted from may have been counted and is refused
+    seen_floor: Option<chrono::DateTime<chrono::Utc>>,
+    // Ticks since the celebration started; only tick_celebration moves it
     pub celebration_tick: u32,
+    // Drives the spinner while there are no bills to watch
+    pub waiting_tick: u32,
+    pub particles: Vec<Particle>,
     pub setup_cursor: usize,
     pub setup_currency_idx: usize,
     pub setup_input: String,
+    // Shows a secret being typed in full instead of masked
+    pub reveal_input: bool,
     pub setup_step: SetupStep,
+    // Steps (with the provider they were about) that led here, so Esc retraces them
+    pub setup_history: Vec<(SetupStep, usize)>,
     pub provider_configs: Vec<ProviderSetupState>,
     pub current_provider_idx: usize,
     pub error_message: Option<String>,
+    pub status_message: Option<String>,
     pub pending_bills: Vec<PendingBill>,
+    // Longest the bill queue has been since it last emptied; scales the backlog bar
+    pub pending_peak: usize,
+    pub paused: bool,
+    // Payments received while paused, ingested on resume
+    pub held_payments: Vec<Payment>,
+    pub last_manual_refresh: Option<std::time::Instant>,
+    pub recent_errors: std::collections::VecDeque<ProviderError>,
+    // When recently counted payments arrived, oldest first, for the per-minute readout
+    pub recent_arrivals: std::collections::VecDeque<std::time::Instant>,
+    // Largest single payment this round, for the new-largest milestone
+    pub largest_payment_cents: i64,
+    // Milestones on screen, oldest first
+    pub toasts: std::collections::VecDeque<Toast>,
+    pub provider_statuses: Vec<ProviderStatus>,
+    pub fx: RateTable,
+    pub tz: DisplayTz,
+    // Currencies we've already warned about missing FX rates for, or skipped
+    fx_warned: std::collections::HashSet<String>,
+    pub show_ledger: bool,
+    pub show_stats: bool,
+    pub stats: Stats,
+    // Config as it was before re-entering setup from Running; Some while editing
+    pub edit_backup: Option<AppConfig>,
+    // Selection in the ledger overlay; row 0 is the newest payment
+    pub payment_list_scroll: ListState,
+    // Why config.json couldn't be loaded; setup moves it to config.json.bak before saving
+    pub config_load_error: Option<String>,
+    // Setup finished but the config couldn't be written; retried on the next save or on quit
+    pub config_unsaved: bool,
+    // Ledger filter (see ledger::filter_payments), and whether keys go to it
+    pub ledger_query: String,
+    pub ledger_filter_editing: bool,
+    #[cfg(feature = "metrics")]
+    pub metrics: crate::metrics::Metrics,
+    // What --ipc-socket hands out
+    #[cfg(unix)]
+    pub ipc: crate::ipc::SummaryFeed,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProviderStatus {
+    pub name: String,
+    pub last_success: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_error: Option<String>,
+    pub consecutive_failures: u32,
+    // Toggled with the number keys; a muted provider's payments aren't counted
+    pub muted: bool,
+    // When watching it began, for judging a provider that hasn't succeeded yet
+    pub since: chrono::DateTime<chrono::Utc>,
+    // The quota its API reported last, for providers that send one
+    pub rate_limit: Option<RateLimit>,
+}
+
+impl ProviderStatus {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            last_success: None,
+            last_error: None,
+            consecutive_failures: 0,
+            muted: false,
+            since: chrono::Utc::now(),
+            rate_limit: None,
+        }
+    }
+
+    // ✓ healthy, ✗ last poll failed, … no success within `stale_after`
+    pub fn health_glyph(&self, now: chrono::DateTime<chrono::Utc>, stale_after: chrono::Duration) -> &'static str {
+        if self.consecutive_failures > 0 {
+            return "✗";
+        }
+        match self.last_success {
+            Some(at) if now - at <= stale_after => "✓",
+            _ => "…",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProviderError {
+    pub provider: String,
+    pub message: String,
+    pub at: chrono::DateTime<chrono::Utc>,
 }
 
 #[derive(Debug, Clone)]
+pub struct Toast {
+    pub milestone: Milestone,
+    pub ticks_left: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PendingBill {
     pub amount_cents: i64,
     pub provider: String,
+    // Payments folded into this bill, and the combo window they came from
+    pub count: usize,
+    pub window: Option<i64>,
+    // Only authorised so far
+    pub unsettled: bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum SetupStep {
+    Welcome,
     Currency,
+    CustomCurrency,
+    Theme,
     ProviderSelect,
+    ProviderLabel,
     ProviderApiKey,
-    ProviderMerchantAccount,
+    ProviderSecondCredential,
     Confirm,
 }
 
+impl SetupStep {
+    // Steps where q is just a letter; Ctrl-C still quits from these
+    pub fn is_text_entry(&self) -> bool {
+        matches!(
+            self,
+            SetupStep::CustomCurrency | SetupStep::ProviderLabel | SetupStep::ProviderApiKey | SetupStep::ProviderSecondCredential
+        )
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ProviderSetupState {
     pub name: String,
     pub enabled: bool,
     pub api_key: String,
-    pub merchant_account: String,
+    // Adyen's merchant account, or the secret half of a SECRET_PROVIDERS credential pair
+    pub second_credential: String,
+    pub label: String,
+}
+
+impl ProviderSetupState {
+    fn new(name: &str, label: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            enabled: false,
+            api_key: String::new(),
+            second_credential: String::new(),
+            label: label.to_string(),
+        }
+    }
+
+    pub fn display_name(&self) -> String {
+        crate::psp::display_name(&self.name, Some(&self.label))
+    }
+}
+
+// What setup asks for: the first credential's label and, for providers that
+// need two, the second one's label and whether to mask it while typing
+pub struct CredentialPrompts {
+    pub first: &'static str,
+    pub second: Option<(&'static str, bool)>,
+}
+
+pub fn credential_prompts(provider: &str) -> CredentialPrompts {
+    match provider {
+        "Adyen" => CredentialPrompts { first: "API key", second: Some(("Merchant Account", false)) },
+        "PayPal" => CredentialPrompts { first: "Client ID", second: Some(("Client Secret", true)) },
+        "Braintree" => CredentialPrompts { first: "Public key", second: Some(("Private key", true)) },
+        _ => CredentialPrompts { first: "API key", second: None },
+    }
+}
+
+// How each provider's keys are known to start; anything else is probably a paste of the wrong thing
+fn key_prefixes(provider: &str) -> &'static [&'static str] {
+    match provider {
+        "Adyen" => &["AQE"],
+        "GoCardless" => &["live_", "sandbox_"],
+        _ => &[],
+    }
+}
+
+// A warning only: providers change key formats, so the key is kept either way
+pub fn key_format_warning(provider: &str, key: &str) -> Option<String> {
+    let prefixes = key_prefixes(provider);
+    if prefixes.is_empty() || prefixes.iter().any(|p| key.starts_with(p)) {
+        return None;
+    }
+    Some(format!("{} keys usually start with {}; double-check it was copied whole", provider, prefixes.join(" or ")))
 }
 
 pub const CURRENCIES: &[(&str, &str)] = &[
@@ -71,7 +307,114 @@ pub const CURRENCIES: &[(&str, &str)] = &[
     ("AUD", "A$"),
 ];
 
-pub const PROVIDERS: &[&str] = &["Mock", "Adyen"];
+// Providers that authenticate with an id/secret pair, kept in PspConfig::api_secret
+pub const SECRET_PROVIDERS: &[&str] = &["PayPal", "Braintree"];
+
+pub const MAX_RECENT_ERRORS: usize = 5;
+
+// Burst spawning kicks in past this many queued bills, releasing one more per this many
+const BURST_THRESHOLD: usize = 5;
+const MAX_BURST: usize = 4;
+
+// Past this many queued bills every style catches up, one more per this many, so
+// the stack doesn't lag minutes behind the total; capped to keep each frame cheap
+const CATCH_UP_THRESHOLD: usize = 30;
+const MAX_CATCH_UP: usize = 8;
+
+// Failed polls in a row before a provider counts as failing rather than flaky
+pub const FAILING_AFTER: u32 = 3;
+
+// Panes shorter than this get the one-line view
+pub const COMPACT_BELOW_HEIGHT: u16 = 5;
+
+// Bill values in cents, largest first
+pub const DENOMINATIONS: &[i64] = &[10_000, 2_000, 500, 100];
+
+// How many of `times` fall within `window` before `now`
+pub fn count_within(times: &std::collections::VecDeque<std::time::Instant>, now: std::time::Instant, window: std::time::Duration) -> usize {
+    times.iter().filter(|at| now.saturating_duration_since(**at) < window).count()
+}
+
+// Cents per time bucket, `buckets` of them evenly spanning start..end. Payments
+// outside the span land in the first or last bucket
+pub fn bin_by_time<'a>(
+    payments: impl IntoIterator<Item = &'a Payment>,
+    start: chrono::DateTime<chrono::Utc>,
+    end: chrono::DateTime<chrono::Utc>,
+    buckets: usize,
+) -> Vec<u64> {
+    let mut bins = vec![0u64; buckets];
+    if buckets == 0 {
+        return bins;
+    }
+    let span = (end - start).num_milliseconds().max(1) as i128;
+    for p in payments {
+        let offset = (p.created_at - start).num_milliseconds() as i128;
+        let idx = (offset * buckets as i128 / span).clamp(0, buckets as i128 - 1) as usize;
+        bins[idx] = bins[idx].saturating_add(p.display_cents().max(0) as u64);
+    }
+    bins
+}
+
+// Cents per `period` seconds over `secs`, rounded half away from zero. The product is
+// taken in i128 so long sessions with large totals neither overflow nor truncate
+fn rate_per(cents: i64, secs: u64, period: u64) -> i64 {
+    if secs == 0 {
+        return 0;
+    }
+    let scaled = cents as i128 * period as i128;
+    let divisor = secs as i128;
+    let rounded = (scaled.abs() + divisor / 2) / divisor;
+    (rounded * scaled.signum()).clamp(i64::MIN as i128, i64::MAX as i128) as i64
+}
+
+fn add_to_totals(totals: &mut Vec<(String, i64, usize)>, payment: &Payment) {
+    match totals.iter_mut().find(|(name, _, _)| *name == payment.provider) {
+        Some(entry) => {
+            entry.1 = entry.1.saturating_add(payment.display_cents());
+            entry.2 += 1;
+        }
+        None => totals.push((payment.provider.clone(), payment.display_cents(), 1)),
+    }
+}
+
+// Greedy split into bills, largest first; anything under the smallest bill is left out.
+// Lazy, so a huge amount costs no more than the bills actually taken
+pub fn denominate(amount_cents: i64) -> impl Iterator<Item = i64> {
+    let mut remaining = amount_cents.max(0);
+    DENOMINATIONS.iter().flat_map(move |&value| {
+        let count = remaining / value;
+        remaining %= value;
+        std::iter::repeat_n(value, count as usize)
+    })
+}
+
+// How many counted payments between sweeps of seen_ids
+const PRUNE_EVERY: usize = 100;
+
+const CONFETTI_COUNT: usize = 120;
+
+// One pass of the main loop
+pub const TICK: std::time::Duration = std::time::Duration::from_millis(50);
+
+// How long a bill stays emphasised after landing
+pub const LANDING_FLASH_TICKS: u32 = 4;
+
+const MANUAL_REFRESH_DEBOUNCE: std::time::Duration = std::time::Duration::from_secs(2);
+
+// How long a milestone toast stays up, the last stretch of it dimmed
+pub const TOAST_TICKS: u32 = 60;
+pub const TOAST_FADE_TICKS: u32 = 15;
+const MAX_TOASTS: usize = 3;
+
+// Span the header's payments-per-minute readout counts over
+pub const THROUGHPUT_WINDOW: std::time::Duration = std::time::Duration::from_secs(60);
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl App {
     pub fn new() -> Self {
@@ -80,111 +423,2381 @@ impl App {
             phase: AppPhase::Setup,
             bills: Vec::new(),
             total_cents: 0,
+            settled_cents: 0,
+            net_cents: 0,
+            displayed_total_cents: 0.0,
             session_payments: Vec::new(),
+            journal: None,
+            payment_count: 0,
+            provider_totals: Vec::new(),
             start_time: chrono::Utc::now(),
-            seen_ids: std::collections::HashSet::new(),
+            backfill_since: None,
+            compact: false,
+            started: std::time::Instant::now(),
+            elapsed_before: std::time::Duration::ZERO,
+            seen_ids: HashMap::new(),
+            seen_floor: None,
+            newest_seen: HashMap::new(),
             celebration_tick: 0,
+            waiting_tick: 0,
+            particles: Vec::new(),
             setup_cursor: 0,
             setup_currency_idx: 0,
             setup_input: String::new(),
+            reveal_input: false,
             setup_step: SetupStep::Currency,
-            provider_configs: PROVIDERS.iter().map(|name| ProviderSetupState {
-                name: name.to_string(),
-                enabled: false,
-                api_key: String::new(),
-                merchant_account: String::new(),
-            }).collect(),
+            setup_history: Vec::new(),
+            provider_configs: registry().setup_names().into_iter().map(|name| ProviderSetupState::new(name, "")).collect(),
             current_provider_idx: 0,
             error_message: None,
+            status_message: None,
             pending_bills: Vec::new(),
+            pending_peak: 0,
+            paused: false,
+            held_payments: Vec::new(),
+            last_manual_refresh: None,
+            recent_errors: std::collections::VecDeque::new(),
+            recent_arrivals: std::collections::VecDeque::new(),
+            largest_payment_cents: 0,
+            toasts: std::collections::VecDeque::new(),
+            provider_statuses: Vec::new(),
+            fx: RateTable::new("EUR", &std::collections::HashMap::new()),
+            fx_warned: std::collections::HashSet::new(),
+            tz: DisplayTz::Local,
+            show_ledger: false,
+            show_stats: false,
+            stats: stats::load_stats(),
+            edit_backup: None,
+            payment_list_scroll: ListState::default(),
+            config_load_error: None,
+            config_unsaved: false,
+            ledger_query: String::new(),
+            ledger_filter_editing: false,
+            #[cfg(feature = "metrics")]
+            metrics: crate::metrics::Metrics::default(),
+            #[cfg(unix)]
+            ipc: crate::ipc::SummaryFeed::default(),
         }
     }
 
     pub fn from_config(config: AppConfig) -> Self {
         let mut app = Self::new();
-        app.config = config.clone();
+        app.config = config;
+        app.apply_config();
+        if let Err(errors) = app.config.validate() {
+            let problems = crate::config::describe_errors(&errors);
+            tracing::warn!("config problems: {}", problems);
+            app.error_message = Some(problems);
+        }
         // Skip setup if already configured with at least one provider
-        if !config.providers.is_empty() {
+        if !app.config.providers.is_empty() {
             app.phase = AppPhase::Running;
-            app.start_time = chrono::Utc::now();
+            app.start_clock();
         }
         app
     }
 
-    pub fn add_payment(&mut self, payment: Payment) {
-        if self.seen_ids.contains(&payment.id) {
+    // Rebuild the state derived from config
+    fn apply_config(&mut self) {
+        self.provider_statuses = self.config.providers.iter().map(|p| ProviderStatus::new(&p.display_name())).collect();
+        self.fx = RateTable::new(&self.config.currency, &self.config.fx_rates);
+        let (tz, warning) = DisplayTz::from_config(self.config.timezone.as_deref());
+        self.tz = tz;
+        if let Some(warning) = warning {
+            self.push_error("Config".to_string(), warning);
+        }
+    }
+
+    // Back into setup, pre-filled from the running config. Session totals are untouched.
+    pub fn enter_edit_mode(&mut self) {
+        self.edit_backup = Some(self.config.clone());
+        self.setup_currency_idx = self
+            .currency_options()
+            .iter()
+            .position(|c| c.code == self.config.currency)
+            .unwrap_or(0);
+        // One row per known provider, plus one per extra labelled account
+        self.provider_configs = registry().setup_names().into_iter().map(|name| ProviderSetupState::new(name, "")).collect();
+        for p in &self.config.providers {
+            let label = p.label.clone().unwrap_or_default();
+            let idx = match self.provider_configs.iter().position(|s| s.name == p.provider && s.label == label) {
+                Some(idx) => idx,
+                None => {
+                    self.provider_configs.push(ProviderSetupState::new(&p.provider, &label));
+                    self.provider_configs.len() - 1
+                }
+            };
+            let row = &mut self.provider_configs[idx];
+            row.enabled = true;
+            row.api_key = p.api_key.clone();
+            row.second_credential = p.merchant_account.clone().or(p.api_secret.clone()).unwrap_or_default();
+        }
+        self.setup_cursor = 0;
+        self.setup_input.clear();
+        self.setup_step = SetupStep::Currency;
+        self.setup_history.clear();
+        self.error_message = None;
+        self.show_ledger = false;
+        self.phase = AppPhase::Setup;
+    }
+
+    // Leave edit mode without applying anything
+    pub fn cancel_edit(&mut self) {
+        if let Some(config) = self.edit_backup.take() {
+            self.config = config;
+            self.error_message = None;
+            self.phase = AppPhase::Running;
+        }
+    }
+
+    // Built-in currencies, then extra_currencies, then the user's own, each code once with
+    // the first listing winning; setup adds a "Custom…" row after these
+    pub fn currency_options(&self) -> Vec<CustomCurrency> {
+        let mut options: Vec<CustomCurrency> = Vec::new();
+        let listed = CURRENCIES
+            .iter()
+            .map(|(code, symbol)| CustomCurrency::new(code, symbol, crate::psp::minor_units(code)))
+            .chain(self.config.extra_currencies.iter().cloned())
+            .chain(self.config.custom_currencies.iter().cloned());
+        for currency in listed {
+            if !options.iter().any(|c| c.code.eq_ignore_ascii_case(&currency.code)) {
+                options.push(currency);
+            }
+        }
+        options
+    }
+
+    // Picking the currency already in use keeps its symbol and separators, which may have
+    // been set by hand for the locale
+    pub fn select_currency(&mut self, currency: &CustomCurrency) {
+        if currency.code == self.config.currency {
             return;
         }
-        self.seen_ids.insert(payment.id.clone());
-        self.total_cents += payment.amount_cents;
+        self.apply_currency(currency);
+    }
 
-        // Queue bills: one bill per currency unit
-        let units = (payment.amount_cents as f64 / 100.0).floor() as i64;
-        for _ in 0..units.min(10) {
-            self.pending_bills.push(PendingBill {
-                amount_cents: 100,
-                provider: payment.provider.clone(),
-            });
+    fn apply_currency(&mut self, currency: &CustomCurrency) {
+        self.config.currency = currency.code.clone();
+        self.config.currency_symbol = currency.symbol.clone();
+        self.config.currency_decimals = currency.decimals;
+        self.config.symbol_position = currency.symbol_position;
+        self.config.decimal_separator = currency.decimal_separator.clone();
+        self.config.thousands_separator = currency.thousands_separator.clone();
+    }
+
+    pub fn theme(&self) -> &'static Theme {
+        Theme::resolve(&self.config.theme, crate::theme::color_enabled())
+    }
+
+    // Moves through the built-in themes; the setup screen redraws in the new one right away
+    pub fn step_theme(&mut self, forward: bool) {
+        let themes = crate::theme::THEMES;
+        let current = themes.iter().position(|t| t.name == Theme::named(&self.config.theme).name).unwrap_or(0);
+        let next = if forward {
+            (current + 1).min(themes.len() - 1)
+        } else {
+            current.saturating_sub(1)
+        };
+        self.config.theme = themes[next].name.to_string();
+    }
+
+    // Parses "CODE [SYMBOL] [DECIMALS] [prefix|suffix]", e.g. "NOK kr 2 suffix", remembers it and selects it
+    pub fn add_custom_currency(&mut self, input: &str) -> Result<(), String> {
+        let mut parts = input.split_whitespace();
+        let code = parts.next().unwrap_or("").to_uppercase();
+        if code.len() != 3 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err("Currency code must be 3 letters, e.g. NOK".to_string());
+        }
+        let symbol = parts.next().unwrap_or(&code).to_string();
+        let decimals = match parts.next() {
+            Some(d) => d.parse::<u32>().ok().filter(|d| *d <= 4).ok_or("Decimals must be 0-4")?,
+            None => 2,
+        };
+        let symbol_position = match parts.next() {
+            Some("suffix") => SymbolPosition::Suffix,
+            Some("prefix") | None => SymbolPosition::Prefix,
+            Some(_) => return Err("Symbol position must be prefix or suffix".to_string()),
+        };
+        if parts.next().is_some() {
+            return Err("Expected: CODE [SYMBOL] [DECIMALS] [prefix|suffix]".to_string());
+        }
+        let currency = CustomCurrency { symbol_position, ..CustomCurrency::new(&code, &symbol, decimals) };
+        let listed = CURRENCIES.iter().any(|(c, _)| *c == currency.code)
+            || self.config.extra_currencies.iter().any(|c| c.code.eq_ignore_ascii_case(&currency.code));
+        if listed {
+            return Err(format!("{} is already in the list", currency.code));
+        }
+        self.config.custom_currencies.retain(|c| c.code != currency.code);
+        self.config.custom_currencies.push(currency.clone());
+        self.apply_currency(&currency);
+        Ok(())
+    }
+
+    // Adds another, labelled account of the provider under the cursor
+    pub fn add_provider_instance(&mut self, label: &str) {
+        let name = self.provider_configs[self.setup_cursor].name.clone();
+        let mut row = ProviderSetupState::new(&name, label.trim());
+        row.enabled = true;
+        self.setup_cursor += 1;
+        self.provider_configs.insert(self.setup_cursor, row);
+    }
+
+    // Only a true first run starts on the welcome step; a broken config goes
+    // straight back to setup
+    pub fn welcome_first_run(&mut self) {
+        if self.phase == AppPhase::Setup && self.config_load_error.is_none() && !self.config.seen_welcome {
+            self.setup_step = SetupStep::Welcome;
+        }
+    }
+
+    pub fn goto_step(&mut self, step: SetupStep) {
+        self.goto_provider_step(step, self.current_provider_idx);
+    }
+
+    pub fn goto_provider_step(&mut self, step: SetupStep, provider_idx: usize) {
+        self.setup_history.push((self.setup_step.clone(), self.current_provider_idx));
+        self.setup_step = step;
+        self.current_provider_idx = provider_idx;
+        self.error_message = None;
+        self.load_setup_input();
+    }
+
+    // False when already at the first step
+    pub fn step_back(&mut self) -> bool {
+        let Some((step, provider_idx)) = self.setup_history.pop() else {
+            return false;
+        };
+        self.setup_step = step;
+        self.current_provider_idx = provider_idx;
+        self.error_message = None;
+        self.load_setup_input();
+        true
+    }
+
+    // Credential prompts start from what was already entered, so going back keeps it
+    fn load_setup_input(&mut self) {
+        self.reveal_input = false;
+        let provider = self.provider_configs.get(self.current_provider_idx);
+        self.setup_input = match (&self.setup_step, provider) {
+            (SetupStep::ProviderApiKey, Some(p)) => p.api_key.clone(),
+            (SetupStep::ProviderSecondCredential, Some(p)) => p.second_credential.clone(),
+            _ => String::new(),
+        };
+    }
+
+    // Setup confirmed: start a fresh session, or carry on the current one after an edit
+    pub fn finish_setup(&mut self) {
+        self.setup_history.clear();
+        if self.edit_backup.take().is_none() {
+            self.start_clock();
+        }
+        self.apply_config();
+        self.phase = AppPhase::Running;
+    }
+
+    // Setup's last step: the session starts even when the config can't be
+    // written, with the error shown and the save retried on quit
+    pub fn complete_setup(&mut self, store: &dyn ConfigStore, secrets: &dyn SecretStore) {
+        self.finish_setup();
+        if let Err(e) = self.persist_config(store, secrets) {
+            self.error_message = Some(format!("Saving config failed, will retry on quit: {:#}", e));
+        }
+    }
+
+    // Writes the config from setup. A failure leaves it marked unsaved (and a broken
+    // file un-backed-up) so the next attempt does the whole job again
+    pub fn persist_config(&mut self, store: &dyn ConfigStore, secrets: &dyn SecretStore) -> anyhow::Result<()> {
+        self.config_unsaved = true;
+        if self.config_load_error.is_some() {
+            crate::config::backup_config()?;
+            self.config_load_error = None;
+        }
+        crate::config::save_config_to(store, &self.config, secrets)
+            .inspect_err(|e| tracing::error!("saving config failed: {:#}", e))?;
+        self.config_unsaved = false;
+        Ok(())
+    }
+
+    // Returns false when the payment was already counted, or isn't counted at all
+    pub fn add_payment(&mut self, mut payment: Payment) -> bool {
+        let pruned = self.seen_floor.is_some_and(|floor| payment.created_at < floor);
+        if pruned && self.newest_seen.contains_key(&payment.provider) {
+            return false;
+        }
+        if self.seen_ids.insert(payment.dedup_key(), payment.created_at).is_some() {
+            return false;
+        }
+        let newest = self.newest_seen.entry(payment.provider.clone()).or_insert(payment.created_at);
+        *newest = (*newest).max(payment.created_at);
+        // Remembered as seen, so unmuting doesn't count it late
+        if self.provider_statuses.iter().any(|s| s.muted && s.name == payment.provider) {
+            return false;
+        }
+        if !payment.currency.eq_ignore_ascii_case(&self.config.currency) {
+            match self.config.mismatched_currency {
+                MismatchedCurrency::Skip => {
+                    self.skip_payment(&payment);
+                    return false;
+                }
+                MismatchedCurrency::Count => {}
+                MismatchedCurrency::Convert => self.convert_currency(&mut payment),
+            }
+        }
+        let before = self.total_cents;
+        let progress_before = self.progress();
+        self.recent_arrivals.push_back(std::time::Instant::now());
+        // Totals pin at the i64 limits rather than wrapping
+        self.total_cents = self.total_cents.saturating_add(payment.display_cents());
+        self.net_cents = self.net_cents.saturating_add(payment.display_cents() - self.fee_cents(&payment));
+        if payment.settled {
+            self.settled_cents = self.settled_cents.saturating_add(payment.display_cents());
         }
 
+        // Crossing the session goal celebrates straight away
+        if let Some(goal) = self.config.goal_cents.filter(|g| *g > 0) {
+            if self.config.celebration_enabled && self.phase == AppPhase::Running && before < goal && self.total_cents >= goal {
+                self.start_celebration();
+            }
+        }
+
+        if let Some(window) = self.config.combo_window_secs.filter(|w| *w > 0) {
+            self.queue_combo_bill(&payment, window);
+        } else {
+            // Queue bills largest denomination first, up to the per-payment cap
+            for amount_cents in denominate(payment.display_cents()).take(self.config.max_bills_per_payment) {
+                self.pending_bills.push(PendingBill {
+                    amount_cents,
+                    provider: payment.provider.clone(),
+                    count: 1,
+                    window: None,
+                    unsettled: !payment.settled,
+                });
+            }
+        }
+        self.pending_peak = self.pending_peak.max(self.pending_bills.len());
+
+        self.stats.record(payment.display_cents(), self.tz.date(chrono::Utc::now()));
+        #[cfg(feature = "metrics")]
+        self.metrics.record_payment(&payment.provider, self.total_cents);
+        self.payment_count += 1;
+        self.largest_payment_cents = self.largest_payment_cents.max(payment.display_cents());
+        for milestone in self.milestones_crossed(progress_before) {
+            self.show_toast(milestone);
+        }
+        add_to_totals(&mut self.provider_totals, &payment);
+        if let Some(Err(e)) = self.journal.as_mut().map(|j| j.append(&payment)) {
+            tracing::warn!("payment journal: {:#}", e);
+        }
         self.session_payments.push(payment);
+        let excess = self.session_payments.len().saturating_sub(self.config.max_session_payments.max(1));
+        self.session_payments.drain(..excess);
+        if self.payment_count.is_multiple_of(PRUNE_EVERY) {
+            self.prune_seen_ids();
+        }
+        // New rows land on top; keep the selected payment under the cursor
+        let shown = self.session_payments.last().is_some_and(|p| ledger::matches(p, &self.ledger_query));
+        if let Some(selected) = self.payment_list_scroll.selected().filter(|i| *i > 0 && shown) {
+            self.payment_list_scroll.select(Some(selected + 1));
+        }
+        self.publish_summary();
+        true
     }
 
-    pub fn spawn_next_bill(&mut self, terminal_height: u16) {
-        if self.pending_bills.is_empty() {
-            return;
+    fn progress(&self) -> Progress {
+        Progress {
+            total_cents: self.total_cents,
+            count: self.payment_count,
+            largest_cents: self.largest_payment_cents,
         }
+    }
 
-        let pb = self.pending_bills.remove(0);
-        let stack_y = self.calculate_stack_position(terminal_height);
+    // Milestones the payment just counted crossed, none with milestones off
+    pub fn milestones_crossed(&self, before: Progress) -> Vec<Milestone> {
+        if !self.config.milestones {
+            return Vec::new();
+        }
+        milestones::crossed(before, self.progress(), self.config.milestone_thresholds())
+    }
 
-        self.bills.push(BillAnimation {
-            amount_cents: pb.amount_cents,
-            y_pos: 0.0,
-            target_y: stack_y as f64,
-            settled: false,
-            age_ticks: 0,
-            provider: pb.provider,
+    // Only the newest few stay up, so a backfill doesn't bury the screen
+    fn show_toast(&mut self, milestone: Milestone) {
+        self.toasts.push_back(Toast {
+            milestone,
+            ticks_left: TOAST_TICKS,
         });
+        while self.toasts.len() > MAX_TOASTS {
+            self.toasts.pop_front();
+        }
     }
 
-    fn calculate_stack_position(&self, terminal_height: u16) -> u16 {
-        let bill_height = 3u16;
-        let floor = terminal_height.saturating_sub(4);
-        let settled_count = self.bills.iter().filter(|b| b.settled).count() as u16;
-        floor.saturating_sub(settled_count * bill_height)
+    // Folds the payment into a queued bill from the same provider and window, if one
+    // hasn't started falling yet; only the bills combine, totals count each payment
+    fn queue_combo_bill(&mut self, payment: &Payment, window_secs: u32) {
+        let window = payment.created_at.timestamp().div_euclid(window_secs as i64);
+        let queued = self
+            .pending_bills
+            .iter_mut()
+            .find(|b| b.window == Some(window) && b.provider == payment.provider && b.unsettled != payment.settled);
+        match queued {
+            Some(bill) => {
+                bill.amount_cents = bill.amount_cents.saturating_add(payment.display_cents());
+                bill.count += 1;
+            }
+            None => self.pending_bills.push(PendingBill {
+                amount_cents: payment.display_cents(),
+                provider: payment.provider.clone(),
+                count: 1,
+                window: Some(window),
+                unsettled: !payment.settled,
+            }),
+        }
     }
 
-    pub fn tick_animations(&mut self) {
+    // Refreshes what the IPC socket answers with, the summary and the health check. Called on
+    // every payment and poll, so the duration is never more than a poll stale
+    pub fn publish_summary(&self) {
+        #[cfg(unix)]
+        {
+            self.ipc.publish(&self.session_summary(chrono::Utc::now()));
+            self.ipc.publish_health(self.health_check());
+        }
+    }
+
+    pub fn health_check(&self) -> crate::health::HealthCheck {
+        crate::health::HealthCheck::new(
+            &self.provider_statuses,
+            |name| self.config.stale_after(name),
+            self.config.quiet_hours.map(|window| (window, self.tz)),
+        )
+    }
+
+    pub fn toggle_stats(&mut self) {
+        self.show_stats = !self.show_stats;
+        self.show_ledger = false;
+    }
+
+    // Demo sessions never touch the stats file
+    pub fn save_stats(&self) -> anyhow::Result<()> {
+        if self.config.ephemeral {
+            return Ok(());
+        }
+        stats::save_stats(&self.stats)
+    }
+
+    // Keeps every payment of the session on disk from here on, past max_session_payments
+    pub fn keep_journal(&mut self, journal: Journal) {
+        self.journal = Some(journal);
+    }
+
+    // The whole session for an export: the journal when there is one, else what's in memory
+    pub fn all_session_payments(&self) -> Vec<Payment> {
+        match self.journal.as_ref().map(Journal::load) {
+            Some(Ok(payments)) if payments.len() >= self.session_payments.len() => payments,
+            Some(Err(e)) => {
+                tracing::warn!("payment journal: {:#}", e);
+                self.session_payments.clone()
+            }
+            _ => self.session_payments.clone(),
+        }
+    }
+
+    // Quitting from first-run setup has no session to keep, and saving one would wipe the
+    // session saved at the last quit
+    pub fn has_session(&self) -> bool {
+        self.phase != AppPhase::Setup || self.edit_backup.is_some()
+    }
+
+    pub fn save_state(&self) -> anyhow::Result<()> {
+        if self.config.ephemeral {
+            return Ok(());
+        }
+        state::save_state(&self.session_state())
+    }
+
+    // What save_state writes and restore_session reads back
+    pub fn session_state(&self) -> SessionState {
+        SessionState {
+            start_time: self.start_time,
+            active_secs: Some(self.session_elapsed().as_secs()),
+            total_cents: self.total_cents,
+            net_cents: self.net_cents,
+            settled_cents: Some(self.settled_cents),
+            seen_ids: self.seen_ids.clone(),
+            seen_floor: self.seen_floor,
+            newest_seen: self.newest_seen.clone(),
+            session_payments: self.session_payments.clone(),
+            payment_count: self.payment_count,
+            provider_totals: self.provider_totals.clone(),
+            // A full screen mid-celebration is cleared on the way back anyway
+            bills: if self.phase == AppPhase::Running { self.bills.clone() } else { Vec::new() },
+            pending_bills: self.pending_bills.clone(),
+        }
+    }
+
+    // "Watching: Adyen, Mock every 10s since 14:02", or each provider's own
+    // interval when they differ
+    pub fn watch_summary(&self) -> String {
+        let names: Vec<String> = self.config.providers.iter().map(|p| p.display_name()).collect();
+        let intervals: Vec<u64> = names.iter().map(|n| self.config.poll_interval_for(n).as_secs()).collect();
+        let watched = if intervals.windows(2).all(|w| w[0] == w[1]) {
+            format!("{} every {}s", names.join(", "), intervals.first().copied().unwrap_or(self.config.poll_interval_secs))
+        } else {
+            names.iter().zip(&intervals).map(|(n, secs)| format!("{} every {}s", n, secs)).collect::<Vec<_>>().join(", ")
+        };
+        let since = names.iter().map(|n| self.poll_since(n)).min().unwrap_or(self.start_time);
+        let adaptive = match self.config.adaptive_bounds() {
+            Some((floor, ceiling)) => format!(" (adapting {}–{}s)", floor.as_secs(), ceiling.as_secs()),
+            None => String::new(),
+        };
+        format!("Watching: {}{} since {}", watched, adaptive, self.tz.format(since, "%H:%M"))
+    }
+
+    // Where polling a provider should start: just before the newest payment
+    // counted from it, and never before the session began
+    pub fn poll_since(&self, provider: &str) -> chrono::DateTime<chrono::Utc> {
+        let from = self.backfill_since.map_or(self.start_time, |since| since.min(self.start_time));
+        match self.newest_seen.get(provider) {
+            Some(newest) => from.max(*newest - self.config.poll_overlap()),
+            None => from,
+        }
+    }
+
+    // Builds the configured providers and polls them from where this session left off.
+    // Config problems land in the error list; None when no provider could be built
+    pub fn start_polling(
+        &mut self,
+        tx: tokio::sync::mpsc::UnboundedSender<PollEvent>,
+        refresh: std::sync::Arc<tokio::sync::Notify>,
+    ) -> anyhow::Result<Option<tokio::task::JoinHandle<()>>> {
+        let (providers, problems) = build_providers(&self.config)?;
+        for problem in problems {
+            tracing::warn!("provider not started: {}", problem);
+            self.push_error("Config".to_string(), problem);
+        }
+        if providers.is_empty() {
+            self.error_message = Some("No providers could be started — press c to fix the setup".to_string());
+            return Ok(None);
+        }
+        self.status_message = Some(self.watch_summary());
+        let windows = providers.iter().map(|p| self.poll_since(p.name())).collect();
+        let intervals = providers.iter().map(|p| self.config.poll_interval_for(p.name())).collect();
+        let settings = PollSettings {
+            adaptive: self.config.adaptive_bounds(),
+            overlap: self.config.poll_overlap(),
+            quiet_hours: self.config.quiet_hours,
+            tz: self.tz,
+        };
+        Ok(Some(crate::poll::spawn_poller(providers, windows, intervals, settings, tx, refresh)))
+    }
+
+    // No provider is polled from before the oldest of their windows, so IDs
+    // older than that can't come back and no longer need remembering
+    fn prune_seen_ids(&mut self) {
+        let Some(floor) = self.newest_seen.values().min().map(|t| *t - self.config.poll_overlap()) else {
+            return;
+        };
+        self.seen_ids.retain(|_, created_at| *created_at >= floor);
+        self.seen_floor = self.seen_floor.max(Some(floor));
+    }
+
+    // Carry on the session saved at the last quit
+    pub fn restore_session(&mut self, saved: SessionState) {
+        self.start_time = saved.start_time;
+        self.started = std::time::Instant::now();
+        // Time spent closed doesn't count towards the clock or the rates
+        self.elapsed_before = match saved.active_secs {
+            Some(secs) => std::time::Duration::from_secs(secs),
+            None => (chrono::Utc::now() - saved.start_time).to_std().unwrap_or_default(),
+        };
+        self.total_cents = saved.total_cents;
+        self.displayed_total_cents = saved.total_cents as f64;
+        self.net_cents = saved.net_cents;
+        self.settled_cents = saved.settled_cents.unwrap_or(saved.total_cents);
+        self.seen_ids = saved.seen_ids;
+        self.seen_floor = saved.seen_floor;
+        self.newest_seen = saved.newest_seen;
+        self.session_payments = saved.session_payments;
+        // Older state files carry only the payment list
+        self.payment_count = saved.payment_count.max(self.session_payments.len());
+        self.provider_totals = saved.provider_totals;
+        self.largest_payment_cents = self.session_payments.iter().map(|p| p.display_cents()).max().unwrap_or(0);
+        if self.provider_totals.is_empty() {
+            for p in &self.session_payments {
+                add_to_totals(&mut self.provider_totals, p);
+            }
+        }
+        // Bills saved mid-fall come back landed; reflow_bills puts them on this terminal's floor
+        self.bills = saved.bills;
         for bill in &mut self.bills {
-            if !bill.settled {
-                let distance = bill.target_y - bill.y_pos;
-                if distance.abs() < 1.0 {
-                    bill.y_pos = bill.target_y;
-                    bill.settled = true;
-                } else {
-                    bill.y_pos += distance * 0.3;
-                }
+            bill.settled = true;
+            bill.y_pos = bill.target_y;
+            bill.just_settled_ticks = 0;
+        }
+        self.pending_bills = saved.pending_bills;
+        #[cfg(feature = "metrics")]
+        self.metrics.set_total(self.total_cents);
+        self.publish_summary();
+    }
+
+    pub fn toggle_ledger(&mut self) {
+        self.show_ledger = !self.show_ledger;
+        self.show_stats = false;
+        self.ledger_filter_editing = false;
+        if self.show_ledger {
+            self.payment_list_scroll.select(Some(0));
+            *self.payment_list_scroll.offset_mut() = 0;
+        }
+    }
+
+    // Changes the filter and jumps back to the newest match
+    pub fn edit_ledger_query(&mut self, edit: impl FnOnce(&mut String)) {
+        edit(&mut self.ledger_query);
+        self.payment_list_scroll.select(Some(0));
+        *self.payment_list_scroll.offset_mut() = 0;
+    }
+
+    // Moves the ledger selection, clamped to the list
+    pub fn scroll_ledger(&mut self, delta: isize) {
+        let shown = ledger::filter_payments(&self.session_payments, &self.ledger_query).len();
+        let Some(last) = shown.checked_sub(1) else {
+            self.payment_list_scroll.select(None);
+            return;
+        };
+        let current = self.payment_list_scroll.selected().unwrap_or(0).min(last);
+        let next = current.saturating_add_signed(delta).min(last);
+        self.payment_list_scroll.select(Some(next));
+    }
+
+    // Returns the payments held back while paused when resuming
+    pub fn toggle_pause(&mut self) -> Vec<Payment> {
+        self.paused = !self.paused;
+        if self.paused {
+            Vec::new()
+        } else {
+            std::mem::take(&mut self.held_payments)
+        }
+    }
+
+    fn provider_status_mut(&mut self, provider: &str) -> &mut ProviderStatus {
+        let idx = match self.provider_statuses.iter().position(|s| s.name == provider) {
+            Some(idx) => idx,
+            None => {
+                self.provider_statuses.push(ProviderStatus::new(provider));
+                self.provider_statuses.len() - 1
             }
-            bill.age_ticks += 1;
+        };
+        &mut self.provider_statuses[idx]
+    }
+
+    // Mutes or unmutes the nth provider in the status bar
+    pub fn toggle_provider(&mut self, index: usize) {
+        let Some(status) = self.provider_statuses.get_mut(index) else {
+            return;
+        };
+        status.muted = !status.muted;
+        let verb = if status.muted { "Muted" } else { "Unmuted" };
+        self.status_message = Some(format!("{} {}", verb, status.name));
+        self.publish_summary();
+    }
+
+    // Most recent successful poll of any provider
+    pub fn last_checked(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.provider_statuses.iter().filter_map(|s| s.last_success).max()
+    }
+
+    pub fn record_poll_success(&mut self, provider: &str) {
+        let status = self.provider_status_mut(provider);
+        status.last_success = Some(chrono::Utc::now());
+        status.last_error = None;
+        status.consecutive_failures = 0;
+        self.publish_summary();
+    }
+
+    pub fn record_rate_limit(&mut self, provider: &str, limit: RateLimit) {
+        self.provider_status_mut(provider).rate_limit = Some(limit);
+    }
+
+    pub fn record_poll_failure(&mut self, provider: &str, message: &str) {
+        let status = self.provider_status_mut(provider);
+        status.last_error = Some(message.to_string());
+        status.consecutive_failures += 1;
+        let just_gave_up = status.consecutive_failures == FAILING_AFTER;
+        if just_gave_up && self.all_providers_failing() {
+            self.error_message = Some("Every provider is failing — press c to fix the setup".to_string());
         }
+        self.publish_summary();
     }
 
-    pub fn is_screen_full(&self, terminal_height: u16) -> bool {
-        let bill_height = 3u16;
-        let settled = self.bills.iter().filter(|b| b.settled).count() as u16;
-        settled * bill_height >= terminal_height.saturating_sub(6)
+    // Muted providers don't count either way
+    pub fn all_providers_failing(&self) -> bool {
+        let mut active = self.provider_statuses.iter().filter(|s| !s.muted).peekable();
+        active.peek().is_some() && active.all(|s| s.consecutive_failures >= FAILING_AFTER)
     }
 
-    pub fn reset_session(&mut self) {
-        self.bills.clear();
-        self.pending_bills.clear();
-        self.celebration_tick = 0;
-        self.phase = AppPhase::Running;
-        // Keep total and seen_ids so we don't recount
+    // Setup asks for a provider's key when it has none, and when editing, also when the
+    // running provider hasn't been getting through with the one it has
+    pub fn needs_credentials(&self, idx: usize) -> bool {
+        let p = &self.provider_configs[idx];
+        if !p.enabled || p.name == "Mock" {
+            return false;
+        }
+        let name = p.display_name();
+        let failing = self
+            .provider_statuses
+            .iter()
+            .find(|s| s.name == name)
+            .is_none_or(|s| s.consecutive_failures > 0 || s.last_success.is_none());
+        p.api_key.is_empty() || (self.edit_backup.is_some() && failing)
+    }
+
+    pub fn push_error(&mut self, provider: String, message: String) {
+        #[cfg(feature = "metrics")]
+        self.metrics.record_error(&provider);
+        if self.recent_errors.len() == MAX_RECENT_ERRORS {
+            self.recent_errors.pop_front();
+        }
+        self.recent_errors.push_back(ProviderError {
+            provider,
+            message,
+            at: chrono::Utc::now(),
+        });
+    }
+
+    // Debounced manual refresh: returns true when a fetch should be triggered
+    pub fn request_refresh(&mut self) -> bool {
+        let now = std::time::Instant::now();
+        if let Some(last) = self.last_manual_refresh {
+            if now.duration_since(last) < MANUAL_REFRESH_DEBOUNCE {
+                return false;
+            }
+        }
+        self.last_manual_refresh = Some(now);
+        true
     }
 
-    pub fn session_duration(&self) -> chrono::Duration {
-        chrono::Utc::now() - self.start_time
+    fn fee_cents(&self, payment: &Payment) -> i64 {
+        self.config
+            .providers
+            .iter()
+            .find(|p| p.display_name() == payment.provider)
+            .and_then(|p| p.fee)
+            .map_or(0, |fee| fee.fee_cents(payment.display_cents()))
+    }
+
+    pub fn has_fees(&self) -> bool {
+        self.config.providers.iter().any(|p| p.fee.is_some())
+    }
+
+    fn skip_payment(&mut self, payment: &Payment) {
+        tracing::info!(provider = %payment.provider, id = %payment.id, "skipped {} payment", payment.currency);
+        if self.fx_warned.insert(payment.currency.to_uppercase()) {
+            self.push_error(
+                payment.provider.clone(),
+                format!("skipping {} payments, the session is in {}", payment.currency, self.config.currency),
+            );
+        }
+    }
+
+    // Foreign payments are converted into the display currency; without a
+    // rate they count at face value and we warn once per currency
+    fn convert_currency(&mut self, payment: &mut Payment) {
+        match self.fx.convert(payment.amount_cents, &payment.currency, &self.config.currency) {
+            Some(converted) => payment.converted_cents = Some(converted),
+            None => {
+                if self.fx_warned.insert(payment.currency.to_uppercase()) {
+                    self.push_error(
+                        payment.provider.clone(),
+                        format!("no FX rate for {}, counting at face value", payment.currency),
+                    );
+                }
+            }
+        }
+    }
+
+    pub fn spawn_next_bill(&mut self, terminal_height: u16) {
+        if self.paused || self.pending_bills.is_empty() {
+            return;
+        }
+
+        let pb = self.pending_bills.remove(0);
+        if self.pending_bills.is_empty() {
+            self.pending_peak = 0;
+        }
+        let stack_y = self.calculate_stack_position(terminal_height);
+
+        self.bills.push(BillAnimation {
+            amount_cents: pb.amount_cents,
+            y_pos: 0.0,
+            target_y: stack_y as f64,
+            settled: false,
+            age_ticks: 0,
+            provider: pb.provider,
+            count: pb.count,
+            unsettled: pb.unsettled,
+            just_settled_ticks: 0,
+        });
+    }
+
+    // Bills released on this spawn tick
+    pub fn bills_per_spawn(&self) -> usize {
+        let pending = self.pending_bills.len();
+        let catch_up = if pending > CATCH_UP_THRESHOLD {
+            (1 + pending / CATCH_UP_THRESHOLD).min(MAX_CATCH_UP)
+        } else {
+            1
+        };
+        match self.config.spawn_style {
+            SpawnStyle::Burst if pending > BURST_THRESHOLD => (1 + pending / BURST_THRESHOLD).min(MAX_BURST).max(catch_up),
+            _ => catch_up,
+        }
+    }
+
+    // Bills still falling hold their slot too, so several in flight don't share one
+    fn calculate_stack_position(&self, terminal_height: u16) -> u16 {
+        let bill_height = 3u16;
+        let floor = terminal_height.saturating_sub(4);
+        floor.saturating_sub(self.bills.len() as u16 * bill_height)
+    }
+
+    // Drops the oldest bills until the stack fits, e.g. one restored onto a smaller terminal
+    pub fn drop_overflowing_bills(&mut self, terminal_height: u16) {
+        while !self.bills.is_empty() && self.is_screen_full(terminal_height) {
+            self.bills.remove(0);
+        }
+    }
+
+    // Re-derive every bill's target from its stack index after a resize
+    pub fn reflow_bills(&mut self, terminal_height: u16) {
+        let bill_height = 3u16;
+        let floor = terminal_height.saturating_sub(4);
+        for (i, bill) in self.bills.iter_mut().enumerate() {
+            bill.target_y = floor.saturating_sub(i as u16 * bill_height) as f64;
+            if bill.settled {
+                bill.y_pos = bill.target_y;
+            }
+        }
+    }
+
+    pub fn tick_animations(&mut self) {
+        let now = std::time::Instant::now();
+        while self.recent_arrivals.front().is_some_and(|at| now.duration_since(*at) >= THROUGHPUT_WINDOW) {
+            self.recent_arrivals.pop_front();
+        }
+        for toast in &mut self.toasts {
+            toast.ticks_left = toast.ticks_left.saturating_sub(1);
+        }
+        self.toasts.retain(|t| t.ticks_left > 0);
+        if self.paused {
+            return;
+        }
+        let easing = self.config.easing();
+        for bill in &mut self.bills {
+            if !bill.settled {
+                let distance = bill.target_y - bill.y_pos;
+                if distance.abs() < 1.0 {
+                    bill.y_pos = bill.target_y;
+                    bill.settled = true;
+                    bill.just_settled_ticks = LANDING_FLASH_TICKS;
+                } else {
+                    bill.y_pos += distance * easing;
+                }
+            } else {
+                bill.just_settled_ticks = bill.just_settled_ticks.saturating_sub(1);
+            }
+            bill.age_ticks += 1;
+        }
+
+        let distance = self.total_cents as f64 - self.displayed_total_cents;
+        if distance.abs() < 1.0 {
+            self.displayed_total_cents = self.total_cents as f64;
+        } else {
+            self.displayed_total_cents += distance * 0.3;
+        }
+    }
+
+    // Terminal rows left for bills once the goal gauge and big total take their share
+    pub fn stack_height(&self, terminal_height: u16) -> u16 {
+        let mut extra = 0;
+        if self.config.goal_cents.is_some_and(|g| g > 0) {
+            extra += 3;
+        }
+        if self.config.big_total {
+            extra += crate::bigtext::BIG_ROWS as u16 - 1;
+        }
+        terminal_height.saturating_sub(extra)
+    }
+
+    // Asked for, or forced by a pane too short for anything else
+    pub fn is_compact(&self, terminal_height: u16) -> bool {
+        self.compact || terminal_height < COMPACT_BELOW_HEIGHT
+    }
+
+    pub fn is_screen_full(&self, terminal_height: u16) -> bool {
+        let bill_height = 3u16;
+        let settled = self.bills.iter().filter(|b| b.settled).count() as u32;
+        let area = terminal_height.saturating_sub(6) as u32;
+        settled * bill_height as u32 * 100 >= area * self.config.fill_threshold_pct() as u32
+    }
+
+    pub fn on_full(&self) -> OnFull {
+        match self.config.on_full {
+            OnFull::Celebrate if !self.config.celebration_enabled => OnFull::Recycle,
+            on_full => on_full,
+        }
+    }
+
+    // Celebrates, recycles or just stops spawning once the stack reaches the top
+    pub fn handle_full_screen(&mut self, stack_height: u16) {
+        if !self.is_screen_full(stack_height) {
+            return;
+        }
+        match self.on_full() {
+            OnFull::Celebrate if self.pending_bills.is_empty() => self.start_celebration(),
+            OnFull::Recycle => self.recycle_oldest_bills(1, stack_height),
+            _ => {}
+        }
+    }
+
+    pub fn drop_pending_bills(&mut self) {
+        self.pending_bills.clear();
+        self.pending_peak = 0;
+    }
+
+    // Drops up to n of the oldest settled bills and lets the rest slide down; totals are untouched
+    pub fn recycle_oldest_bills(&mut self, n: usize, terminal_height: u16) {
+        for _ in 0..n {
+            let Some(oldest) = self.bills.iter().position(|b| b.settled) else {
+                break;
+            };
+            self.bills.remove(oldest);
+        }
+        self.reflow_bills(terminal_height);
+    }
+
+    // None while the celebration waits for a keypress
+    pub fn celebration_ticks_left(&self) -> Option<u32> {
+        self.config
+            .celebration_duration_ticks
+            .map(|limit| limit.saturating_sub(self.celebration_tick))
+    }
+
+    // Once a frame: the celebration timer while celebrating, and the waiting
+    // spinner while there are no bills to watch
+    pub fn tick_timers(&mut self) {
+        if self.phase == AppPhase::Celebration {
+            self.tick_celebration();
+        }
+        if self.phase == AppPhase::Running && self.bills.is_empty() {
+            self.waiting_tick = self.waiting_tick.wrapping_add(1);
+        }
+    }
+
+    // Advances the celebration by one tick, resetting once its time is up
+    pub fn tick_celebration(&mut self) {
+        self.celebration_tick = self.celebration_tick.saturating_add(1);
+        particles::advance_particles(&mut self.particles, 1.0);
+        if self.celebration_ticks_left() == Some(0) {
+            self.reset_session();
+        }
+    }
+
+    pub fn start_celebration(&mut self) {
+        self.phase = AppPhase::Celebration;
+        self.celebration_tick = 0;
+        self.particles = particles::spawn_confetti(CONFETTI_COUNT, self.theme().celebration);
+    }
+
+    // Shows the celebration on demand, with the session as it stands
+    pub fn celebrate_now(&mut self) {
+        if !self.config.celebration_enabled {
+            self.status_message = Some("Celebrations are turned off".to_string());
+            return;
+        }
+        self.start_celebration();
+    }
+
+    pub fn reset_session(&mut self) {
+        self.bills.clear();
+        self.drop_pending_bills();
+        self.particles.clear();
+        self.celebration_tick = 0;
+        self.phase = AppPhase::Running;
+        // seen_ids always stays, so the next poll's overlap isn't counted into the new round
+        if self.config.reset_total_on_celebration {
+            self.start_round();
+        }
+    }
+
+    // Zeroes the session for a new round. All-time stats were recorded per payment and keep them
+    fn start_round(&mut self) {
+        self.total_cents = 0;
+        self.displayed_total_cents = 0.0;
+        self.net_cents = 0;
+        self.settled_cents = 0;
+        self.payment_count = 0;
+        self.session_payments.clear();
+        if let Some(journal) = self.journal.as_mut() {
+            journal.restart();
+        }
+        self.provider_totals.clear();
+        self.recent_arrivals.clear();
+        self.largest_payment_cents = 0;
+        self.start_clock();
+        #[cfg(feature = "metrics")]
+        self.metrics.set_total(0);
+        self.publish_summary();
+    }
+
+    // Payments counted in the last THROUGHPUT_WINDOW
+    pub fn payments_per_minute(&self) -> usize {
+        count_within(&self.recent_arrivals, std::time::Instant::now(), THROUGHPUT_WINDOW)
+    }
+
+    // The session's n largest payments, the most recent first among equals
+    pub fn top_payments(&self, n: usize) -> Vec<&Payment> {
+        let mut top: Vec<&Payment> = self.session_payments.iter().collect();
+        top.sort_by(|a, b| b.display_cents().cmp(&a.display_cents()).then_with(|| b.created_at.cmp(&a.created_at)));
+        top.truncate(n);
+        top
+    }
+
+    // Each contributing provider's takings over the session so far, in breakdown order
+    pub fn provider_timelines(&self, buckets: usize) -> Vec<(String, Vec<u64>)> {
+        let now = chrono::Utc::now();
+        self.session_breakdown()
+            .into_iter()
+            .map(|(name, _, _)| {
+                let payments = self.session_payments.iter().filter(|p| p.provider == name);
+                let bins = bin_by_time(payments, self.start_time, now, buckets);
+                (name, bins)
+            })
+            .collect()
+    }
+
+    // Per-provider (name, total cents, payment count) for this session, largest first
+    pub fn session_breakdown(&self) -> Vec<(String, i64, usize)> {
+        let mut breakdown = self.provider_totals.clone();
+        breakdown.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        breakdown
+    }
+
+    // The end time is passed in since a resumed session's start is wall-clock but its duration isn't
+    pub fn session_summary(&self, ended_at: chrono::DateTime<chrono::Utc>) -> SessionSummary {
+        SessionSummary {
+            currency: self.config.currency.clone(),
+            total_cents: self.total_cents,
+            net_cents: self.net_cents,
+            payment_count: self.payment_count,
+            duration_secs: self.session_elapsed().as_secs(),
+            started_at: self.start_time,
+            ended_at,
+            providers: self
+                .session_breakdown()
+                .into_iter()
+                .map(|(provider, total_cents, payment_count)| ProviderSummary { provider, total_cents, payment_count })
+                .collect(),
+        }
+    }
+
+    // Zero until a full second has passed, so a brand new session can't divide by zero
+    pub fn rate_per_hour(&self) -> i64 {
+        rate_per(self.total_cents, self.session_elapsed().as_secs(), 3600)
+    }
+
+    pub fn rate_per_minute(&self) -> i64 {
+        rate_per(self.total_cents, self.session_elapsed().as_secs(), 60)
+    }
+
+    // Time left to the goal at the session's rate so far
+    pub fn eta_to_goal(&self) -> Option<chrono::Duration> {
+        let goal = self.config.goal_cents.filter(|g| *g > 0)?;
+        let rate = self.rate_per_hour();
+        if rate <= 0 {
+            return None;
+        }
+        let remaining = goal.saturating_sub(self.total_cents).max(0) as i128;
+        let secs = (remaining * 3600 / rate as i128).min(i64::MAX as i128 / 1000) as i64;
+        Some(chrono::Duration::seconds(secs))
+    }
+
+    fn start_clock(&mut self) {
+        self.start_time = chrono::Utc::now();
+        self.started = std::time::Instant::now();
+        self.elapsed_before = std::time::Duration::ZERO;
+    }
+
+    pub fn session_elapsed(&self) -> std::time::Duration {
+        self.elapsed_before + self.started.elapsed()
+    }
+
+    // Share of the session goal reached, 0.0 to 1.0; None without a goal
+    pub fn goal_progress(&self) -> Option<f64> {
+        let goal = self.config.goal_cents.filter(|g| *g > 0)?;
+        Some((self.total_cents as f64 / goal as f64).clamp(0.0, 1.0))
+    }
+
+    // What --overlay-file holds: the formatted total, or a small JSON object
+    // for overlays that want the count and goal too
+    pub fn overlay_contents(&self, json: bool) -> String {
+        let total = format_money(self.total_cents, &self.config.money_format());
+        if !json {
+            return total;
+        }
+        serde_json::json!({
+            "total": total,
+            "total_cents": self.total_cents,
+            "currency": self.config.currency,
+            "count": self.payment_count,
+            "goal_cents": self.config.goal_cents.filter(|g| *g > 0),
+            "goal_progress": self.goal_progress(),
+        })
+        .to_string()
+    }
+
+    // One-line session summary, e.g. for pasting into a chat
+    pub fn summary_text(&self) -> String {
+        let secs = self.session_elapsed().as_secs();
+        format!(
+            "{} from {} payments in {}m {}s",
+            format_money(self.total_cents, &self.config.money_format()),
+            self.payment_count,
+            secs / 60,
+            secs % 60
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app() -> App {
+        crate::config::use_temp_config_dir();
+        App::new()
+    }
+
+    #[test]
+    fn summary_text_has_total_count_and_duration() {
+        let mut app = app();
+        app.total_cents = 123_456;
+        app.payment_count = 3;
+        app.elapsed_before = std::time::Duration::from_secs(125);
+        assert_eq!(app.summary_text(), "€1,234.56 from 3 payments in 2m 5s");
+    }
+
+    #[test]
+    fn only_a_started_session_is_worth_saving() {
+        let mut app = app();
+        assert!(!app.has_session());
+        app.phase = AppPhase::Running;
+        assert!(app.has_session());
+        // Editing the setup mid-session keeps the session
+        app.enter_edit_mode();
+        app.phase = AppPhase::Setup;
+        assert!(app.has_session());
+    }
+
+    #[test]
+    fn resumed_session_counts_only_time_the_app_was_open() {
+        let mut app = app();
+        let saved = SessionState {
+            start_time: chrono::Utc::now() - chrono::Duration::days(2),
+            active_secs: Some(1_800),
+            total_cents: 90_000,
+            net_cents: 90_000,
+            settled_cents: None,
+            seen_ids: HashMap::new(),
+            seen_floor: None,
+            newest_seen: HashMap::new(),
+            session_payments: Vec::new(),
+            payment_count: 3,
+            provider_totals: Vec::new(),
+            bills: Vec::new(),
+            pending_bills: Vec::new(),
+        };
+        app.restore_session(saved.clone());
+        assert_eq!(app.session_elapsed().as_secs(), 1_800);
+        assert_eq!(app.rate_per_hour(), 180_000);
+
+        // Files from before active_secs fall back to the wall clock
+        app.restore_session(SessionState { active_secs: None, ..saved });
+        assert!(app.session_elapsed() >= std::time::Duration::from_secs(2 * 86_400));
+    }
+
+    fn payment(n: usize, cents: i64) -> Payment {
+        Payment {
+            id: format!("p{}", n),
+            amount_cents: cents,
+            currency: "EUR".to_string(),
+            status: "succeeded".to_string(),
+            created_at: chrono::Utc::now(),
+            provider: "Mock".to_string(),
+            converted_cents: None,
+            settled: true,
+        }
+    }
+
+    #[test]
+    fn capped_ledger_keeps_counting_and_journals_everything() {
+        let mut app = app();
+        app.phase = AppPhase::Running;
+        app.config.max_session_payments = 5;
+        let dir = std::env::temp_dir().join(format!("profit-cli-test-{}-journal", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        app.keep_journal(Journal::new(dir.join("payments.jsonl"), false));
+        for n in 0..12 {
+            assert!(app.add_payment(payment(n, 100)));
+        }
+        assert_eq!(app.session_payments.len(), 5);
+        assert_eq!(app.session_payments[0].id, "p7");
+        assert_eq!((app.total_cents, app.payment_count), (1_200, 12));
+        let all = app.all_session_payments();
+        assert_eq!(all.len(), 12);
+        assert_eq!(all[0].id, "p0");
+    }
+
+    #[test]
+    fn reselecting_the_currency_keeps_hand_set_separators() {
+        let mut app = app();
+        app.config.symbol_position = SymbolPosition::Suffix;
+        app.config.decimal_separator = ",".to_string();
+        app.config.thousands_separator = ".".to_string();
+        let eur = app.currency_options().into_iter().find(|c| c.code == "EUR").unwrap();
+        app.select_currency(&eur);
+        assert_eq!(format_money(123_456, &app.config.money_format()), "1.234,56 €");
+
+        let usd = app.currency_options().into_iter().find(|c| c.code == "USD").unwrap();
+        app.select_currency(&usd);
+        assert_eq!(format_money(123_456, &app.config.money_format()), "$1,234.56");
+    }
+
+    #[test]
+    fn custom_suffix_currency_is_selected() {
+        let mut app = app();
+        app.add_custom_currency("NOK kr 2 suffix").unwrap();
+        assert_eq!(app.config.currency, "NOK");
+        assert_eq!(app.config.symbol_position, SymbolPosition::Suffix);
+        assert!(app.add_custom_currency("NOK kr 2 sideways").is_err());
+    }
+
+    #[test]
+    fn displayed_total_counts_up_to_the_total() {
+        let mut app = app();
+        app.total_cents = 10_000;
+        let mut last = app.displayed_total_cents;
+        for _ in 0..100 {
+            app.tick_animations();
+            assert!(app.displayed_total_cents >= last && app.displayed_total_cents <= 10_000.0);
+            last = app.displayed_total_cents;
+        }
+        assert_eq!(app.displayed_total_cents, 10_000.0);
+    }
+
+    #[test]
+    fn edit_mode_rebuilds_the_setup_rows_from_the_config() {
+        let providers = vec![
+            crate::psp::PspConfig {
+                provider: "Adyen".to_string(),
+                api_key: "adyen_key".to_string(),
+                merchant_account: Some("Shop".to_string()),
+                ..Default::default()
+            },
+            crate::psp::PspConfig {
+                provider: "PayPal".to_string(),
+                api_key: "client_id".to_string(),
+                api_secret: Some("client_secret".to_string()),
+                label: Some("US".to_string()),
+                ..Default::default()
+            },
+        ];
+        crate::config::use_temp_config_dir();
+        let mut app = App::from_config(AppConfig { currency: "USD".to_string(), providers, ..AppConfig::default() });
+        app.enter_edit_mode();
+
+        assert_eq!(app.phase, AppPhase::Setup);
+        assert_eq!(app.currency_options()[app.setup_currency_idx].code, "USD");
+        let enabled: Vec<_> = app.provider_configs.iter().filter(|r| r.enabled).collect();
+        assert_eq!(enabled.len(), 2);
+        assert_eq!((enabled[0].name.as_str(), enabled[0].api_key.as_str(), enabled[0].second_credential.as_str()), ("Adyen", "adyen_key", "Shop"));
+        assert_eq!((enabled[1].display_name().as_str(), enabled[1].second_credential.as_str()), ("PayPal (US)", "client_secret"));
+        // The unlabelled PayPal row is still offered, just not enabled
+        assert!(app.provider_configs.iter().any(|r| r.name == "PayPal" && r.label.is_empty() && !r.enabled));
+    }
+
+    fn ticks_to_land(animation_speed: f64) -> usize {
+        let mut app = app();
+        app.config.animation_speed = animation_speed;
+        app.add_payment(payment(1, 1_000));
+        app.spawn_next_bill(40);
+        let mut ticks = 0;
+        while !app.bills[0].settled {
+            app.tick_animations();
+            ticks += 1;
+            assert!(ticks < 1_000, "bill never landed at easing {}", animation_speed);
+        }
+        ticks
+    }
+
+    #[test]
+    fn faster_easing_lands_bills_in_fewer_ticks() {
+        assert!(ticks_to_land(0.6) < ticks_to_land(0.2));
+        // Out of range speeds fall back into (0, 1]
+        assert_eq!(ticks_to_land(5.0), ticks_to_land(1.0));
+        assert_eq!(ticks_to_land(0.0), ticks_to_land(AppConfig::default().animation_speed));
+    }
+
+    #[test]
+    fn all_time_stats_accumulate_across_payments_and_sessions() {
+        let mut app = app();
+        let carried = app.stats.all_time_cents;
+        let count = app.stats.payment_count;
+        app.add_payment(payment(1, 1_000));
+        app.add_payment(payment(2, 2_500));
+        // A duplicate isn't revenue twice
+        app.add_payment(payment(2, 2_500));
+        assert_eq!(app.stats.all_time_cents, carried + 3_500);
+        assert_eq!(app.stats.payment_count, count + 2);
+        assert_eq!(app.stats.day_total(app.tz.date(chrono::Utc::now())), app.stats.last_days(app.tz.date(chrono::Utc::now()), 7)[6]);
+        app.save_stats().unwrap();
+
+        // The next session picks up where this one left off
+        let mut next = App::new();
+        next.add_payment(payment(3, 500));
+        assert_eq!(next.stats.all_time_cents, carried + 4_000);
+        assert_eq!(next.total_cents, 500);
+    }
+
+    #[test]
+    fn rate_and_eta_from_a_known_total_and_duration() {
+        let mut app = app();
+        app.config.goal_cents = Some(20_000);
+        // A brand new session has no rate to divide by
+        assert_eq!(app.rate_per_hour(), 0);
+        assert_eq!(app.eta_to_goal(), None);
+
+        app.add_payment(payment(1, 10_000));
+        app.elapsed_before = std::time::Duration::from_secs(2 * 3600);
+        assert_eq!(app.rate_per_hour(), 5_000);
+        assert_eq!(app.rate_per_minute(), 83);
+        assert_eq!(app.eta_to_goal(), Some(chrono::Duration::hours(2)));
+
+        app.add_payment(payment(2, 15_000));
+        assert_eq!(app.eta_to_goal(), Some(chrono::Duration::zero()));
+        app.config.goal_cents = None;
+        assert_eq!(app.eta_to_goal(), None);
+    }
+
+    #[test]
+    fn net_total_takes_each_providers_fee() {
+        let mut app = app();
+        app.config.providers = vec![crate::psp::PspConfig {
+            provider: "Mock".to_string(),
+            fee: Some(crate::psp::FeeModel { percent: 1.4, fixed_cents: 25 }),
+            ..Default::default()
+        }];
+        assert!(app.has_fees());
+        app.add_payment(payment(1, 10_000));
+        app.add_payment(payment(2, 125));
+        let mut other = payment(3, 5_000);
+        other.provider = "PayPal".to_string();
+        app.add_payment(other);
+        assert_eq!(app.total_cents, 15_125);
+        assert_eq!(app.net_cents, 15_125 - 165 - 27);
+    }
+
+    #[test]
+    fn custom_currency_survives_a_restart_and_stays_selectable() {
+        let mut app = app();
+        app.add_custom_currency("NOK kr 2 suffix").unwrap();
+        let store = crate::config::InMemoryConfigStore::new();
+        let secrets = crate::secrets::InMemorySecretStore::new();
+        crate::config::save_config_to(&store, &app.config, &secrets).unwrap();
+
+        let loaded = crate::config::load_config_from(&store, &secrets).unwrap().unwrap();
+        let mut next = App::from_config(loaded);
+        assert_eq!(next.config.currency, "NOK");
+        let options = next.currency_options();
+        let nok = options.iter().find(|c| c.code == "NOK").expect("NOK is listed after a restart").clone();
+        assert_eq!((nok.symbol.as_str(), nok.decimals, nok.symbol_position), ("kr", 2, SymbolPosition::Suffix));
+        // Listed once, after the built-in ones
+        assert_eq!(options.iter().filter(|c| c.code == "NOK").count(), 1);
+        assert_eq!(options.last().map(|c| c.code.as_str()), Some("NOK"));
+
+        let eur = options.iter().find(|c| c.code == "EUR").unwrap().clone();
+        next.select_currency(&eur);
+        next.select_currency(&nok);
+        assert_eq!((next.config.currency.as_str(), next.config.currency_symbol.as_str()), ("NOK", "kr"));
+        assert_eq!(next.config.symbol_position, SymbolPosition::Suffix);
+    }
+
+    #[test]
+    fn celebration_waits_for_a_key_or_resets_after_its_ticks() {
+        let mut app = app();
+        app.config.celebration_duration_ticks = None;
+        app.start_celebration();
+        for _ in 0..10_000 {
+            app.tick_celebration();
+        }
+        assert_eq!(app.phase, AppPhase::Celebration);
+        assert_eq!(app.celebration_ticks_left(), None);
+
+        app.config.celebration_duration_ticks = Some(5);
+        app.start_celebration();
+        for left in (1..5).rev() {
+            app.tick_celebration();
+            assert_eq!(app.phase, AppPhase::Celebration);
+            assert_eq!(app.celebration_ticks_left(), Some(left));
+        }
+        app.tick_celebration();
+        assert_eq!(app.phase, AppPhase::Running);
+    }
+
+    // Lands bills until a `height`-row stack is full
+    fn fill_screen(app: &mut App, height: u16) {
+        let mut n = 1_000;
+        while !app.is_screen_full(height) {
+            n += 1;
+            app.add_payment(payment(n, 100));
+            app.spawn_next_bill(height);
+            while app.bills.iter().any(|b| !b.settled) {
+                app.tick_animations();
+            }
+        }
+        app.drop_pending_bills();
+    }
+
+    #[test]
+    fn full_screen_never_celebrates_with_celebrations_off() {
+        let mut app = app();
+        app.phase = AppPhase::Running;
+        app.config.celebration_enabled = false;
+        app.config.goal_cents = Some(500);
+        fill_screen(&mut app, 20);
+        // Neither the goal nor the full screen celebrates; the stack recycles instead
+        assert!(app.total_cents >= 500);
+        let bills = app.bills.len();
+        app.handle_full_screen(20);
+        assert_eq!(app.phase, AppPhase::Running);
+        assert_eq!(app.bills.len(), bills - 1);
+
+        app.config.celebration_enabled = true;
+        fill_screen(&mut app, 20);
+        app.handle_full_screen(20);
+        assert_eq!(app.phase, AppPhase::Celebration);
+    }
+
+    #[test]
+    fn recycling_drops_the_oldest_settled_bills_and_closes_the_gap() {
+        let mut app = app();
+        app.phase = AppPhase::Running;
+        // One bill each
+        let amounts = [100, 500, 2_000, 10_000, 500];
+        for (n, cents) in amounts.into_iter().enumerate() {
+            app.add_payment(payment(n, cents));
+            app.spawn_next_bill(30);
+            while app.bills.iter().any(|b| !b.settled) {
+                app.tick_animations();
+            }
+        }
+        // One still falling, which keeps its place in the queue
+        app.add_payment(payment(6, 2_000));
+        app.spawn_next_bill(30);
+        let (total, count) = (app.total_cents, app.payment_count);
+
+        app.recycle_oldest_bills(2, 30);
+        let amounts: Vec<i64> = app.bills.iter().map(|b| b.amount_cents).collect();
+        assert_eq!(amounts, [2_000, 10_000, 500, 2_000]);
+        let floor = 30 - 4;
+        for (i, bill) in app.bills.iter().enumerate() {
+            assert_eq!(bill.target_y, (floor - i as u16 * 3) as f64);
+            if bill.settled {
+                assert_eq!(bill.y_pos, bill.target_y);
+            }
+        }
+        assert!(!app.bills[3].settled);
+        assert_eq!((app.total_cents, app.payment_count), (total, count));
+    }
+
+    #[test]
+    fn wall_clock_jumping_back_never_runs_the_session_clock_backwards() {
+        let mut app = app();
+        app.add_payment(payment(1, 6_000));
+        let before = app.session_elapsed();
+        // The clock was set back an hour: the wall-clock start is now in the future
+        app.start_time = chrono::Utc::now() + chrono::Duration::hours(1);
+        let after = app.session_elapsed();
+        assert!(after >= before);
+        assert!(app.rate_per_hour() >= 0);
+        assert_eq!(app.session_summary(chrono::Utc::now()).duration_secs, after.as_secs());
+
+        // A saved session from "the future" resumes from zero rather than going negative
+        app.restore_session(SessionState {
+            start_time: chrono::Utc::now() + chrono::Duration::hours(1),
+            active_secs: None,
+            total_cents: 6_000,
+            net_cents: 6_000,
+            settled_cents: None,
+            seen_ids: HashMap::new(),
+            seen_floor: None,
+            newest_seen: HashMap::new(),
+            session_payments: Vec::new(),
+            payment_count: 1,
+            provider_totals: Vec::new(),
+            bills: Vec::new(),
+            pending_bills: Vec::new(),
+        });
+        assert!(app.session_elapsed() < std::time::Duration::from_secs(5));
+        assert!(app.rate_per_hour() >= 0);
+    }
+
+    #[test]
+    fn each_currency_policy_handles_a_mixed_batch() {
+        let batch = || {
+            let mut usd = payment(2, 1_000);
+            usd.currency = "usd".to_string();
+            vec![payment(1, 5_000), usd, payment(3, 2_000)]
+        };
+        let mut totals = Vec::new();
+        for policy in [MismatchedCurrency::Skip, MismatchedCurrency::Count, MismatchedCurrency::Convert] {
+            let mut app = app();
+            app.config.currency = "EUR".to_string();
+            app.config.mismatched_currency = policy;
+            app.fx = RateTable::new("EUR", &HashMap::from([("USD".to_string(), 0.9)]));
+            let counted: Vec<bool> = batch().into_iter().map(|p| app.add_payment(p)).collect();
+            totals.push((counted, app.total_cents, app.payment_count, app.recent_errors.len()));
+        }
+        // Skip leaves the dollars out and says so once; Count takes them at face value
+        assert_eq!(totals[0], (vec![true, false, true], 7_000, 2, 1));
+        assert_eq!(totals[1], (vec![true, true, true], 8_000, 3, 0));
+        assert_eq!(totals[2], (vec![true, true, true], 7_900, 3, 0));
+
+        // Converting without a rate counts at face value, with a warning
+        let mut app = app();
+        app.config.currency = "EUR".to_string();
+        app.config.mismatched_currency = MismatchedCurrency::Convert;
+        let mut gbp = payment(1, 1_000);
+        gbp.currency = "GBP".to_string();
+        assert!(app.add_payment(gbp));
+        assert_eq!(app.total_cents, 1_000);
+        assert_eq!(app.recent_errors.len(), 1);
+    }
+
+    struct ReadOnlyStore;
+
+    impl ConfigStore for ReadOnlyStore {
+        fn load(&self) -> anyhow::Result<Option<String>> {
+            Ok(None)
+        }
+
+        fn save(&self, _data: &str) -> anyhow::Result<()> {
+            anyhow::bail!("read-only file system")
+        }
+
+        fn describe(&self) -> String {
+            "read-only config".to_string()
+        }
+    }
+
+    #[test]
+    fn failed_setup_save_still_starts_the_session() {
+        let mut app = app();
+        let secrets = crate::secrets::InMemorySecretStore::new();
+        app.phase = AppPhase::Setup;
+        app.complete_setup(&ReadOnlyStore, &secrets);
+        assert_eq!(app.phase, AppPhase::Running);
+        assert!(app.config_unsaved);
+        assert!(app.error_message.as_deref().is_some_and(|e| e.contains("read-only file system")));
+
+        // The retry on quit writes it out
+        let store = crate::config::InMemoryConfigStore::new();
+        app.persist_config(&store, &secrets).unwrap();
+        assert!(!app.config_unsaved);
+        assert!(store.load().unwrap().is_some());
+    }
+
+    #[test]
+    fn a_muted_providers_payments_are_dropped_while_others_count() {
+        let mut app = app();
+        app.record_poll_success("Adyen");
+        app.record_poll_success("Mock");
+        let now = chrono::Utc::now();
+        app.toggle_provider(0);
+        assert_eq!(app.status_message.as_deref(), Some("Muted Adyen"));
+        // Out of range does nothing
+        app.toggle_provider(8);
+
+        assert!(!app.add_payment(crate::psp::ScriptedProvider::payment("Adyen", "a1", 5_000, now)));
+        assert!(app.add_payment(crate::psp::ScriptedProvider::payment("Mock", "m1", 1_000, now)));
+        assert_eq!(app.total_cents, 1_000);
+        assert_eq!(app.payment_count, 1);
+
+        // Unmuted, it counts again, but what arrived while muted stays uncounted
+        app.toggle_provider(0);
+        assert!(!app.add_payment(crate::psp::ScriptedProvider::payment("Adyen", "a1", 5_000, now)));
+        assert!(app.add_payment(crate::psp::ScriptedProvider::payment("Adyen", "a2", 2_000, now)));
+        assert_eq!(app.total_cents, 3_000);
+    }
+
+    #[test]
+    fn unexpected_key_prefixes_warn() {
+        assert_eq!(key_format_warning("Adyen", "AQEyhmfxK..."), None);
+        assert_eq!(key_format_warning("GoCardless", "live_abc"), None);
+        assert_eq!(key_format_warning("GoCardless", "sandbox_abc"), None);
+        assert_eq!(
+            key_format_warning("GoCardless", "test_abc").as_deref(),
+            Some("GoCardless keys usually start with live_ or sandbox_; double-check it was copied whole")
+        );
+        assert!(key_format_warning("Adyen", "QEyhmfxK").is_some());
+        // Providers without a known format never warn
+        assert_eq!(key_format_warning("PayPal", "anything"), None);
+    }
+
+    #[test]
+    fn summary_serializes_totals_breakdown_and_times() {
+        let mut app = app();
+        app.config.currency = "EUR".to_string();
+        app.config.providers = vec![crate::psp::PspConfig {
+            provider: "Mock".to_string(),
+            fee: Some(crate::psp::FeeModel { percent: 1.4, fixed_cents: 25 }),
+            ..Default::default()
+        }];
+        app.add_payment(payment(1, 10_000));
+        let mut paypal = payment(2, 20_000);
+        paypal.provider = "PayPal".to_string();
+        app.add_payment(paypal);
+        app.add_payment(payment(3, 5_000));
+        app.start_time = "2026-10-16T09:00:00Z".parse().unwrap();
+        app.elapsed_before = std::time::Duration::from_secs(90 * 60);
+
+        let ended_at = "2026-10-16T10:30:00Z".parse().unwrap();
+        let summary = serde_json::to_value(app.session_summary(ended_at)).unwrap();
+        assert_eq!(
+            summary,
+            serde_json::json!({
+                "currency": "EUR",
+                "total_cents": 35_000,
+                "net_cents": 35_000 - 165 - 95,
+                "payment_count": 3,
+                "duration_secs": 5_400,
+                "started_at": "2026-10-16T09:00:00Z",
+                "ended_at": "2026-10-16T10:30:00Z",
+                "providers": [
+                    { "provider": "PayPal", "total_cents": 20_000, "payment_count": 1 },
+                    { "provider": "Mock", "total_cents": 15_000, "payment_count": 2 }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn waiting_spinner_and_celebration_timer_tick_separately() {
+        let mut app = app();
+        app.phase = AppPhase::Running;
+        app.config.celebration_duration_ticks = None;
+        for _ in 0..30 {
+            app.tick_timers();
+        }
+        assert_eq!((app.waiting_tick, app.celebration_tick), (30, 0));
+
+        app.start_celebration();
+        for _ in 0..12 {
+            app.tick_timers();
+        }
+        assert_eq!((app.waiting_tick, app.celebration_tick), (30, 12));
+
+        // Bills on screen stop the spinner too
+        app.reset_session();
+        app.add_payment(payment(1, 500));
+        app.spawn_next_bill(30);
+        app.tick_timers();
+        assert_eq!((app.waiting_tick, app.celebration_tick), (30, 0));
+    }
+
+    #[test]
+    fn a_celebration_after_a_long_wait_lasts_its_full_duration() {
+        let mut app = app();
+        app.phase = AppPhase::Running;
+        app.config.celebration_duration_ticks = Some(50);
+        for _ in 0..100_000 {
+            app.tick_timers();
+        }
+        app.start_celebration();
+        for _ in 0..49 {
+            app.tick_timers();
+        }
+        assert_eq!(app.phase, AppPhase::Celebration);
+        app.tick_timers();
+        assert_eq!(app.phase, AppPhase::Running);
+
+        // And the next one gets its full time again
+        app.start_celebration();
+        assert_eq!(app.celebration_ticks_left(), Some(50));
+    }
+
+    #[test]
+    fn burst_spawns_more_bills_per_tick_than_steady_for_a_backlog() {
+        let spawned = |style: SpawnStyle| {
+            let mut app = app();
+            app.phase = AppPhase::Running;
+            app.config.spawn_style = style;
+            for n in 0..12 {
+                app.add_payment(payment(n, 10_000));
+            }
+            let per_spawn = app.bills_per_spawn();
+            for _ in 0..per_spawn {
+                app.spawn_next_bill(60);
+            }
+            // Bills released together still take their own slots in the stack
+            let mut targets: Vec<i64> = app.bills.iter().map(|b| b.target_y as i64).collect();
+            targets.dedup();
+            assert_eq!(targets.len(), app.bills.len());
+            per_spawn
+        };
+        assert_eq!(spawned(SpawnStyle::Steady), 1);
+        assert_eq!(spawned(SpawnStyle::Burst), 3);
+
+        // A small queue trickles out either way
+        let mut app = app();
+        app.config.spawn_style = SpawnStyle::Burst;
+        app.add_payment(payment(1, 10_000));
+        assert_eq!(app.bills_per_spawn(), 1);
+    }
+
+    #[test]
+    fn payments_in_one_window_fall_as_one_combo_bill() {
+        let mut app = app();
+        app.config.combo_window_secs = Some(60);
+        let at = |secs: i64| chrono::DateTime::from_timestamp(1_760_000_040 + secs, 0).unwrap();
+        let mut paid = |provider: &str, id: &str, cents: i64, secs: i64| {
+            app.add_payment(crate::psp::ScriptedProvider::payment(provider, id, cents, at(secs)));
+        };
+        paid("Adyen", "a1", 1_250, 0);
+        paid("Adyen", "a2", 500, 10);
+        paid("PayPal", "p1", 700, 15);
+        paid("Adyen", "a3", 2_000, 59);
+        paid("Adyen", "a4", 100, 60);
+
+        let bills: Vec<(&str, i64, usize)> =
+            app.pending_bills.iter().map(|b| (b.provider.as_str(), b.amount_cents, b.count)).collect();
+        assert_eq!(bills, [("Adyen", 3_750, 3), ("PayPal", 700, 1), ("Adyen", 100, 1)]);
+        // Totals still count every payment
+        assert_eq!((app.total_cents, app.payment_count), (4_550, 5));
+
+        app.spawn_next_bill(40);
+        assert_eq!((app.bills[0].amount_cents, app.bills[0].count), (3_750, 3));
+    }
+
+    #[test]
+    fn fill_threshold_decides_when_the_screen_is_full() {
+        let mut app = app();
+        let settled = |n: usize| -> Vec<BillAnimation> {
+            (0..n)
+                .map(|_| BillAnimation {
+                    amount_cents: 100,
+                    y_pos: 0.0,
+                    target_y: 0.0,
+                    settled: true,
+                    age_ticks: 0,
+                    provider: "Mock".to_string(),
+                    count: 1,
+                    unsettled: false,
+                    just_settled_ticks: 0,
+                })
+                .collect()
+        };
+        // A 46-row terminal has 40 rows for bills, 13 three-row bills to fill them
+        for (pct, first_full) in [(80, 11), (100, 14), (10, 2), (0, 2), (255, 14)] {
+            app.config.fill_threshold_pct = pct;
+            app.bills = settled(first_full - 1);
+            assert!(!app.is_screen_full(46), "{}% with {} bills", pct, first_full - 1);
+            app.bills = settled(first_full);
+            assert!(app.is_screen_full(46), "{}% with {} bills", pct, first_full);
+        }
+        // Falling bills don't count towards it
+        app.config.fill_threshold_pct = 10;
+        app.bills = settled(2);
+        app.bills[1].settled = false;
+        assert!(!app.is_screen_full(46));
+    }
+
+    #[test]
+    fn authorised_and_settled_payments_keep_two_totals() {
+        let mut app = app();
+        let authorised = |n: usize, cents: i64| {
+            let mut p = payment(n, cents);
+            p.settled = false;
+            p
+        };
+        let batch = [payment(1, 10_000), authorised(2, 2_500), payment(3, 500), authorised(4, 7_000)];
+        for p in batch {
+            app.add_payment(p);
+        }
+        assert_eq!((app.total_cents, app.settled_cents), (20_000, 10_500));
+        // Authorised-only bills fall dashed
+        let unsettled: i64 = app.pending_bills.iter().filter(|b| b.unsettled).map(|b| b.amount_cents).sum();
+        assert_eq!(unsettled, 9_500);
+
+        app.start_round();
+        assert_eq!((app.total_cents, app.settled_cents), (0, 0));
+    }
+
+    #[test]
+    fn huge_amounts_saturate_instead_of_overflowing() {
+        let mut app = app();
+        let big = i64::MAX / 100;
+        app.config.goal_cents = Some(i64::MAX);
+        app.config.providers = vec![crate::psp::PspConfig {
+            provider: "Mock".to_string(),
+            fee: Some(crate::psp::FeeModel { percent: 2.9, fixed_cents: 30 }),
+            ..Default::default()
+        }];
+        app.add_payment(payment(0, big));
+        // Only the capped number of bills is ever made, however large the payment
+        assert_eq!(app.pending_bills.len(), app.config.max_bills_per_payment);
+
+        app.elapsed_before = std::time::Duration::from_secs(5 * 3600);
+        // €922 trillion over five hours, rounded to the cent
+        assert_eq!(app.rate_per_hour(), 18_446_744_073_709_552);
+        assert_eq!(app.rate_per_minute(), 307_445_734_561_826);
+        assert!(app.eta_to_goal().is_some());
+
+        for n in 1..150 {
+            app.add_payment(payment(n, big));
+        }
+        assert_eq!(app.total_cents, i64::MAX);
+        assert_eq!(app.settled_cents, i64::MAX);
+        assert!(app.net_cents > i64::MAX / 2);
+        assert_eq!(app.rate_per_hour(), i64::MAX / 5);
+        assert_eq!(app.eta_to_goal(), Some(chrono::Duration::zero()));
+        assert_eq!(app.session_breakdown()[0].1, i64::MAX);
+        assert!(format_money(app.total_cents, &app.config.money_format()).starts_with('€'));
+    }
+
+    #[test]
+    fn celebrating_on_demand_keeps_the_session_totals() {
+        let mut app = app();
+        app.phase = AppPhase::Running;
+        app.add_payment(payment(1, 4_200));
+        app.add_payment(payment(2, 800));
+        app.celebrate_now();
+        assert_eq!(app.phase, AppPhase::Celebration);
+        assert_eq!((app.total_cents, app.payment_count), (5_000, 2));
+
+        // Enter goes back to the same session
+        app.reset_session();
+        assert_eq!(app.phase, AppPhase::Running);
+        assert_eq!((app.total_cents, app.payment_count), (5_000, 2));
+
+        app.config.celebration_enabled = false;
+        app.celebrate_now();
+        assert_eq!(app.phase, AppPhase::Running);
+        assert_eq!(app.status_message.as_deref(), Some("Celebrations are turned off"));
+    }
+
+    #[test]
+    fn top_payments_are_the_largest_with_ties_going_to_the_newest() {
+        let mut app = app();
+        assert!(app.top_payments(3).is_empty());
+        let start = chrono::Utc::now() - chrono::Duration::hours(1);
+        for (n, cents) in [(1, 4_200), (2, 900), (3, 12_000), (4, 4_200), (5, 50)] {
+            let mut p = payment(n, cents);
+            p.created_at = start + chrono::Duration::minutes(n as i64);
+            app.add_payment(p);
+        }
+        let ids = |n| app.top_payments(n).into_iter().map(|p| p.id.as_str()).collect::<Vec<_>>();
+        assert_eq!(ids(3), ["p3", "p4", "p1"]);
+        assert_eq!(ids(1), ["p3"]);
+        // Fewer payments than asked for is just all of them
+        assert_eq!(ids(10), ["p3", "p4", "p1", "p2", "p5"]);
+        assert!(ids(0).is_empty());
+    }
+
+    #[test]
+    fn landing_sets_the_flash_which_counts_down_to_zero() {
+        let mut app = app();
+        app.add_payment(payment(1, 100));
+        app.spawn_next_bill(30);
+        while !app.bills[0].settled {
+            assert_eq!(app.bills[0].just_settled_ticks, 0);
+            app.tick_animations();
+        }
+        assert_eq!(app.bills[0].y_pos, app.bills[0].target_y);
+        for left in (0..LANDING_FLASH_TICKS).rev() {
+            app.tick_animations();
+            assert_eq!(app.bills[0].just_settled_ticks, left);
+        }
+        app.tick_animations();
+        assert_eq!(app.bills[0].just_settled_ticks, 0);
+    }
+
+    #[test]
+    fn watch_summary_names_each_provider_its_interval_and_the_start() {
+        let mut app = app();
+        let provider = |name: &str, label: Option<&str>, secs: Option<u64>| crate::psp::PspConfig {
+            provider: name.to_string(),
+            label: label.map(str::to_string),
+            poll_interval_secs: secs,
+            ..Default::default()
+        };
+        app.config.poll_interval_secs = 10;
+        app.config.providers = vec![provider("Adyen", Some("EU"), None), provider("Mock", None, None)];
+        app.tz = DisplayTz::from_config(Some("UTC")).0;
+        app.start_time = "2026-10-16T14:02:30Z".parse().unwrap();
+        assert_eq!(app.watch_summary(), "Watching: Adyen (EU), Mock every 10s since 14:02");
+
+        // Per-provider intervals are spelled out, and a backfill moves the start back
+        app.config.providers[1].poll_interval_secs = Some(2);
+        app.backfill_since = Some("2026-10-16T12:00:00Z".parse().unwrap());
+        assert_eq!(app.watch_summary(), "Watching: Adyen (EU) every 10s, Mock every 2s since 12:00");
+
+        app.config.providers[1].poll_interval_secs = None;
+        app.config.poll_mode = crate::config::PollMode::Adaptive;
+        app.config.adaptive_floor_secs = 5;
+        app.config.adaptive_ceiling_secs = 60;
+        assert_eq!(app.watch_summary(), "Watching: Adyen (EU), Mock every 10s (adapting 5–60s) since 12:00");
+    }
+
+    #[test]
+    fn the_bill_stack_survives_a_save_and_restore() {
+        let mut before = app();
+        before.phase = AppPhase::Running;
+        let bill = |cents: i64, provider: &str, target_y: f64, settled: bool| BillAnimation {
+            amount_cents: cents,
+            y_pos: if settled { target_y } else { 5.0 },
+            target_y,
+            settled,
+            age_ticks: 40,
+            provider: provider.to_string(),
+            count: 1,
+            unsettled: false,
+            just_settled_ticks: 0,
+        };
+        // Stacked on a 40-row terminal, the top one still falling
+        before.bills = vec![bill(1_000, "Mock", 36.0, true), bill(2_500, "Adyen", 33.0, true), bill(700, "Mock", 30.0, false)];
+        before.pending_bills =
+            vec![PendingBill { amount_cents: 4_200, provider: "Mock".to_string(), count: 2, window: Some(7), unsettled: true }];
+        before.save_state().unwrap();
+
+        let mut restored = app();
+        restored.restore_session(state::load_state().unwrap());
+        let stack: Vec<(i64, &str)> = restored.bills.iter().map(|b| (b.amount_cents, b.provider.as_str())).collect();
+        assert_eq!(stack, [(1_000, "Mock"), (2_500, "Adyen"), (700, "Mock")]);
+        assert!(restored.bills.iter().all(|b| b.settled && b.y_pos == b.target_y));
+        let pending: Vec<(i64, usize, Option<i64>, bool)> =
+            restored.pending_bills.iter().map(|p| (p.amount_cents, p.count, p.window, p.unsettled)).collect();
+        assert_eq!(pending, [(4_200, 2, Some(7), true)]);
+
+        // Back on a 16-row terminal the oldest bill makes room and the rest sit on its floor
+        restored.config.fill_threshold_pct = 80;
+        restored.drop_overflowing_bills(16);
+        restored.reflow_bills(16);
+        let stack: Vec<(i64, f64)> = restored.bills.iter().map(|b| (b.amount_cents, b.y_pos)).collect();
+        assert_eq!(stack, [(2_500, 12.0), (700, 9.0)]);
+    }
+
+    #[test]
+    fn payments_are_binned_by_when_they_came_in() {
+        let start: chrono::DateTime<chrono::Utc> = "2026-10-16T14:00:00Z".parse().unwrap();
+        let at = |secs: i64, cents: i64| {
+            crate::psp::ScriptedProvider::payment("Adyen", &format!("p{}", secs), cents, start + chrono::Duration::seconds(secs))
+        };
+        // Four 25-second buckets; the ends and anything outside the session clamp to the nearest bucket
+        let payments = [at(0, 100), at(24, 200), at(25, 300), at(99, 400), at(100, 50), at(-10, 10), at(50, -500)];
+        let end = start + chrono::Duration::seconds(100);
+        assert_eq!(bin_by_time(&payments, start, end, 4), [310, 300, 0, 450]);
+        assert_eq!(bin_by_time(&payments, start, end, 1), [1_060]);
+        assert!(bin_by_time(&payments, start, end, 0).is_empty());
+
+        // A lone payment is a single bump, and a session with no length doesn't divide by zero
+        assert_eq!(bin_by_time(&payments[2..3], start, end, 4), [0, 300, 0, 0]);
+        assert_eq!(bin_by_time(&payments[..1], start, start, 3), [100, 0, 0]);
+    }
+
+    #[test]
+    fn the_next_since_is_the_last_seen_payment_minus_the_configured_overlap() {
+        let mut app = app();
+        assert_eq!(app.config.poll_overlap_secs, 30);
+        app.start_time = "2026-10-16T08:00:00Z".parse().unwrap();
+        let last_seen: chrono::DateTime<chrono::Utc> = "2026-10-16T14:00:00Z".parse().unwrap();
+        app.newest_seen.insert("Adyen".to_string(), last_seen);
+
+        for secs in [0, 30, 600] {
+            app.config.poll_overlap_secs = secs;
+            assert_eq!(app.poll_since("Adyen"), last_seen - chrono::Duration::seconds(secs as i64));
+        }
+        // A provider with nothing seen yet starts at the session, and no overlap reaches back past it
+        assert_eq!(app.poll_since("Mock"), app.start_time);
+        app.config.poll_overlap_secs = 12 * 60 * 60;
+        assert_eq!(app.poll_since("Adyen"), app.start_time);
+    }
+
+    #[test]
+    fn payments_per_minute_counts_only_the_last_minute() {
+        let now = std::time::Instant::now() + std::time::Duration::from_secs(600);
+        let ago = |secs: u64| now - std::time::Duration::from_secs(secs);
+        let times: std::collections::VecDeque<_> = [ago(300), ago(61), ago(60), ago(59), ago(30), ago(0)].into();
+        assert_eq!(count_within(&times, now, THROUGHPUT_WINDOW), 3);
+        assert_eq!(count_within(&times, now, std::time::Duration::from_secs(600)), 6);
+        assert_eq!(count_within(&std::collections::VecDeque::new(), now, THROUGHPUT_WINDOW), 0);
+
+        // A fresh session reads 0/min, and each counted payment adds to it
+        let mut app = app();
+        assert_eq!(app.payments_per_minute(), 0);
+        app.add_payment(payment(1, 500));
+        app.add_payment(payment(2, 500));
+        assert_eq!(app.payments_per_minute(), 2);
+    }
+
+    #[test]
+    fn extra_currencies_join_the_picker_once_per_code() {
+        let mut app = app();
+        let codes = |app: &App| -> Vec<String> { app.currency_options().into_iter().map(|c| c.code).collect() };
+        assert_eq!(codes(&app), ["EUR", "USD", "GBP", "JPY", "CHF", "CAD", "AUD"]);
+
+        app.config.extra_currencies = vec![
+            CustomCurrency { symbol_position: SymbolPosition::Suffix, ..CustomCurrency::new("SEK", "kr", 2) },
+            CustomCurrency::new("usd", "US$", 2),
+            CustomCurrency::new("PLN", "zł", 2),
+            CustomCurrency::new("SEK", "SEK", 0),
+        ];
+        app.config.custom_currencies = vec![CustomCurrency::new("PLN", "PLN", 2), CustomCurrency::new("NOK", "kr", 2)];
+        assert_eq!(codes(&app), ["EUR", "USD", "GBP", "JPY", "CHF", "CAD", "AUD", "SEK", "PLN", "NOK"]);
+
+        // The first listing of a code wins: built-ins over extras, extras over the user's own
+        let options = app.currency_options();
+        let find = |code: &str| options.iter().find(|c| c.code == code).unwrap();
+        assert_eq!(find("USD").symbol, "$");
+        assert_eq!((find("SEK").symbol.as_str(), find("SEK").decimals, find("SEK").symbol_position), ("kr", 2, SymbolPosition::Suffix));
+        assert_eq!(find("PLN").symbol, "zł");
+        assert_eq!(app.add_custom_currency("sek"), Err("SEK is already in the list".to_string()));
+    }
+
+    #[test]
+    fn replaying_payments_into_a_restored_session_counts_none_twice() {
+        let now = chrono::Utc::now();
+        let ago = |minutes: i64| now - chrono::Duration::minutes(minutes);
+        let payments: Vec<Payment> = [("Mock", 180), ("Mock", 90), ("Adyen", 60), ("Mock", 45), ("Adyen", 20), ("Mock", 0)]
+            .into_iter()
+            .enumerate()
+            .map(|(i, (provider, minutes))| {
+                crate::psp::ScriptedProvider::payment(provider, &format!("p{}", i), 1_000 + i as i64, ago(minutes))
+            })
+            .collect();
+
+        let mut before = app();
+        for p in &payments {
+            assert!(before.add_payment(p.clone()));
+        }
+        // The older Mock IDs are pruned; only the floor remembers them
+        before.prune_seen_ids();
+        assert!(before.seen_ids.len() < payments.len());
+
+        // Through JSON, as the state file would carry it
+        let saved = serde_json::to_string(&before.session_state()).unwrap();
+        let mut restored = app();
+        restored.restore_session(serde_json::from_str(&saved).unwrap());
+        assert_eq!(restored.total_cents, 6_015);
+        // A backfill overlapping everything already counted
+        for p in &payments {
+            assert!(!restored.add_payment(p.clone()), "{} counted twice", p.id);
+        }
+        assert_eq!((restored.total_cents, restored.payment_count), (6_015, 6));
+
+        // Something genuinely new still counts
+        assert!(restored.add_payment(crate::psp::ScriptedProvider::payment("Mock", "new", 500, now)));
+        assert_eq!(restored.total_cents, 6_515);
+    }
+
+    #[test]
+    fn a_celebration_carries_the_total_or_starts_a_new_round() {
+        for reset in [false, true] {
+            let mut app = app();
+            app.phase = AppPhase::Running;
+            app.config.reset_total_on_celebration = reset;
+            app.start_time = chrono::Utc::now() - chrono::Duration::hours(1);
+            let started = app.start_time;
+            app.add_payment(payment(1, 1_000));
+            app.add_payment(payment(2, 2_500));
+            let all_time = app.stats.all_time_cents;
+
+            app.start_celebration();
+            app.reset_session();
+            assert_eq!(app.phase, AppPhase::Running);
+            assert!(app.bills.is_empty() && app.pending_bills.is_empty());
+            if reset {
+                assert_eq!((app.total_cents, app.payment_count, app.session_payments.len()), (0, 0, 0));
+                assert!(app.provider_totals.is_empty());
+                assert!(app.start_time > started);
+            } else {
+                assert_eq!((app.total_cents, app.payment_count, app.session_payments.len()), (3_500, 2, 2));
+                assert_eq!(app.start_time, started);
+            }
+            // Either way nothing already counted comes back, and all-time keeps growing
+            assert!(!app.add_payment(payment(2, 2_500)));
+            assert!(app.add_payment(payment(3, 500)));
+            assert_eq!(app.total_cents, if reset { 500 } else { 4_000 });
+            assert_eq!(app.stats.all_time_cents, all_time + 500);
+        }
+    }
+
+    #[test]
+    fn a_huge_backlog_drains_faster_but_never_floods_a_tick() {
+        let mut app = app();
+        app.phase = AppPhase::Running;
+        app.pending_bills = (0..300)
+            .map(|_| PendingBill { amount_cents: 10_000, provider: "Mock".to_string(), count: 1, window: None, unsettled: false })
+            .collect();
+        let mut per_round = Vec::new();
+        while !app.pending_bills.is_empty() {
+            let n = app.bills_per_spawn();
+            for _ in 0..n {
+                app.spawn_next_bill(u16::MAX);
+            }
+            per_round.push(n);
+        }
+        assert_eq!(app.bills.len(), 300);
+        // Flat out while the queue is deep, easing off as it empties, down to one at a time
+        assert_eq!(per_round[0], MAX_CATCH_UP);
+        assert!(per_round.iter().all(|&n| (1..=MAX_CATCH_UP).contains(&n)));
+        assert!(per_round.windows(2).all(|w| w[0] >= w[1]), "{:?}", per_round);
+        assert_eq!(per_round[per_round.len() - CATCH_UP_THRESHOLD..], [1; CATCH_UP_THRESHOLD]);
+        assert!(per_round.len() < 100, "{} rounds", per_round.len());
+    }
+
+    #[test]
+    fn one_payment_crossing_a_thousand_and_the_hundredth_toasts_both_once() {
+        let mut app = app();
+        app.config.milestones = true;
+        app.config.milestone_every_cents = Some(100_000);
+        app.config.milestone_every_payments = Some(100);
+        for n in 0..99 {
+            app.add_payment(payment(n, 1_000));
+        }
+        assert_eq!(app.total_cents, 99_000);
+        app.toasts.clear();
+
+        assert!(app.add_payment(payment(99, 1_000)));
+        let shown: Vec<Milestone> = app.toasts.iter().map(|t| t.milestone).collect();
+        assert_eq!(shown, [Milestone::Total(100_000), Milestone::Count(100)]);
+        // Neither fires again on the next payment, or for a replay of this one
+        assert!(!app.add_payment(payment(99, 1_000)));
+        assert!(app.add_payment(payment(100, 1_000)));
+        assert_eq!(app.toasts.len(), 2);
+    }
+
+    #[test]
+    fn session_breakdown_totals_each_provider_largest_first() {
+        let mut app = app();
+        let now = chrono::Utc::now();
+        let paid = |provider: &str, id: &str, cents: i64| crate::psp::ScriptedProvider::payment(provider, id, cents, now);
+        for p in [
+            paid("Mock", "1", 500),
+            paid("Adyen", "1", 2_000),
+            paid("PayPal", "1", 1_200),
+            paid("Adyen", "2", 300),
+            paid("Mock", "2", 1_500),
+            paid("Adyen (US)", "1", 100),
+            paid("PayPal", "2", -200),
+            paid("Coinbase", "1", 1_000),
+        ] {
+            app.add_payment(p);
+        }
+        // Ties go by name; refunds net off; labelled accounts stay separate
+        assert_eq!(
+            app.session_breakdown(),
+            [
+                ("Adyen".to_string(), 2_300, 2),
+                ("Mock".to_string(), 2_000, 2),
+                ("Coinbase".to_string(), 1_000, 1),
+                ("PayPal".to_string(), 1_000, 2),
+                ("Adyen (US)".to_string(), 100, 1),
+            ]
+        );
+        assert!(App::new().session_breakdown().is_empty());
+    }
+
+    #[test]
+    fn ticks_while_paused_leave_the_bills_where_they_are() {
+        let mut app = app();
+        app.add_payment(payment(1, 500));
+        app.spawn_next_bill(18);
+        app.add_payment(payment(2, 700));
+        let frozen = |app: &App| app.bills.iter().map(|b| (b.y_pos, b.age_ticks, b.settled)).collect::<Vec<_>>();
+        let (before, pending) = (frozen(&app), app.pending_bills.len());
+
+        assert!(app.toggle_pause().is_empty());
+        // The loop holds back what arrives meanwhile
+        app.held_payments.push(payment(3, 900));
+        for _ in 0..50 {
+            app.tick_animations();
+            app.spawn_next_bill(18);
+        }
+        assert_eq!(frozen(&app), before);
+        assert_eq!((app.bills.len(), app.pending_bills.len(), app.payment_count), (1, pending, 2));
+
+        let held = app.toggle_pause();
+        assert_eq!(held.len(), 1);
+        assert!(app.held_payments.is_empty());
+        app.tick_animations();
+        app.spawn_next_bill(18);
+        assert_ne!(frozen(&app)[0], before[0]);
+        assert_eq!(app.bills.len(), 2);
+    }
+
+    #[test]
+    fn recent_errors_keep_only_the_newest_few() {
+        let mut app = app();
+        app.push_error("Adyen".to_string(), "401 Unauthorized".to_string());
+        assert_eq!(app.recent_errors.len(), 1);
+        assert_eq!((app.recent_errors[0].provider.as_str(), app.recent_errors[0].message.as_str()), ("Adyen", "401 Unauthorized"));
+
+        for n in 0..MAX_RECENT_ERRORS + 2 {
+            app.push_error("PayPal".to_string(), format!("timeout {}", n));
+        }
+        assert_eq!(app.recent_errors.len(), MAX_RECENT_ERRORS);
+        let messages: Vec<&str> = app.recent_errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, ["timeout 2", "timeout 3", "timeout 4", "timeout 5", "timeout 6"]);
+    }
+
+    #[test]
+    fn health_glyph_shows_success_failure_and_staleness() {
+        let now = chrono::Utc::now();
+        let stale_after = chrono::Duration::minutes(5);
+        let mut status = ProviderStatus::new("Adyen");
+        assert_eq!(status.health_glyph(now, stale_after), "…");
+
+        status.last_success = Some(now - chrono::Duration::seconds(3));
+        assert_eq!(status.health_glyph(now, stale_after), "✓");
+        assert_eq!(status.health_glyph(now + stale_after - chrono::Duration::seconds(3), stale_after), "✓");
+        assert_eq!(status.health_glyph(now + stale_after, stale_after), "…");
+
+        // A failure shows even right after a success
+        status.consecutive_failures = 1;
+        status.last_error = Some("401 Unauthorized".to_string());
+        assert_eq!(status.health_glyph(now, stale_after), "✗");
+    }
+
+    #[test]
+    fn crossing_the_goal_celebrates_straight_away() {
+        let mut app = app();
+        app.phase = AppPhase::Running;
+        app.config.goal_cents = Some(5_000);
+        app.add_payment(payment(1, 3_000));
+        app.add_payment(payment(2, 1_999));
+        assert_eq!(app.phase, AppPhase::Running);
+        app.add_payment(payment(3, 1));
+        assert_eq!(app.phase, AppPhase::Celebration);
+
+        // Only the crossing counts, and no goal means no celebration
+        app.reset_session();
+        app.add_payment(payment(4, 2_000));
+        assert_eq!(app.phase, AppPhase::Running);
+        for goal in [None, Some(0)] {
+            let mut app = self::app();
+            app.phase = AppPhase::Running;
+            app.config.goal_cents = goal;
+            app.add_payment(payment(1, 10_000));
+            assert_eq!(app.phase, AppPhase::Running);
+        }
+    }
+
+    #[test]
+    fn the_ledger_scrolls_within_its_ends() {
+        let mut app = app();
+        app.scroll_ledger(1);
+        assert_eq!(app.payment_list_scroll.selected(), None);
+
+        for n in 0..5 {
+            app.add_payment(payment(n, 100));
+        }
+        app.scroll_ledger(-1);
+        assert_eq!(app.payment_list_scroll.selected(), Some(0));
+        app.scroll_ledger(3);
+        assert_eq!(app.payment_list_scroll.selected(), Some(3));
+        app.scroll_ledger(10);
+        assert_eq!(app.payment_list_scroll.selected(), Some(4));
+        app.scroll_ledger(-10);
+        assert_eq!(app.payment_list_scroll.selected(), Some(0));
+
+        // More payments make room further down
+        app.scroll_ledger(10);
+        for n in 5..8 {
+            app.add_payment(payment(n, 100));
+        }
+        app.scroll_ledger(10);
+        assert_eq!(app.payment_list_scroll.selected(), Some(7));
+    }
+
+    #[test]
+    fn the_same_raw_id_from_two_providers_counts_twice() {
+        let mut app = app();
+        let now = chrono::Utc::now();
+        let paid = |provider: &str| crate::psp::ScriptedProvider::payment(provider, "pay_001", 1_000, now);
+        assert!(app.add_payment(paid("Adyen")));
+        assert!(app.add_payment(paid("PayPal")));
+        assert!(!app.add_payment(paid("Adyen")));
+        assert_eq!((app.total_cents, app.payment_count), (2_000, 2));
+
+        // The composite key survives a save and restore
+        let saved = serde_json::to_string(&app.session_state()).unwrap();
+        let mut restored = self::app();
+        restored.restore_session(serde_json::from_str(&saved).unwrap());
+        assert!(!restored.add_payment(paid("PayPal")));
+        assert!(restored.add_payment(paid("Coinbase")));
+    }
+
+    #[test]
+    fn reflowed_bills_stack_up_from_the_new_floor() {
+        let mut app = app();
+        for n in 0..4 {
+            app.add_payment(payment(n, 100));
+            app.spawn_next_bill(40);
+        }
+        for _ in 0..200 {
+            app.tick_animations();
+        }
+        assert!(app.bills.iter().all(|b| b.settled));
+
+        for height in [30, 60, 16] {
+            app.reflow_bills(height);
+            let targets: Vec<f64> = app.bills.iter().map(|b| b.target_y).collect();
+            assert_eq!(targets[0], (height - 4) as f64);
+            assert!(targets.windows(2).all(|pair| pair[0] - pair[1] == 3.0 || pair[1] == 0.0), "{:?} at {}", targets, height);
+            assert!(app.bills.iter().all(|b| b.y_pos == b.target_y));
+        }
+    }
+
+    #[test]
+    fn add_payment_makes_no_more_bills_than_the_configured_cap() {
+        let mut app = app();
+        app.config.max_bills_per_payment = 3;
+        // €186 would be 100 + 4 × 20 + 5 + 1
+        app.add_payment(payment(1, 18_600));
+        let amounts: Vec<i64> = app.pending_bills.iter().map(|b| b.amount_cents).collect();
+        assert_eq!(amounts, [10_000, 2_000, 2_000]);
+        assert_eq!(app.total_cents, 18_600);
+
+        app.pending_bills.clear();
+        app.config.max_bills_per_payment = 50;
+        app.add_payment(payment(2, 18_600));
+        assert_eq!(app.pending_bills.len(), 7);
+    }
+
+    #[test]
+    fn denominations_add_up_to_the_amount_largest_first() {
+        assert_eq!(denominate(24_000).collect::<Vec<_>>(), [10_000, 10_000, 2_000, 2_000]);
+        assert_eq!(denominate(12_600).collect::<Vec<_>>(), [10_000, 2_000, 500, 100]);
+        assert_eq!(denominate(100).collect::<Vec<_>>(), [100]);
+        for cents in [100, 700, 2_500, 9_900, 24_000, 123_400, 1_000_000] {
+            let bills: Vec<i64> = denominate(cents).collect();
+            assert_eq!(bills.iter().sum::<i64>(), cents, "{:?}", bills);
+            assert!(bills.windows(2).all(|pair| pair[0] >= pair[1]));
+        }
+        // Cents under the smallest bill, and refunds, make no bills
+        assert_eq!(denominate(1_299).sum::<i64>(), 1_200);
+        assert_eq!(denominate(99).count(), 0);
+        assert_eq!(denominate(-5_000).count(), 0);
+        // A huge amount is only as expensive as the bills taken
+        assert_eq!(denominate(i64::MAX).take(10).count(), 10);
     }
 }
@@ -0,0 +1,68 @@
+// Block-digit font for the kiosk-style total
+pub const BIG_ROWS: usize = 5;
+
+pub fn digit_rows(c: char) -> Option<[&'static str; BIG_ROWS]> {
+    Some(match c {
+        '0' => ["███", "█ █", "█ █", "█ █", "███"],
+        '1' => [" █ ", "██ ", " █ ", " █ ", "███"],
+        '2' => ["███", "  █", "███", "█  ", "███"],
+        '3' => ["███", "  █", "███", "  █", "███"],
+        '4' => ["█ █", "█ █", "███", "  █", "  █"],
+        '5' => ["███", "█  ", "███", "  █", "███"],
+        '6' => ["███", "█  ", "███", "█ █", "███"],
+        '7' => ["███", "  █", "  █", "  █", "  █"],
+        '8' => ["███", "█ █", "███", "█ █", "███"],
+        '9' => ["███", "█ █", "███", "  █", "███"],
+        '.' => ["   ", "   ", "   ", "   ", " █ "],
+        ',' => ["   ", "   ", "   ", " █ ", "█  "],
+        '-' => ["   ", "   ", "███", "   ", "   "],
+        ' ' => ["   ", "   ", "   ", "   ", "   "],
+        _ => return None,
+    })
+}
+
+// Characters outside the font (currency symbols) sit on the middle row
+pub fn render_big_number(text: &str) -> Vec<String> {
+    let mut rows = vec![String::new(); BIG_ROWS];
+    for (i, c) in text.chars().enumerate() {
+        for (r, row) in rows.iter_mut().enumerate() {
+            if i > 0 {
+                row.push(' ');
+            }
+            match digit_rows(c) {
+                Some(glyph) => row.push_str(glyph[r]),
+                None if r == BIG_ROWS / 2 => row.push(c),
+                None => row.push(' '),
+            }
+        }
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_digit_renders_as_its_glyph() {
+        let rows = render_big_number("1234");
+        assert_eq!(rows.len(), BIG_ROWS);
+        // Four three-wide glyphs with a space between each
+        assert!(rows.iter().all(|r| r.chars().count() == 4 * 3 + 3));
+        for (i, c) in "1234".chars().enumerate() {
+            let glyph = digit_rows(c).unwrap();
+            for (row, line) in rows.iter().enumerate() {
+                let cell: String = line.chars().skip(i * 4).take(3).collect();
+                assert_eq!(cell, glyph[row], "{} row {}", c, row);
+            }
+        }
+        assert!("0123456789.,- ".chars().all(|c| digit_rows(c).is_some()));
+    }
+
+    #[test]
+    fn symbols_outside_the_font_sit_on_the_middle_row() {
+        let rows = render_big_number("€5");
+        assert_eq!(rows[BIG_ROWS / 2].chars().next(), Some('€'));
+        assert!(rows.iter().enumerate().all(|(r, row)| r == BIG_ROWS / 2 || row.starts_with(' ')));
+    }
+}
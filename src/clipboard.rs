@@ -0,0 +1,12 @@
+// System clipboard access, compiled in with the `clipboard` feature
+#[cfg(feature = "clipboard")]
+pub fn copy(text: &str) -> anyhow::Result<()> {
+    let mut board = arboard::Clipboard::new()?;
+    board.set_text(text)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "clipboard"))]
+pub fn copy(_text: &str) -> anyhow::Result<()> {
+    anyhow::bail!("built without clipboard support")
+}
@@ -1,47 +1,1573 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
+use crate::app::{CURRENCIES, SECRET_PROVIDERS};
+use crate::milestones::Thresholds;
+use crate::money::MoneyFormat;
+use crate::psp::replay::ReplaySource;
+use crate::psp::registry::registry;
 use crate::psp::PspConfig;
+use crate::secrets::{self, SecretStore, KEYRING_PLACEHOLDER};
+use crate::tz::DisplayTz;
+
+pub const MIN_POLL_INTERVAL_SECS: u64 = 1;
+// A day back is plenty for any late-publishing provider
+pub const MAX_POLL_OVERLAP_SECS: u64 = 24 * 60 * 60;
+
+// Bump together with a step in migrate() whenever the on-disk shape changes
+pub const CONFIG_VERSION: u32 = 2;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
+    // Missing in pre-versioning (v0) files
+    #[serde(default)]
+    pub version: u32,
     pub currency: String,
     pub currency_symbol: String,
+    #[serde(default = "default_currency_decimals")]
+    pub currency_decimals: u32,
+    #[serde(default)]
+    pub symbol_position: SymbolPosition,
+    #[serde(default = "default_decimal_separator")]
+    pub decimal_separator: String,
+    #[serde(default = "default_thousands_separator")]
+    pub thousands_separator: String,
+    // A curated set offered in the picker after the built-in ones; edited by hand, never by setup
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_currencies: Vec<CustomCurrency>,
+    // Currencies added through setup, listed after the built-in and extra ones
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub custom_currencies: Vec<CustomCurrency>,
     pub providers: Vec<PspConfig>,
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+    // Each poll re-fetches this far behind the newest payment seen. Bigger costs more
+    // bandwidth but catches payments a provider publishes late or backdated; seen_ids
+    // drops the repeats either way
+    #[serde(default = "default_poll_overlap_secs")]
+    pub poll_overlap_secs: u64,
+    #[serde(default)]
+    pub poll_mode: PollMode,
+    // Bounds an adaptive poll interval moves between
+    #[serde(default = "default_adaptive_floor_secs")]
+    pub adaptive_floor_secs: u64,
+    #[serde(default = "default_adaptive_ceiling_secs")]
+    pub adaptive_ceiling_secs: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_bundle_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify_threshold_cents: Option<i64>,
+    // Shows the VAT inside totals, in basis points (2100 = 21%)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vat_rate_bps: Option<u32>,
+    // 1 unit of the keyed currency = rate units of `currency`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub fx_rates: HashMap<String, f64>,
+    #[serde(default)]
+    pub fx_online: bool,
+    #[serde(default)]
+    pub mismatched_currency: MismatchedCurrency,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub goal_cents: Option<i64>,
+    // IANA zone name for displayed times; system local time when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    // How long a provider may go without a successful poll before it counts as
+    // stale; three of its (slowest) intervals when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_stale_secs: Option<u64>,
+    // Start and end hour, in that zone, during which polling is paused
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quiet_hours: Option<(u8, u8)>,
+    // Bills queued per payment; the total always counts the full amount
+    #[serde(default = "default_max_bills_per_payment")]
+    pub max_bills_per_payment: usize,
+    // Share of the remaining distance a falling bill covers per tick, in (0, 1]
+    #[serde(default = "default_animation_speed")]
+    pub animation_speed: f64,
+    #[serde(default = "default_spawn_every_n_ticks")]
+    pub spawn_every_n_ticks: u32,
+    #[serde(default)]
+    pub spawn_style: SpawnStyle,
+    // Payments from one provider created within the same window of this many seconds
+    // share a single "×N" bill; unset gives each payment its own bills
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub combo_window_secs: Option<u32>,
+    // Payments kept in memory for the ledger; totals, and exports through the journal, count every payment
+    #[serde(default = "default_max_session_payments")]
+    pub max_session_payments: usize,
+    // Show the total in large block digits, for kiosk screens
+    #[serde(default)]
+    pub big_total: bool,
+    // One of theme::THEMES; unknown names draw with the default palette
+    #[serde(default = "default_theme")]
+    pub theme: String,
+    // Set once the first-run welcome has been shown
+    #[serde(default)]
+    pub seen_welcome: bool,
+    // Ring the terminal bell for new payments and celebrations
+    #[serde(default)]
+    pub sound: bool,
+    #[serde(default)]
+    pub on_full: OnFull,
+    // Share of the bill area settled bills fill before the screen counts as full, 10–100
+    #[serde(default = "default_fill_threshold_pct")]
+    pub fill_threshold_pct: u8,
+    // Off never celebrates; a full screen set to celebrate recycles instead
+    #[serde(default = "default_celebration_enabled")]
+    pub celebration_enabled: bool,
+    // Ticks before the celebration resets itself; null waits for Enter
+    #[serde(default = "default_celebration_duration_ticks")]
+    pub celebration_duration_ticks: Option<u32>,
+    // Each screen-full starts a new round from zero instead of carrying the total on
+    #[serde(default)]
+    pub reset_total_on_celebration: bool,
+    // Brief toasts for the first payment, a new largest one and the thresholds below
+    #[serde(default = "default_milestones")]
+    pub milestones: bool,
+    // A toast each time the total passes a multiple of this; null for none
+    #[serde(default = "default_milestone_every_cents")]
+    pub milestone_every_cents: Option<i64>,
+    // A toast every this many payments; null for none
+    #[serde(default = "default_milestone_every_payments")]
+    pub milestone_every_payments: Option<usize>,
+    // Simulated and imported sessions don't count toward saved stats; never saved
+    #[serde(skip)]
+    pub ephemeral: bool,
+    // Set from --seed for reproducible mock runs; never saved
+    #[serde(skip)]
+    pub mock_seed: Option<u64>,
+    // Set from --import; never saved
+    #[serde(skip)]
+    pub replay: Option<ReplaySource>,
+}
+
+fn default_poll_interval_secs() -> u64 {
+    10
+}
+
+fn default_poll_overlap_secs() -> u64 {
+    30
+}
+
+fn default_adaptive_floor_secs() -> u64 {
+    2
+}
+
+fn default_adaptive_ceiling_secs() -> u64 {
+    120
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomCurrency {
+    pub code: String,
+    pub symbol: String,
+    pub decimals: u32,
+    #[serde(default)]
+    pub symbol_position: SymbolPosition,
+    #[serde(default = "default_decimal_separator")]
+    pub decimal_separator: String,
+    #[serde(default = "default_thousands_separator")]
+    pub thousands_separator: String,
+}
+
+impl CustomCurrency {
+    // Prefix symbol, comma thousands and dot decimals unless configured otherwise
+    pub fn new(code: &str, symbol: &str, decimals: u32) -> Self {
+        Self {
+            code: code.to_string(),
+            symbol: symbol.to_string(),
+            decimals,
+            symbol_position: SymbolPosition::Prefix,
+            decimal_separator: default_decimal_separator(),
+            thousands_separator: default_thousands_separator(),
+        }
+    }
+}
+
+// How payments in a currency other than the session's are counted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MismatchedCurrency {
+    // Left out of the total, with a warning per currency
+    Skip,
+    // Added at face value, as if already in the session currency
+    Count,
+    // Converted with the FX rates, at face value when there's no rate
+    #[default]
+    Convert,
+}
+
+// What happens once settled bills reach the top of the screen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OnFull {
+    // Celebrate, then start a fresh stack
+    #[default]
+    Celebrate,
+    // Drop the oldest bills so the stack keeps moving
+    Recycle,
+    // Leave the stack as it is and stop adding bills
+    Stop,
+}
+
+// How the poll interval behaves over a session
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PollMode {
+    // Always the configured interval
+    #[default]
+    Fixed,
+    // Shorter while payments keep arriving, longer while a provider is quiet
+    Adaptive,
+}
+
+// How quickly queued bills are released
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpawnStyle {
+    // One bill every spawn_every_n_ticks
+    #[default]
+    Steady,
+    // Several at once while a big payment has the queue backed up
+    Burst,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SymbolPosition {
+    // €1,234.56
+    #[default]
+    Prefix,
+    // 1 234,56 kr
+    Suffix,
+}
+
+fn default_currency_decimals() -> u32 {
+    2
+}
+
+fn default_decimal_separator() -> String {
+    ".".to_string()
+}
+
+fn default_thousands_separator() -> String {
+    ",".to_string()
+}
+
+fn default_max_bills_per_payment() -> usize {
+    10
+}
+
+fn default_celebration_enabled() -> bool {
+    true
+}
+
+fn default_celebration_duration_ticks() -> Option<u32> {
+    Some(100)
+}
+
+fn default_milestones() -> bool {
+    true
+}
+
+fn default_milestone_every_cents() -> Option<i64> {
+    Some(100_000)
+}
+
+fn default_milestone_every_payments() -> Option<usize> {
+    Some(100)
+}
+
+fn default_theme() -> String {
+    "default".to_string()
+}
+
+fn default_max_session_payments() -> usize {
+    1_000
+}
+
+fn default_animation_speed() -> f64 {
+    0.3
+}
+
+fn default_spawn_every_n_ticks() -> u32 {
+    3
+}
+
+fn default_fill_threshold_pct() -> u8 {
+    100
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
+            version: CONFIG_VERSION,
             currency: "EUR".to_string(),
             currency_symbol: "€".to_string(),
+            currency_decimals: 2,
+            symbol_position: SymbolPosition::Prefix,
+            decimal_separator: default_decimal_separator(),
+            thousands_separator: default_thousands_separator(),
+            extra_currencies: Vec::new(),
+            custom_currencies: Vec::new(),
             providers: vec![PspConfig {
                 provider: "Mock".to_string(),
-                api_key: String::new(),
+                ..PspConfig::default()
             }],
+            poll_interval_secs: default_poll_interval_secs(),
+            poll_overlap_secs: default_poll_overlap_secs(),
+            poll_mode: PollMode::default(),
+            adaptive_floor_secs: default_adaptive_floor_secs(),
+            adaptive_ceiling_secs: default_adaptive_ceiling_secs(),
+            proxy: None,
+            ca_bundle_path: None,
+            notify_threshold_cents: None,
+            vat_rate_bps: None,
+            fx_rates: HashMap::new(),
+            fx_online: false,
+            mismatched_currency: MismatchedCurrency::default(),
+            goal_cents: None,
+            timezone: None,
+            health_stale_secs: None,
+            quiet_hours: None,
+            max_bills_per_payment: default_max_bills_per_payment(),
+            animation_speed: default_animation_speed(),
+            spawn_every_n_ticks: default_spawn_every_n_ticks(),
+            spawn_style: SpawnStyle::default(),
+            combo_window_secs: None,
+            max_session_payments: default_max_session_payments(),
+            big_total: false,
+            theme: default_theme(),
+            seen_welcome: false,
+            sound: false,
+            on_full: OnFull::default(),
+            fill_threshold_pct: default_fill_threshold_pct(),
+            celebration_enabled: default_celebration_enabled(),
+            celebration_duration_ticks: default_celebration_duration_ticks(),
+            reset_total_on_celebration: false,
+            milestones: default_milestones(),
+            milestone_every_cents: default_milestone_every_cents(),
+            milestone_every_payments: default_milestone_every_payments(),
+            ephemeral: false,
+            mock_seed: None,
+            replay: None,
+        }
+    }
+}
+
+impl AppConfig {
+    pub fn money_format(&self) -> MoneyFormat<'_> {
+        MoneyFormat {
+            symbol: &self.currency_symbol,
+            position: self.symbol_position,
+            decimal_separator: &self.decimal_separator,
+            thousands_separator: &self.thousands_separator,
+            // Configs saved before built-in currencies carried their decimals say 2 for JPY and KWD too
+            decimals: match crate::psp::minor_units(&self.currency) {
+                2 => self.currency_decimals,
+                units => units,
+            },
+        }
+    }
+
+    pub fn should_notify(&self, amount_cents: i64) -> bool {
+        self.notify_threshold_cents.is_some_and(|threshold| amount_cents >= threshold)
+    }
+
+    pub fn milestone_thresholds(&self) -> Thresholds {
+        Thresholds {
+            every_cents: self.milestone_every_cents,
+            every_payments: self.milestone_every_payments,
+        }
+    }
+
+    pub fn easing(&self) -> f64 {
+        if self.animation_speed.is_finite() && self.animation_speed > 0.0 {
+            self.animation_speed.min(1.0)
+        } else {
+            default_animation_speed()
+        }
+    }
+
+    pub fn spawn_every(&self) -> u32 {
+        self.spawn_every_n_ticks.max(1)
+    }
+
+    pub fn fill_threshold_pct(&self) -> u8 {
+        self.fill_threshold_pct.clamp(10, 100)
+    }
+
+    pub fn poll_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.poll_interval_secs.max(MIN_POLL_INTERVAL_SECS))
+    }
+
+    pub fn poll_overlap(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.poll_overlap_secs.min(MAX_POLL_OVERLAP_SECS) as i64)
+    }
+
+    // Floor and ceiling for adaptive polling; None in fixed mode
+    pub fn adaptive_bounds(&self) -> Option<(std::time::Duration, std::time::Duration)> {
+        if self.poll_mode != PollMode::Adaptive {
+            return None;
+        }
+        let floor = self.adaptive_floor_secs.max(MIN_POLL_INTERVAL_SECS);
+        let ceiling = self.adaptive_ceiling_secs.max(floor);
+        Some((std::time::Duration::from_secs(floor), std::time::Duration::from_secs(ceiling)))
+    }
+
+    // A copy safe to print: credentials cut to their last four characters, and
+    // any password in the proxy URL masked
+    pub fn redacted(&self) -> AppConfig {
+        let mut config = self.clone();
+        for p in &mut config.providers {
+            if p.provider != "Mock" {
+                p.api_key = secret_tail(&p.api_key);
+            }
+            // Kept in the keyring alongside the key, so treated as a credential too
+            p.merchant_account = p.merchant_account.as_deref().map(secret_tail);
+            p.api_secret = p.api_secret.as_deref().map(secret_tail);
+        }
+        if let Some(proxy) = config.proxy.as_mut() {
+            if let Ok(mut url) = reqwest::Url::parse(proxy) {
+                if url.password().is_some() && url.set_password(Some("****")).is_ok() {
+                    *proxy = url.to_string();
+                }
+            }
+        }
+        config
+    }
+
+    pub fn stale_after(&self, name: &str) -> chrono::Duration {
+        let secs = match self.health_stale_secs {
+            Some(secs) => secs,
+            None => {
+                let interval = self.poll_interval_for(name);
+                // An adaptive interval may have stretched as far as the ceiling
+                let slowest = self.adaptive_bounds().map_or(interval, |(_, ceiling)| ceiling.max(interval));
+                slowest.as_secs() * 3
+            }
+        };
+        chrono::Duration::seconds(secs.min(i64::MAX as u64 / 1000) as i64)
+    }
+
+    // Whether `at` falls inside the quiet hours, read in `tz`
+    pub fn is_quiet_at(&self, tz: DisplayTz, at: chrono::DateTime<chrono::Utc>) -> bool {
+        self.quiet_hours.is_some_and(|window| in_quiet_hours(window, tz.hour(at)))
+    }
+
+    // The provider's own override when it has one, by display name
+    pub fn poll_interval_for(&self, name: &str) -> std::time::Duration {
+        self.providers
+            .iter()
+            .find(|p| p.display_name() == name)
+            .and_then(|p| p.poll_interval_secs)
+            .map_or(self.poll_interval(), |secs| std::time::Duration::from_secs(secs.max(MIN_POLL_INTERVAL_SECS)))
+    }
+
+    // Everything wrong with a config at once, so a hand-edited file can be fixed in one go
+    pub fn validate(&self) -> std::result::Result<(), Vec<ConfigError>> {
+        let mut errors = Vec::new();
+        if self.providers.is_empty() {
+            errors.push(ConfigError::NoProviders);
+        }
+        for p in &self.providers {
+            let name = p.display_name();
+            // Mock has no credentials and Replay reads a file
+            if !matches!(p.provider.as_str(), "Mock" | "Replay") && p.api_key.trim().is_empty() {
+                errors.push(ConfigError::MissingApiKey(name.clone()));
+            }
+            if p.provider == "Adyen" && p.merchant_account.as_deref().is_none_or(|m| m.trim().is_empty()) {
+                errors.push(ConfigError::MissingMerchantAccount(name.clone()));
+            }
+            if p.poll_interval_secs.is_some_and(|secs| secs < 1) {
+                errors.push(ConfigError::ProviderPollIntervalTooShort(name.clone()));
+            }
+            if SECRET_PROVIDERS.contains(&p.provider.as_str()) && p.api_secret.as_deref().is_none_or(|s| s.trim().is_empty()) {
+                errors.push(ConfigError::MissingSecret(name));
+            }
+        }
+        let known = CURRENCIES.iter().any(|(code, _)| *code == self.currency)
+            || self.extra_currencies.iter().chain(&self.custom_currencies).any(|c| c.code == self.currency);
+        if !known {
+            errors.push(ConfigError::UnknownCurrency(self.currency.clone()));
+        }
+        if self.poll_interval_secs < 1 {
+            errors.push(ConfigError::PollIntervalTooShort);
+        }
+        if self.poll_mode == PollMode::Adaptive && (self.adaptive_floor_secs < 1 || self.adaptive_floor_secs > self.adaptive_ceiling_secs) {
+            errors.push(ConfigError::AdaptiveBounds);
+        }
+        if self.quiet_hours.is_some_and(|(start, end)| start > 23 || end > 23) {
+            errors.push(ConfigError::QuietHours);
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
         }
     }
 }
 
-fn config_path() -> PathBuf {
-    let dir = dirs::config_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join("profit-cli");
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    NoProviders,
+    MissingApiKey(String),
+    MissingMerchantAccount(String),
+    MissingSecret(String),
+    UnknownCurrency(String),
+    PollIntervalTooShort,
+    ProviderPollIntervalTooShort(String),
+    AdaptiveBounds,
+    QuietHours,
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::NoProviders => write!(f, "No providers are configured"),
+            ConfigError::MissingApiKey(p) => write!(f, "{} provider is missing an API key", p),
+            ConfigError::MissingMerchantAccount(p) => write!(f, "{} provider is missing a merchant account", p),
+            ConfigError::MissingSecret(p) => write!(f, "{} provider is missing its secret", p),
+            ConfigError::UnknownCurrency(c) => write!(f, "Currency {} is not built in, nor in extra_currencies or custom_currencies", c),
+            ConfigError::PollIntervalTooShort => write!(f, "poll_interval_secs must be at least 1"),
+            ConfigError::ProviderPollIntervalTooShort(p) => write!(f, "{} provider's poll_interval_secs must be at least 1", p),
+            ConfigError::AdaptiveBounds => write!(f, "adaptive_floor_secs must be at least 1 and no more than adaptive_ceiling_secs"),
+            ConfigError::QuietHours => write!(f, "quiet_hours must be two hours from 0 to 23"),
+        }
+    }
+}
+
+// Fixed-width, so a long key doesn't push the line off screen: "****abcd"
+pub fn secret_tail(secret: &str) -> String {
+    let len = secret.chars().count();
+    if len == 0 {
+        return "(none)".to_string();
+    }
+    if len <= 4 {
+        return "****".to_string();
+    }
+    format!("****{}", secret.chars().skip(len - 4).collect::<String>())
+}
+
+// Quiet from `start` up to, but not including, `end`. A window whose start is
+// later than its end runs past midnight; equal hours mean no quiet time at all
+pub fn in_quiet_hours((start, end): (u8, u8), hour: u32) -> bool {
+    let (start, end) = (start as u32, end as u32);
+    if start <= end {
+        (start..end).contains(&hour)
+    } else {
+        hour >= start || hour < end
+    }
+}
+
+pub fn describe_errors(errors: &[ConfigError]) -> String {
+    errors.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+}
+
+// Set once at startup from --config-dir or PROFIT_CONFIG_DIR
+static CONFIG_DIR_OVERRIDE: std::sync::OnceLock<PathBuf> = std::sync::OnceLock::new();
+
+// --config-dir wins over PROFIT_CONFIG_DIR; either must be a directory we can write to
+pub fn set_config_dir(flag: Option<&Path>) -> Result<()> {
+    let env = std::env::var_os("PROFIT_CONFIG_DIR").filter(|d| !d.is_empty()).map(PathBuf::from);
+    let Some(dir) = flag.map(Path::to_path_buf).or(env) else {
+        return Ok(());
+    };
+    std::fs::create_dir_all(&dir).with_context(|| format!("cannot create config directory {}", dir.display()))?;
+    let probe = dir.join(".write-test");
+    std::fs::write(&probe, b"").with_context(|| format!("config directory {} is not writable", dir.display()))?;
+    std::fs::remove_file(&probe).ok();
+    CONFIG_DIR_OVERRIDE.set(dir).ok();
+    Ok(())
+}
+
+// The platform config directory's profit-cli folder, unless overridden
+pub fn config_dir() -> PathBuf {
+    let dir = match CONFIG_DIR_OVERRIDE.get() {
+        Some(dir) => dir.clone(),
+        None => dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("profit-cli"),
+    };
     std::fs::create_dir_all(&dir).ok();
-    dir.join("config.json")
+    dir
+}
+
+// Keeps tests that touch the profile files out of the real config directory
+#[cfg(test)]
+pub(crate) fn use_temp_config_dir() {
+    CONFIG_DIR_OVERRIDE.get_or_init(|| std::env::temp_dir().join(format!("profit-cli-test-{}", std::process::id())));
+}
+
+// Set once at startup from --profile
+static PROFILE: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+
+pub fn set_profile(name: &str) -> Result<()> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        anyhow::bail!("profile name '{}' may only use letters, digits, '-' and '_'", name);
+    }
+    PROFILE.set(name.to_string()).ok();
+    Ok(())
+}
+
+// "config.json" for the default profile, "config-<name>.json" for a named one
+pub fn profile_file(stem: &str) -> PathBuf {
+    match PROFILE.get() {
+        Some(name) => config_dir().join(format!("{}-{}.json", stem, name)),
+        None => config_dir().join(format!("{}.json", stem)),
+    }
+}
+
+// Set once at startup from --config
+static CONFIG_FILE_OVERRIDE: std::sync::OnceLock<PathBuf> = std::sync::OnceLock::new();
+
+pub fn set_config_file(path: &Path) -> Result<()> {
+    let json = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("json"));
+    if !json && !is_toml(path) {
+        anyhow::bail!("config file {} must end in .json or .toml", path.display());
+    }
+    CONFIG_FILE_OVERRIDE.set(path.to_path_buf()).ok();
+    Ok(())
+}
+
+// --config wins; otherwise a hand-written config.toml is read in place of config.json
+pub fn config_path() -> PathBuf {
+    if let Some(path) = CONFIG_FILE_OVERRIDE.get() {
+        return path.clone();
+    }
+    let json = profile_file("config");
+    let toml = json.with_extension("toml");
+    if toml.exists() {
+        toml
+    } else {
+        json
+    }
 }
 
-pub fn load_config() -> Option<AppConfig> {
+fn is_toml(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("toml"))
+}
+
+// config.json.bak, or config.toml.bak for a TOML config
+pub fn backup_path() -> PathBuf {
     let path = config_path();
-    let data = std::fs::read_to_string(path).ok()?;
-    serde_json::from_str(&data).ok()
+    let ext = path.extension().map(|e| e.to_string_lossy().into_owned()).unwrap_or_default();
+    path.with_extension(format!("{}.bak", ext))
 }
 
-pub fn save_config(config: &AppConfig) -> Result<()> {
+// Keyring entries are shared across the directory, so named profiles get their own
+fn secret_account(name: &str) -> String {
+    match PROFILE.get() {
+        Some(profile) => format!("{}/{}", profile, name),
+        None => name.to_string(),
+    }
+}
+
+// Where the raw config JSON is kept. The binary uses the file; anything that
+// shouldn't touch the user's real config can swap in another store
+pub trait ConfigStore {
+    // Ok(None) when nothing has been saved yet
+    fn load(&self) -> Result<Option<String>>;
+    fn save(&self, data: &str) -> Result<()>;
+    // Names the config in error messages
+    fn describe(&self) -> String;
+}
+
+pub struct FileConfigStore {
+    pub path: PathBuf,
+}
+
+impl Default for FileConfigStore {
+    fn default() -> Self {
+        Self { path: config_path() }
+    }
+}
+
+// A .toml path is read as TOML and handed on as JSON, and written back as TOML.
+// Writing it back drops any comments the file had
+impl ConfigStore for FileConfigStore {
+    fn load(&self) -> Result<Option<String>> {
+        let data = match std::fs::read_to_string(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(anyhow::Error::new(e).context(format!("failed to read {}", self.path.display()))),
+        };
+        if !is_toml(&self.path) {
+            return Ok(Some(data));
+        }
+        let raw = toml_to_json(&data).with_context(|| format!("{} is not valid TOML", self.path.display()))?;
+        Ok(Some(raw.to_string()))
+    }
+
+    fn save(&self, data: &str) -> Result<()> {
+        if !is_toml(&self.path) {
+            return write_atomic(&self.path, data.as_bytes());
+        }
+        let raw: serde_json::Value = serde_json::from_str(data)?;
+        let toml = json_to_toml(&raw).with_context(|| format!("cannot write {} as TOML", self.path.display()))?;
+        // Settings left out for being null must come back as the same config
+        let written = serde_json::to_value(migrate(toml_to_json(&toml)?)?)?;
+        if written != serde_json::to_value(migrate(raw)?)? {
+            anyhow::bail!("cannot write {} as TOML without changing a setting; use config.json", self.path.display());
+        }
+        write_atomic(&self.path, format!("{}{}", TOML_HEADER, toml).as_bytes())
+    }
+
+    fn describe(&self) -> String {
+        self.path.display().to_string()
+    }
+}
+
+// The same shape as config.json. Stamped with the current version when it has none,
+// since no TOML config predates the migrations
+fn toml_to_json(data: &str) -> Result<serde_json::Value> {
+    let doc = toml_edit::Document::parse(data)?;
+    let mut raw = toml_item(doc.as_item());
+    if raw.get("version").is_none() {
+        raw["version"] = CONFIG_VERSION.into();
+    }
+    Ok(raw)
+}
+
+const TOML_HEADER: &str = "# Saved by profit-cli; comments in the previous file were not kept\n";
+
+// Plain keys and values first, then [tables], then [[arrays of tables]], so every key lands
+// under the right header. Nulls are left out, which TOML can't spell
+fn json_to_toml(raw: &serde_json::Value) -> Result<String> {
+    let serde_json::Value::Object(root) = raw else {
+        anyhow::bail!("config is not a JSON object");
+    };
+    let mut out = String::new();
+    write_toml_table(&mut out, "", root)?;
+    Ok(out)
+}
+
+fn write_toml_table(out: &mut String, path: &str, table: &serde_json::Map<String, serde_json::Value>) -> Result<()> {
+    let is_table = |v: &serde_json::Value| v.as_object().is_some_and(|o| !o.is_empty());
+    let is_table_array =
+        |v: &serde_json::Value| v.as_array().is_some_and(|a| !a.is_empty() && a.iter().all(serde_json::Value::is_object));
+    for (key, value) in table {
+        if !value.is_null() && !is_table(value) && !is_table_array(value) {
+            out.push_str(&format!("{} = {}\n", toml_key(key), toml_inline(value)?));
+        }
+    }
+    for (key, value) in table {
+        if let Some(sub) = value.as_object().filter(|_| is_table(value)) {
+            let sub_path = format!("{}{}", path, toml_key(key));
+            out.push_str(&format!("\n[{}]\n", sub_path));
+            write_toml_table(out, &format!("{}.", sub_path), sub)?;
+        }
+    }
+    for (key, value) in table {
+        if let Some(items) = value.as_array().filter(|_| is_table_array(value)) {
+            let sub_path = format!("{}{}", path, toml_key(key));
+            for item in items.iter().filter_map(serde_json::Value::as_object) {
+                out.push_str(&format!("\n[[{}]]\n", sub_path));
+                write_toml_table(out, &format!("{}.", sub_path), item)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn toml_key(key: &str) -> String {
+    if !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        key.to_string()
+    } else {
+        serde_json::Value::from(key).to_string()
+    }
+}
+
+// JSON string escapes are all valid in TOML basic strings
+fn toml_inline(value: &serde_json::Value) -> Result<String> {
+    Ok(match value {
+        serde_json::Value::Null => anyhow::bail!("TOML has no null for a list entry"),
+        serde_json::Value::Bool(_) | serde_json::Value::Number(_) | serde_json::Value::String(_) => value.to_string(),
+        serde_json::Value::Array(items) => {
+            format!("[{}]", items.iter().map(toml_inline).collect::<Result<Vec<_>>>()?.join(", "))
+        }
+        serde_json::Value::Object(table) => {
+            let fields = table
+                .iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| Ok(format!("{} = {}", toml_key(k), toml_inline(v)?)))
+                .collect::<Result<Vec<_>>>()?;
+            if fields.is_empty() {
+                "{}".to_string()
+            } else {
+                format!("{{ {} }}", fields.join(", "))
+            }
+        }
+    })
+}
+
+fn toml_item(item: &toml_edit::Item) -> serde_json::Value {
+    match item {
+        toml_edit::Item::None => serde_json::Value::Null,
+        toml_edit::Item::Value(value) => toml_value(value),
+        toml_edit::Item::Table(table) => toml_table(table),
+        toml_edit::Item::ArrayOfTables(tables) => tables.iter().map(toml_table).collect(),
+    }
+}
+
+fn toml_table(table: &toml_edit::Table) -> serde_json::Value {
+    table.iter().map(|(k, v)| (k.to_string(), toml_item(v))).collect()
+}
+
+fn toml_value(value: &toml_edit::Value) -> serde_json::Value {
+    match value {
+        toml_edit::Value::String(s) => s.value().as_str().into(),
+        toml_edit::Value::Integer(i) => (*i.value()).into(),
+        toml_edit::Value::Float(f) => (*f.value()).into(),
+        toml_edit::Value::Boolean(b) => (*b.value()).into(),
+        toml_edit::Value::Datetime(d) => d.value().to_string().into(),
+        toml_edit::Value::Array(items) => items.iter().map(toml_value).collect(),
+        toml_edit::Value::InlineTable(table) => table.iter().map(|(k, v)| (k.to_string(), toml_value(v))).collect(),
+    }
+}
+
+// Holds the config in memory only, e.g. for tests
+#[derive(Default)]
+pub struct InMemoryConfigStore {
+    data: std::sync::Mutex<Option<String>>,
+}
+
+impl InMemoryConfigStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ConfigStore for InMemoryConfigStore {
+    fn load(&self) -> Result<Option<String>> {
+        Ok(self.data.lock().unwrap_or_else(|e| e.into_inner()).clone())
+    }
+
+    fn save(&self, data: &str) -> Result<()> {
+        *self.data.lock().unwrap_or_else(|e| e.into_inner()) = Some(data.to_string());
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        "in-memory config".to_string()
+    }
+}
+
+// Ok(None) when there's no config file yet; an error when there is one but it
+// can't be read or parsed, so it isn't mistaken for a first run and overwritten
+pub fn load_config() -> Result<Option<AppConfig>> {
+    load_config_from(&FileConfigStore::default(), secrets::active_store().as_ref())
+}
+
+pub fn load_config_from(store: &dyn ConfigStore, secrets: &dyn SecretStore) -> Result<Option<AppConfig>> {
+    let Some(data) = store.load()? else {
+        return Ok(None);
+    };
+    let raw: serde_json::Value =
+        serde_json::from_str(&data).with_context(|| format!("{} is not valid JSON", store.describe()))?;
+    let from_version = raw_version(&raw);
+    let mut config = migrate(raw).with_context(|| format!("{} could not be loaded", store.describe()))?;
+    hydrate_secrets(&mut config, secrets)?;
+    if from_version < 2 {
+        split_legacy_adyen_keys(&mut config);
+    }
+    merge_duplicate_providers(&mut config);
+    if from_version < CONFIG_VERSION {
+        save_config_to(store, &config, secrets).ok();
+    }
+    Ok(Some(config))
+}
+
+// Moves an unreadable config aside so setup can write a fresh one
+pub fn backup_config() -> Result<PathBuf> {
     let path = config_path();
-    let data = serde_json::to_string_pretty(config)?;
-    std::fs::write(path, data)?;
+    let backup = backup_path();
+    std::fs::rename(&path, &backup)?;
+    Ok(backup)
+}
+
+fn raw_version(raw: &serde_json::Value) -> u32 {
+    raw.get("version").and_then(|v| v.as_u64()).unwrap_or(0) as u32
+}
+
+// Upgrade a config of any older version to the current shape, one step at a time
+pub fn migrate(mut raw: serde_json::Value) -> Result<AppConfig> {
+    if !raw.is_object() {
+        anyhow::bail!("config is not a JSON object");
+    }
+    let version = raw_version(&raw);
+    if version > CONFIG_VERSION {
+        anyhow::bail!("config version {} is newer than this build supports ({})", version, CONFIG_VERSION);
+    }
+
+    // v0 → v1: stamp the version; new fields are filled by serde defaults
+    if version < 1 {
+        raw["version"] = 1.into();
+    }
+
+    // v1 → v2: Adyen's merchant account moves out of the "key|merchant" api_key
+    if version < 2 {
+        if let Some(providers) = raw["providers"].as_array_mut() {
+            for p in providers {
+                if p["provider"] != "Adyen" {
+                    continue;
+                }
+                let packed = p["api_key"].as_str().and_then(|k| k.split_once('|'));
+                if let Some((key, merchant)) = packed.map(|(k, m)| (k.to_string(), m.to_string())) {
+                    p["api_key"] = key.into();
+                    p["merchant_account"] = merchant.into();
+                }
+            }
+        }
+        raw["version"] = 2.into();
+    }
+
+    let mut config: AppConfig = serde_json::from_value(raw)?;
+    config.version = CONFIG_VERSION;
+    Ok(config)
+}
+
+// Keys stored in the keyring before v2 still carry the packed merchant
+fn split_legacy_adyen_keys(config: &mut AppConfig) {
+    for p in &mut config.providers {
+        if p.provider == "Adyen" && p.merchant_account.is_none() {
+            if let Some((key, merchant)) = p.api_key.split_once('|') {
+                let (key, merchant) = (key.to_string(), merchant.to_string());
+                p.api_key = key;
+                p.merchant_account = Some(merchant);
+            }
+        }
+    }
+}
+
+// Entries with the same name and credentials would fetch every payment twice, so only
+// the first is kept. Accounts that differ in label or any credential stay apart
+fn merge_duplicate_providers(config: &mut AppConfig) {
+    let mut seen = std::collections::HashSet::new();
+    config.providers.retain(|p| {
+        let identity = (p.display_name(), p.api_key.clone(), p.merchant_account.clone(), p.api_secret.clone());
+        let first = seen.insert(identity);
+        if !first {
+            tracing::warn!("ignoring duplicate provider entry {}", p.display_name());
+        }
+        first
+    });
+}
+
+// Saved config with environment overrides applied (env > config.json).
+// Returns None only when there's neither a config file nor any provider
+// resolvable from the environment.
+pub fn resolve_config() -> Result<Option<AppConfig>> {
+    let loaded = load_config()?;
+    let found = loaded.is_some();
+    let mut config = loaded.unwrap_or_else(|| AppConfig {
+        providers: Vec::new(),
+        ..AppConfig::default()
+    });
+    apply_env_overrides(&mut config);
+    if found || !config.providers.is_empty() {
+        Ok(Some(config))
+    } else {
+        Ok(None)
+    }
+}
+
+// The config fields a provider needs, each with the environment variable that can supply it
+pub fn credential_fields(provider: &str) -> Vec<(&'static str, String)> {
+    if provider == "Mock" {
+        return Vec::new();
+    }
+    let prefix = format!("PROFIT_{}", provider.to_uppercase());
+    let mut fields = vec![("api_key", format!("{}_API_KEY", prefix))];
+    if provider == "Adyen" {
+        fields.push(("merchant_account", format!("{}_MERCHANT", prefix)));
+    }
+    if SECRET_PROVIDERS.contains(&provider) {
+        fields.push(("api_secret", format!("{}_SECRET", prefix)));
+    }
+    fields
+}
+
+// PROFIT_<PROVIDER>_API_KEY (plus PROFIT_ADYEN_MERCHANT / PROFIT_<PROVIDER>_SECRET)
+// replace the stored credentials, adding the provider when it isn't configured yet
+fn apply_env_overrides(config: &mut AppConfig) {
+    for name in registry().setup_names().iter().filter(|n| **n != "Mock") {
+        let prefix = format!("PROFIT_{}", name.to_uppercase());
+        let Some(key) = env_var(&format!("{}_API_KEY", prefix)) else { continue };
+        // Labelled extra accounts keep their stored credentials
+        let existing = config.providers.iter().position(|p| p.provider == *name && p.label.is_none());
+
+        let stored_merchant = existing.and_then(|i| config.providers[i].merchant_account.clone());
+        let merchant_account = env_var(&format!("{}_MERCHANT", prefix)).or(stored_merchant);
+        if *name == "Adyen" && merchant_account.is_none() {
+            continue;
+        }
+        let stored_secret = existing.and_then(|i| config.providers[i].api_secret.clone());
+        let api_secret = env_var(&format!("{}_SECRET", prefix)).or(stored_secret);
+        if SECRET_PROVIDERS.contains(name) && api_secret.is_none() {
+            continue;
+        }
+
+        match existing {
+            Some(i) => {
+                config.providers[i].api_key = key;
+                config.providers[i].merchant_account = merchant_account;
+                config.providers[i].api_secret = api_secret;
+            }
+            None => config.providers.push(PspConfig {
+                provider: name.to_string(),
+                api_key: key,
+                merchant_account,
+                api_secret,
+                ..PspConfig::default()
+            }),
+        }
+    }
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.trim().is_empty())
+}
+
+pub fn save_config(config: &AppConfig) -> Result<()> {
+    save_config_to(&FileConfigStore::default(), config, secrets::active_store().as_ref())
+}
+
+pub fn save_config_to(store: &dyn ConfigStore, config: &AppConfig, secrets: &dyn SecretStore) -> Result<()> {
+    let stored = stash_secrets(config, secrets);
+    let data = serde_json::to_string_pretty(&stored)?;
+    store.save(&data)
+}
+
+// Write to a sibling temp file and rename it over the target, so a crash
+// mid-write leaves the previous file intact
+pub fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp = PathBuf::from(tmp_name);
+
+    let mut file = std::fs::File::create(&tmp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    drop(file);
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+// Move API keys into the secret store, leaving placeholders behind.
+// Keys the store can't take stay in plaintext so nothing is lost.
+fn stash_secrets(config: &AppConfig, store: &dyn SecretStore) -> AppConfig {
+    let mut stored = config.clone();
+    for p in &mut stored.providers {
+        let account = secret_account(&p.display_name());
+        stash_secret(store, &account, &mut p.api_key);
+        if let Some(merchant) = p.merchant_account.as_mut() {
+            stash_secret(store, &format!("{}:merchant", account), merchant);
+        }
+        if let Some(secret) = p.api_secret.as_mut() {
+            stash_secret(store, &format!("{}:secret", account), secret);
+        }
+    }
+    stored
+}
+
+fn stash_secret(store: &dyn SecretStore, account: &str, secret: &mut String) {
+    if secret.is_empty() || secret == KEYRING_PLACEHOLDER {
+        return;
+    }
+    if store.set(account, secret).is_ok() {
+        *secret = KEYRING_PLACEHOLDER.to_string();
+    }
+}
+
+fn hydrate_secrets(config: &mut AppConfig, store: &dyn SecretStore) -> Result<()> {
+    for p in &mut config.providers {
+        let name = p.display_name();
+        let account = secret_account(&name);
+        hydrate_secret(store, &name, "API key", &account, &mut p.api_key)?;
+        if let Some(merchant) = p.merchant_account.as_mut() {
+            hydrate_secret(store, &name, "merchant account", &format!("{}:merchant", account), merchant)?;
+        }
+        if let Some(secret) = p.api_secret.as_mut() {
+            hydrate_secret(store, &name, "API secret", &format!("{}:secret", account), secret)?;
+        }
+    }
     Ok(())
 }
 
+// A placeholder whose entry is gone (or can't be read) is an error, not an empty credential
+fn hydrate_secret(store: &dyn SecretStore, provider: &str, field: &str, account: &str, secret: &mut String) -> Result<()> {
+    if secret != KEYRING_PLACEHOLDER {
+        return Ok(());
+    }
+    let hint = "enter it again in setup, or run with --no-keyring to keep keys in the config file";
+    match store.get(account) {
+        Ok(Some(value)) => {
+            *secret = value;
+            Ok(())
+        }
+        Ok(None) => anyhow::bail!("the {} {} is missing from the keyring; {}", provider, field, hint),
+        Err(e) => Err(e.context(format!("cannot read the {} {} from the keyring; {}", provider, field, hint))),
+    }
+}
+
+// Removes a provider's keys from the secret store, returning the accounts that held one
+pub fn forget_secrets(provider: &PspConfig, store: &dyn SecretStore) -> Result<Vec<String>> {
+    let account = secret_account(&provider.display_name());
+    let mut removed = Vec::new();
+    for name in [format!("{}:merchant", account), format!("{}:secret", account), account] {
+        if store.delete(&name).with_context(|| format!("cannot remove keyring entry {}", name))? {
+            removed.push(name);
+        }
+    }
+    Ok(removed)
+}
+
+// Drops the provider with this display name (any case) from the config, keys first so
+// none are left behind. Returns its name and the keyring entries cleared
+pub fn forget_provider(name: &str) -> Result<(String, Vec<String>)> {
+    let store = secrets::active_store();
+    let Some(mut config) = load_config_from(&FileConfigStore::default(), store.as_ref())? else {
+        anyhow::bail!("No configuration found at {}", config_path().display());
+    };
+    let Some(index) = config.providers.iter().position(|p| p.display_name().eq_ignore_ascii_case(name.trim())) else {
+        let names: Vec<String> = config.providers.iter().map(|p| p.display_name()).collect();
+        anyhow::bail!("No provider named {} (configured: {})", name, names.join(", "));
+    };
+    let provider = config.providers.remove(index);
+    let cleared = forget_secrets(&provider, store.as_ref())?;
+    save_config_to(&FileConfigStore::default(), &config, store.as_ref())?;
+    Ok((provider.display_name(), cleared))
+}
+
+// The profile's files: config in either form, backups (which may hold keys), session state and stats
+pub fn profile_data_files() -> Vec<PathBuf> {
+    let json = profile_file("config");
+    let toml = json.with_extension("toml");
+    let mut files = vec![json.with_extension("json.bak"), toml.with_extension("toml.bak"), json, toml];
+    let config = config_path();
+    if !files.contains(&config) {
+        files.extend([backup_path(), config]);
+    }
+    files.extend([profile_file("state"), crate::state::journal_path(), profile_file("stats")]);
+    files
+}
+
+// Clears every configured provider's keys and deletes the profile's files, returning
+// what was removed. A config that can't be read is still deleted
+pub fn reset_profile() -> Result<Vec<String>> {
+    let store = secrets::active_store();
+    let mut removed = Vec::new();
+    if let Ok(Some(config)) = load_config_from(&FileConfigStore::default(), store.as_ref()) {
+        for p in &config.providers {
+            let cleared = forget_secrets(p, store.as_ref())?;
+            removed.extend(cleared.into_iter().map(|account| format!("keyring entry {}", account)));
+        }
+    }
+    for path in profile_data_files() {
+        match std::fs::remove_file(&path) {
+            Ok(()) => removed.push(path.display().to_string()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e).with_context(|| format!("cannot remove {}", path.display())),
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secrets::{InMemorySecretStore, PlaintextStore};
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("profit-cli-test-{}-{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    const TOML_CONFIG: &str = r#"
+# Hand-written
+currency = "NOK"
+currency_symbol = "kr"
+symbol_position = "suffix"
+decimal_separator = ","
+thousands_separator = " "
+
+[fx_rates]
+EUR = 11.5
+
+[[providers]]
+provider = "Adyen"
+api_key = "test_key"
+merchant_account = "Shop"
+label = "EU"
+
+[[providers]]
+provider = "Mock"
+api_key = ""
+"#;
+
+    fn json_config() -> String {
+        serde_json::json!({
+            "version": CONFIG_VERSION,
+            "currency": "NOK",
+            "currency_symbol": "kr",
+            "symbol_position": "suffix",
+            "decimal_separator": ",",
+            "thousands_separator": " ",
+            "fx_rates": { "EUR": 11.5 },
+            "providers": [
+                { "provider": "Adyen", "api_key": "test_key", "merchant_account": "Shop", "label": "EU" },
+                { "provider": "Mock", "api_key": "" },
+            ],
+        })
+        .to_string()
+    }
+
+    fn load(path: &Path) -> AppConfig {
+        load_config_from(&FileConfigStore { path: path.to_path_buf() }, &PlaintextStore).unwrap().unwrap()
+    }
+
+    #[test]
+    fn json_and_toml_load_to_the_same_config() {
+        let dir = scratch("json-toml");
+        std::fs::write(dir.join("config.json"), json_config()).unwrap();
+        std::fs::write(dir.join("config.toml"), TOML_CONFIG).unwrap();
+        let json = load(&dir.join("config.json"));
+        let toml = load(&dir.join("config.toml"));
+        assert_eq!(serde_json::to_value(&json).unwrap(), serde_json::to_value(&toml).unwrap());
+        assert_eq!(toml.providers.len(), 2);
+        assert_eq!(toml.money_format().thousands_separator, " ");
+    }
+
+    #[test]
+    fn toml_config_is_saved_back_as_toml() {
+        let dir = scratch("toml-save");
+        let path = dir.join("config.toml");
+        std::fs::write(&path, TOML_CONFIG).unwrap();
+        let store = FileConfigStore { path: path.clone() };
+        let mut config = load(&path);
+        config.currency_symbol = "NOK".to_string();
+        save_config_to(&store, &config, &PlaintextStore).unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written.contains("currency_symbol = \"NOK\""));
+        assert!(written.contains("[[providers]]"));
+        let reloaded = load(&path);
+        assert_eq!(serde_json::to_value(&config).unwrap(), serde_json::to_value(&reloaded).unwrap());
+    }
+
+    #[test]
+    fn toml_save_refuses_a_setting_it_would_lose() {
+        let dir = scratch("toml-null");
+        let path = dir.join("config.toml");
+        std::fs::write(&path, TOML_CONFIG).unwrap();
+        // Left out as null, this would come back as the default of every 100 payments
+        let config = AppConfig { milestone_every_payments: None, ..load(&path) };
+        assert!(save_config_to(&FileConfigStore { path: path.clone() }, &config, &PlaintextStore).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), TOML_CONFIG);
+    }
+
+    fn two_providers() -> AppConfig {
+        AppConfig {
+            providers: vec![
+                PspConfig {
+                    provider: "Adyen".to_string(),
+                    api_key: "adyen_key".to_string(),
+                    merchant_account: Some("Shop".to_string()),
+                    ..PspConfig::default()
+                },
+                PspConfig {
+                    provider: "PayPal".to_string(),
+                    api_key: "client_id".to_string(),
+                    api_secret: Some("client_secret".to_string()),
+                    label: Some("US".to_string()),
+                    ..PspConfig::default()
+                },
+            ],
+            ..AppConfig::default()
+        }
+    }
+
+    #[test]
+    fn in_memory_round_trip_keeps_keys_out_of_the_config() {
+        let store = InMemoryConfigStore::new();
+        let secrets = InMemorySecretStore::new();
+        assert!(load_config_from(&store, &secrets).unwrap().is_none());
+
+        let config = two_providers();
+        save_config_to(&store, &config, &secrets).unwrap();
+        let saved = store.load().unwrap().unwrap();
+        for secret in ["adyen_key", "Shop", "client_id", "client_secret"] {
+            assert!(!saved.contains(secret), "{} was written to the config", secret);
+        }
+        assert_eq!(secrets.get(&secret_account("PayPal (US)")).unwrap().as_deref(), Some("client_id"));
+
+        let loaded = load_config_from(&store, &secrets).unwrap().unwrap();
+        assert_eq!(serde_json::to_value(&loaded).unwrap(), serde_json::to_value(&config).unwrap());
+    }
+
+    #[test]
+    fn in_memory_legacy_config_is_migrated_and_saved_back() {
+        let store = InMemoryConfigStore::new();
+        let secrets = InMemorySecretStore::new();
+        let legacy = serde_json::json!({
+            "version": 1,
+            "currency": "EUR",
+            "currency_symbol": "€",
+            "poll_interval_secs": 5,
+            "providers": [{ "provider": "Adyen", "api_key": "adyen_key|Shop" }],
+        });
+        store.save(&legacy.to_string()).unwrap();
+
+        let loaded = load_config_from(&store, &secrets).unwrap().unwrap();
+        assert_eq!(loaded.providers[0].api_key, "adyen_key");
+        assert_eq!(loaded.providers[0].merchant_account.as_deref(), Some("Shop"));
+        let saved: serde_json::Value = serde_json::from_str(&store.load().unwrap().unwrap()).unwrap();
+        assert_eq!(raw_version(&saved), CONFIG_VERSION);
+        assert_eq!(saved["providers"][0]["api_key"], KEYRING_PLACEHOLDER);
+    }
+
+    #[test]
+    fn forgetting_a_provider_clears_its_secrets() {
+        let secrets = InMemorySecretStore::new();
+        let config = two_providers();
+        save_config_to(&InMemoryConfigStore::new(), &config, &secrets).unwrap();
+        assert_eq!(forget_secrets(&config.providers[1], &secrets).unwrap().len(), 2);
+        assert!(secrets.get(&secret_account("PayPal (US)")).unwrap().is_none());
+        assert!(secrets.get(&secret_account("Adyen")).unwrap().is_some());
+    }
+
+    fn valid_config() -> AppConfig {
+        AppConfig {
+            currency: "EUR".to_string(),
+            providers: vec![
+                PspConfig { provider: "Mock".to_string(), ..PspConfig::default() },
+                PspConfig {
+                    provider: "Adyen".to_string(),
+                    api_key: "adyen_key".to_string(),
+                    merchant_account: Some("Shop".to_string()),
+                    ..PspConfig::default()
+                },
+            ],
+            ..AppConfig::default()
+        }
+    }
+
+    #[test]
+    fn valid_config_passes_validation() {
+        assert_eq!(valid_config().validate(), Ok(()));
+    }
+
+    #[test]
+    fn each_invalid_setting_is_reported() {
+        let check = |edit: &dyn Fn(&mut AppConfig)| {
+            let mut config = valid_config();
+            edit(&mut config);
+            config.validate().unwrap_err()
+        };
+        assert_eq!(check(&|c| c.providers.clear()), [ConfigError::NoProviders]);
+        assert_eq!(check(&|c| c.providers[1].api_key = " ".to_string()), [ConfigError::MissingApiKey("Adyen".to_string())]);
+        assert_eq!(check(&|c| c.providers[1].merchant_account = None), [ConfigError::MissingMerchantAccount("Adyen".to_string())]);
+        assert_eq!(
+            check(&|c| c.providers.push(PspConfig { provider: "PayPal".to_string(), api_key: "id".to_string(), label: Some("US".to_string()), ..PspConfig::default() })),
+            [ConfigError::MissingSecret("PayPal (US)".to_string())]
+        );
+        assert_eq!(check(&|c| c.currency = "XYZ".to_string()), [ConfigError::UnknownCurrency("XYZ".to_string())]);
+        assert_eq!(check(&|c| c.poll_interval_secs = 0), [ConfigError::PollIntervalTooShort]);
+        assert_eq!(check(&|c| c.providers[0].poll_interval_secs = Some(0)), [ConfigError::ProviderPollIntervalTooShort("Mock".to_string())]);
+        assert_eq!(
+            check(&|c| {
+                c.poll_mode = PollMode::Adaptive;
+                c.adaptive_floor_secs = 60;
+                c.adaptive_ceiling_secs = 30;
+            }),
+            [ConfigError::AdaptiveBounds]
+        );
+        assert_eq!(check(&|c| c.quiet_hours = Some((22, 24))), [ConfigError::QuietHours]);
+        // Everything wrong at once is reported at once
+        let errors = check(&|c| {
+            c.providers[1].merchant_account = None;
+            c.poll_interval_secs = 0;
+        });
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].to_string(), "Adyen provider is missing a merchant account");
+    }
+
+    #[test]
+    fn absent_valid_and_corrupt_configs_load_differently() {
+        let path = scratch("load-cases").join("config.json");
+        let store = FileConfigStore { path: path.clone() };
+        std::fs::remove_file(&path).ok();
+        assert!(load_config_from(&store, &PlaintextStore).unwrap().is_none());
+
+        std::fs::write(&path, json_config()).unwrap();
+        assert_eq!(load_config_from(&store, &PlaintextStore).unwrap().unwrap().currency, "NOK");
+
+        // A broken file is an error, not a fresh start, and stays on disk to be backed up
+        std::fs::write(&path, "{\"currency\": \"NOK\",").unwrap();
+        let error = load_config_from(&store, &PlaintextStore).unwrap_err();
+        assert!(format!("{:#}", error).contains(&path.display().to_string()), "{:#}", error);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{\"currency\": \"NOK\",");
+    }
+
+    #[test]
+    fn quiet_hours_cover_their_window_including_past_midnight() {
+        let quiet = |window: (u8, u8)| -> Vec<u32> { (0..24).filter(|&h| in_quiet_hours(window, h)).collect() };
+        assert_eq!(quiet((9, 17)), (9..17).collect::<Vec<_>>());
+        // 22→7 runs over midnight: from 22:00 up to 06:59
+        assert_eq!(quiet((22, 7)), [0, 1, 2, 3, 4, 5, 6, 22, 23]);
+        assert_eq!(quiet((23, 0)), [23]);
+        assert_eq!(quiet((0, 1)), [0]);
+        assert!(quiet((8, 8)).is_empty());
+
+        // The hour is read in the display timezone, not UTC
+        let mut config = AppConfig { quiet_hours: Some((22, 7)), ..AppConfig::default() };
+        let amsterdam = crate::tz::DisplayTz::from_config(Some("Europe/Amsterdam")).0;
+        let utc = crate::tz::DisplayTz::from_config(Some("UTC")).0;
+        let at: chrono::DateTime<chrono::Utc> = "2026-07-01T21:30:00Z".parse().unwrap();
+        assert!(config.is_quiet_at(amsterdam, at));
+        assert!(!config.is_quiet_at(utc, at));
+        config.quiet_hours = None;
+        assert!(!config.is_quiet_at(amsterdam, at));
+    }
+
+    #[test]
+    fn only_exact_duplicate_providers_collapse() {
+        let entry = |provider: &str, key: &str, merchant: Option<&str>, label: Option<&str>| {
+            serde_json::json!({ "provider": provider, "api_key": key, "merchant_account": merchant, "label": label })
+        };
+        let store = InMemoryConfigStore::new();
+        let config = serde_json::json!({
+            "version": CONFIG_VERSION,
+            "currency": "EUR",
+            "currency_symbol": "€",
+            "poll_interval_secs": 5,
+            "providers": [
+                entry("Adyen", "adyen_key", Some("ShopEU"), None),
+                entry("Adyen", "adyen_key", Some("ShopEU"), None),
+                // Same provider, another merchant, key or label: a second account, not a copy
+                entry("Adyen", "adyen_key", Some("ShopUS"), None),
+                entry("Adyen", "other_key", Some("ShopEU"), None),
+                entry("Adyen", "adyen_key", Some("ShopEU"), Some("EU")),
+                entry("GoCardless", "live_key", None, None),
+                entry("GoCardless", "live_key", None, None),
+                entry("Adyen", "adyen_key", Some("ShopEU"), None),
+            ],
+        });
+        store.save(&config.to_string()).unwrap();
+
+        let loaded = load_config_from(&store, &PlaintextStore).unwrap().unwrap();
+        let kept: Vec<(String, &str, Option<&str>)> =
+            loaded.providers.iter().map(|p| (p.display_name(), p.api_key.as_str(), p.merchant_account.as_deref())).collect();
+        assert_eq!(
+            kept,
+            [
+                ("Adyen".to_string(), "adyen_key", Some("ShopEU")),
+                ("Adyen".to_string(), "adyen_key", Some("ShopUS")),
+                ("Adyen".to_string(), "other_key", Some("ShopEU")),
+                ("Adyen (EU)".to_string(), "adyen_key", Some("ShopEU")),
+                ("GoCardless".to_string(), "live_key", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_half_written_temp_file_leaves_the_good_config_loading() {
+        let dir = scratch("atomic");
+        let path = dir.join("config.json");
+        let store = FileConfigStore { path: path.clone() };
+        let mut config = migrate(serde_json::from_str(&json_config()).unwrap()).unwrap();
+        save_config_to(&store, &config, &PlaintextStore).unwrap();
+
+        // What a crash mid-write leaves behind
+        let tmp = dir.join("config.json.tmp");
+        std::fs::write(&tmp, "{\"currency\": \"US").unwrap();
+        assert_eq!(load_config_from(&store, &PlaintextStore).unwrap().unwrap().currency, "NOK");
+
+        // The next save writes over it and cleans it up
+        config.currency = "SEK".to_string();
+        save_config_to(&store, &config, &PlaintextStore).unwrap();
+        assert!(!tmp.exists());
+        assert_eq!(load_config_from(&store, &PlaintextStore).unwrap().unwrap().currency, "SEK");
+    }
+
+    #[test]
+    fn a_v0_config_migrates_to_the_current_shape() {
+        // As the first release wrote it: no version, no poll interval, Adyen's merchant packed into the key
+        let v0 = serde_json::json!({
+            "currency": "EUR",
+            "currency_symbol": "€",
+            "providers": [
+                { "provider": "Adyen", "api_key": "AQEkey|AcmeECOM" },
+                { "provider": "Mock", "api_key": "" },
+            ],
+        });
+        let config = migrate(v0).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!((config.poll_interval_secs, config.currency_decimals), (10, 2));
+        assert_eq!((config.decimal_separator.as_str(), config.thousands_separator.as_str()), (".", ","));
+        let adyen = &config.providers[0];
+        assert_eq!((adyen.api_key.as_str(), adyen.merchant_account.as_deref()), ("AQEkey", Some("AcmeECOM")));
+        assert_eq!((config.providers[1].provider.as_str(), config.providers[1].merchant_account.as_deref()), ("Mock", None));
+
+        assert!(migrate(serde_json::json!({ "version": CONFIG_VERSION + 1, "currency": "EUR" })).is_err());
+    }
+
+    #[test]
+    fn the_notify_threshold_picks_payments_at_or_above_it() {
+        let mut config = AppConfig::default();
+        let amounts = [500, 9_999, 10_000, 25_000, -20_000];
+        assert!(amounts.iter().all(|&cents| !config.should_notify(cents)));
+
+        config.notify_threshold_cents = Some(10_000);
+        let picked: Vec<i64> = amounts.into_iter().filter(|&cents| config.should_notify(cents)).collect();
+        assert_eq!(picked, [10_000, 25_000]);
+    }
+
+    // A keyring that can't be reached at all
+    struct LockedSecretStore;
+
+    impl SecretStore for LockedSecretStore {
+        fn get(&self, _account: &str) -> Result<Option<String>> {
+            anyhow::bail!("no secret service running")
+        }
+
+        fn set(&self, _account: &str, _secret: &str) -> Result<()> {
+            anyhow::bail!("no secret service running")
+        }
+
+        fn delete(&self, _account: &str) -> Result<bool> {
+            anyhow::bail!("no secret service running")
+        }
+    }
+
+    #[test]
+    fn a_credential_missing_from_the_keyring_fails_the_load_by_name() {
+        let store = InMemoryConfigStore::new();
+        let secrets = InMemorySecretStore::new();
+        save_config_to(&store, &two_providers(), &secrets).unwrap();
+
+        // As after a reboot that lost the keyring: nothing comes back blank
+        let error = format!("{:#}", load_config_from(&store, &InMemorySecretStore::new()).unwrap_err());
+        assert!(error.starts_with("the Adyen API key is missing from the keyring; "), "{}", error);
+        assert!(error.contains("--no-keyring"), "{}", error);
+
+        secrets.delete(&format!("{}:secret", secret_account("PayPal (US)"))).unwrap();
+        let error = format!("{:#}", load_config_from(&store, &secrets).unwrap_err());
+        assert!(error.starts_with("the PayPal (US) API secret is missing from the keyring"), "{}", error);
+
+        let error = format!("{:#}", load_config_from(&store, &LockedSecretStore).unwrap_err());
+        assert!(error.starts_with("cannot read the Adyen API key from the keyring"), "{}", error);
+        assert!(error.ends_with("no secret service running"), "{}", error);
+    }
+}
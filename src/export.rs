@@ -0,0 +1,395 @@
+use anyhow::Result;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::psp::replay::CastEntry;
+use crate::psp::Payment;
+use crate::tz::DisplayTz;
+
+// One JSON object per line, flushed so downstream readers see it immediately
+pub fn write_json_line(out: &mut impl Write, payment: &Payment) -> Result<()> {
+    serde_json::to_writer(&mut *out, payment)?;
+    out.write_all(b"\n")?;
+    out.flush()?;
+    Ok(())
+}
+
+// One --record line: the payment and how far into the session it was counted
+pub fn write_cast_line(out: &mut impl Write, offset: std::time::Duration, payment: &Payment) -> Result<()> {
+    let entry = CastEntry {
+        offset_ms: offset.as_millis() as u64,
+        payment: payment.clone(),
+    };
+    serde_json::to_writer(&mut *out, &entry)?;
+    out.write_all(b"\n")?;
+    out.flush()?;
+    Ok(())
+}
+
+// First wait after an output fails; it doubles with each failure in a row, up to RETRY_MAX
+const RETRY_FIRST: Duration = Duration::from_millis(250);
+const RETRY_MAX: Duration = Duration::from_secs(30);
+// Failures in a row before an output is reported as failing
+const WARN_AFTER: u32 = 3;
+// Tries a CSV export gets before it's given up on
+const EXPORT_ATTEMPTS: u32 = 5;
+
+// What came of offering a write to a ResilientWriter
+#[derive(Debug, PartialEq)]
+pub enum WriteOutcome {
+    Written,
+    // Failed, or still waiting out the backoff after a failure; try again later
+    Deferred,
+    // Has just failed WARN_AFTER times in a row. Reported once; retries carry on
+    Failing(String),
+}
+
+// Paces retries of an output that keeps failing, so a locked or briefly missing
+// file costs a warning instead of the session. Never sleeps; callers come back later
+pub struct ResilientWriter {
+    what: &'static str,
+    failures: u32,
+    retry_at: Option<Instant>,
+    last_error: Option<String>,
+}
+
+impl ResilientWriter {
+    pub fn new(what: &'static str) -> Self {
+        Self {
+            what,
+            failures: 0,
+            retry_at: None,
+            last_error: None,
+        }
+    }
+
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    // Runs `write` unless a backoff is still running; `force` skips it, for a last try on the way out
+    pub fn attempt(&mut self, force: bool, write: impl FnOnce() -> Result<()>) -> WriteOutcome {
+        if !force && self.retry_at.is_some_and(|at| Instant::now() < at) {
+            return WriteOutcome::Deferred;
+        }
+        match write() {
+            Ok(()) => {
+                if self.failures >= WARN_AFTER {
+                    tracing::info!("{} is writing again", self.what);
+                }
+                self.failures = 0;
+                self.retry_at = None;
+                self.last_error = None;
+                WriteOutcome::Written
+            }
+            Err(e) => {
+                self.failures = self.failures.saturating_add(1);
+                let delay = RETRY_FIRST.saturating_mul(1 << (self.failures - 1).min(7)).min(RETRY_MAX);
+                self.retry_at = Some(Instant::now() + delay);
+                tracing::warn!("{} failed ({} in a row): {:#}", self.what, self.failures, e);
+                let message = format!("{:#}", e);
+                self.last_error = Some(message.clone());
+                if self.failures == WARN_AFTER {
+                    WriteOutcome::Failing(format!("{} failing, still retrying: {}", self.what, message))
+                } else {
+                    WriteOutcome::Deferred
+                }
+            }
+        }
+    }
+}
+
+// An append-only output (--json-out, --record). Lines queue in memory until the file takes them
+pub struct AppendSink {
+    file: std::fs::File,
+    pending: Vec<u8>,
+    writer: ResilientWriter,
+}
+
+impl AppendSink {
+    pub fn open(path: &Path, what: &'static str) -> Result<Self> {
+        let file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file,
+            pending: Vec::new(),
+            writer: ResilientWriter::new(what),
+        })
+    }
+
+    // Where the next line goes; nothing reaches the file before flush
+    pub fn queue(&mut self) -> &mut Vec<u8> {
+        &mut self.pending
+    }
+
+    // Bytes the file took are dropped from the queue, so a retry never repeats them
+    pub fn flush(&mut self, force: bool) -> WriteOutcome {
+        if self.pending.is_empty() {
+            return WriteOutcome::Written;
+        }
+        let (file, pending) = (&mut self.file, &mut self.pending);
+        self.writer.attempt(force, || {
+            while !pending.is_empty() {
+                let n = file.write(pending)?;
+                if n == 0 {
+                    anyhow::bail!("the file accepted no more data");
+                }
+                pending.drain(..n);
+            }
+            file.flush()?;
+            Ok(())
+        })
+    }
+
+    pub fn sync(&mut self) -> Result<()> {
+        if let Some(err) = self.writer.last_error().filter(|_| !self.pending.is_empty()) {
+            anyhow::bail!("{} unwritten bytes: {}", self.pending.len(), err);
+        }
+        self.file.sync_all()?;
+        Ok(())
+    }
+}
+
+// Closest two --overlay-file writes may be, so a burst of payments doesn't churn the disk
+pub const OVERLAY_MIN_GAP: Duration = Duration::from_millis(250);
+
+// A file an OBS text or browser source reads, replaced atomically whenever its
+// contents change. A .json path gets the JSON form
+pub struct OverlayFile {
+    path: PathBuf,
+    written: Option<String>,
+    last_write: Option<Instant>,
+    writer: ResilientWriter,
+}
+
+impl OverlayFile {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            written: None,
+            last_write: None,
+            writer: ResilientWriter::new("overlay file"),
+        }
+    }
+
+    pub fn wants_json(&self) -> bool {
+        self.path.extension().is_some_and(|e| e.eq_ignore_ascii_case("json"))
+    }
+
+    // Changes inside OVERLAY_MIN_GAP of the last write wait for a later call, unless forced
+    pub fn update(&mut self, contents: String, force: bool) -> WriteOutcome {
+        if self.written.as_ref() == Some(&contents) {
+            return WriteOutcome::Written;
+        }
+        if !force && self.last_write.is_some_and(|at| at.elapsed() < OVERLAY_MIN_GAP) {
+            return WriteOutcome::Deferred;
+        }
+        let path = &self.path;
+        let outcome = self.writer.attempt(force, || crate::config::write_atomic(path, contents.as_bytes()));
+        if outcome == WriteOutcome::Written {
+            self.written = Some(contents);
+            self.last_write = Some(Instant::now());
+        }
+        outcome
+    }
+}
+
+// A CSV export of the session, retried a few times before it's given up on
+pub struct CsvExport {
+    pub path: PathBuf,
+    payments: Vec<Payment>,
+    tz: DisplayTz,
+    writer: ResilientWriter,
+}
+
+impl CsvExport {
+    pub fn new(payments: Vec<Payment>, tz: DisplayTz, path: PathBuf) -> Self {
+        Self {
+            path,
+            payments,
+            tz,
+            writer: ResilientWriter::new("CSV export"),
+        }
+    }
+
+    pub fn attempt(&mut self) -> WriteOutcome {
+        let (payments, tz, path) = (&self.payments, self.tz, &self.path);
+        self.writer.attempt(false, || export_csv(payments, tz, path))
+    }
+
+    // Why the export was abandoned, once it has used up its tries
+    pub fn gave_up(&self) -> Option<&str> {
+        if self.writer.failures() >= EXPORT_ATTEMPTS {
+            self.writer.last_error()
+        } else {
+            None
+        }
+    }
+}
+
+pub fn export_csv(payments: &[Payment], tz: DisplayTz, path: &Path) -> Result<()> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    wtr.write_record(["id", "provider", "amount_cents", "currency", "status", "created_at"])?;
+    for p in payments {
+        wtr.write_record([
+            p.id.as_str(),
+            p.provider.as_str(),
+            &p.amount_cents.to_string(),
+            p.currency.as_str(),
+            p.status.as_str(),
+            &tz.rfc3339(p.created_at),
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::psp::ScriptedProvider;
+    use crate::App;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("profit-cli-export-{}-{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn an_ingested_payment_lands_in_the_overlay_file() {
+        crate::config::use_temp_config_dir();
+        let mut app = App::new();
+        let dir = scratch("overlay");
+        let mut text = OverlayFile::new(&dir.join("total.txt"));
+        let mut json = OverlayFile::new(&dir.join("total.json"));
+        assert!(!text.wants_json() && json.wants_json());
+
+        app.add_payment(ScriptedProvider::payment("Mock", "a", 1_250, chrono::Utc::now()));
+        assert_eq!(text.update(app.overlay_contents(text.wants_json()), false), WriteOutcome::Written);
+        assert_eq!(std::fs::read_to_string(dir.join("total.txt")).unwrap(), "€12.50");
+
+        // A second payment right behind it waits out the throttle, unless forced
+        app.add_payment(ScriptedProvider::payment("Mock", "b", 100_000, chrono::Utc::now()));
+        assert_eq!(text.update(app.overlay_contents(false), false), WriteOutcome::Deferred);
+        assert_eq!(std::fs::read_to_string(dir.join("total.txt")).unwrap(), "€12.50");
+        assert_eq!(text.update(app.overlay_contents(false), true), WriteOutcome::Written);
+        assert_eq!(std::fs::read_to_string(dir.join("total.txt")).unwrap(), "€1,012.50");
+
+        app.config.goal_cents = Some(202_500);
+        assert_eq!(json.update(app.overlay_contents(json.wants_json()), false), WriteOutcome::Written);
+        let written: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.join("total.json")).unwrap()).unwrap();
+        assert_eq!(
+            written,
+            serde_json::json!({
+                "total": "€1,012.50",
+                "total_cents": 101_250,
+                "currency": "EUR",
+                "count": 2,
+                "goal_cents": 202_500,
+                "goal_progress": 0.5,
+            })
+        );
+    }
+
+    #[test]
+    fn a_writer_that_fails_twice_still_writes_and_a_dead_one_only_warns() {
+        let dir = scratch("resilient");
+        let path = dir.join("out.txt");
+        let mut writer = ResilientWriter::new("test output");
+        let mut tries = 0;
+        let write = |tries: &mut u32| -> Result<()> {
+            *tries += 1;
+            if *tries <= 2 {
+                anyhow::bail!("file locked");
+            }
+            std::fs::write(&path, "€12.50")?;
+            Ok(())
+        };
+        assert_eq!(writer.attempt(false, || write(&mut tries)), WriteOutcome::Deferred);
+        // Inside the backoff nothing is tried at all
+        assert_eq!(writer.attempt(false, || write(&mut tries)), WriteOutcome::Deferred);
+        assert_eq!(tries, 1);
+        assert_eq!(writer.attempt(true, || write(&mut tries)), WriteOutcome::Deferred);
+        assert_eq!((writer.failures(), writer.last_error()), (2, Some("file locked")));
+        assert_eq!(writer.attempt(true, || write(&mut tries)), WriteOutcome::Written);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "€12.50");
+        assert_eq!((writer.failures(), writer.last_error()), (0, None));
+
+        // One that never recovers warns once, on the third failure, and keeps deferring after that
+        let mut dead = ResilientWriter::new("overlay file");
+        let outcomes: Vec<WriteOutcome> = (0..5).map(|_| dead.attempt(true, || anyhow::bail!("disk gone"))).collect();
+        assert_eq!(
+            outcomes,
+            [
+                WriteOutcome::Deferred,
+                WriteOutcome::Deferred,
+                WriteOutcome::Failing("overlay file failing, still retrying: disk gone".to_string()),
+                WriteOutcome::Deferred,
+                WriteOutcome::Deferred,
+            ]
+        );
+
+        // A CSV export gives up after its last try and says why
+        let mut export = CsvExport::new(Vec::new(), DisplayTz::Local, dir.join("missing").join("session.csv"));
+        for _ in 0..EXPORT_ATTEMPTS {
+            assert_eq!(export.gave_up(), None);
+            export.writer.attempt(true, || anyhow::bail!("no such directory"));
+        }
+        assert_eq!(export.gave_up(), Some("no such directory"));
+    }
+
+    #[test]
+    fn each_new_payment_is_one_json_line_and_repeats_print_nothing() {
+        crate::config::use_temp_config_dir();
+        let mut app = App::new();
+        let now = chrono::Utc::now();
+        let polled = [
+            ScriptedProvider::payment("Mock", "a", 1_250, now),
+            ScriptedProvider::payment("Mock", "b", 300, now),
+            ScriptedProvider::payment("Mock", "a", 1_250, now),
+            ScriptedProvider::payment("Mock", "c", 9_900, now),
+        ];
+        let mut out = Vec::new();
+        for p in polled {
+            if app.add_payment(p.clone()) {
+                write_json_line(&mut out, &p).unwrap();
+            }
+        }
+
+        let lines: Vec<Payment> = String::from_utf8(out).unwrap().lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        let ids: Vec<(&str, i64)> = lines.iter().map(|p| (p.id.as_str(), p.amount_cents)).collect();
+        assert_eq!(ids, [("a", 1_250), ("b", 300), ("c", 9_900)]);
+        assert!(lines.iter().all(|p| p.provider == "Mock" && p.created_at == now));
+    }
+
+    #[test]
+    fn exported_csv_reads_back_as_the_same_payments() {
+        let dir = scratch("csv");
+        let path = dir.join("export.csv");
+        let at = chrono::DateTime::parse_from_rfc3339("2024-03-01T12:30:00Z").unwrap().with_timezone(&chrono::Utc);
+        let mut pending = ScriptedProvider::payment("Braintree", "bt_2", 4_999, at);
+        pending.status = "requires_capture".to_string();
+        let payments = [ScriptedProvider::payment("Mock", "a,\"quoted\"", 1_250, at), pending];
+        export_csv(&payments, DisplayTz::Named(chrono_tz::Europe::Amsterdam), &path).unwrap();
+
+        let mut reader = csv::Reader::from_path(&path).unwrap();
+        assert_eq!(reader.headers().unwrap(), vec!["id", "provider", "amount_cents", "currency", "status", "created_at"]);
+        let rows: Vec<csv::StringRecord> = reader.records().map(|r| r.unwrap()).collect();
+        assert_eq!(rows.len(), payments.len());
+        for (row, p) in rows.iter().zip(&payments) {
+            assert_eq!(&row[0], p.id);
+            assert_eq!(&row[1], p.provider);
+            assert_eq!(row[2].parse::<i64>().unwrap(), p.amount_cents);
+            assert_eq!(&row[3], p.currency);
+            assert_eq!(&row[4], p.status);
+            assert_eq!(chrono::DateTime::parse_from_rfc3339(&row[5]).unwrap(), p.created_at);
+        }
+        // Written in the display timezone
+        assert_eq!(&rows[0][5], "2024-03-01T13:30:00+01:00");
+    }
+}
@@ -0,0 +1,95 @@
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+pub trait Converter {
+    // None when there's no rate for the pair
+    fn convert(&self, amount_cents: i64, from: &str, to: &str) -> Option<i64>;
+}
+
+// Offline rates into a single base currency: 1 unit of `code` = rates[code] base units
+pub struct RateTable {
+    base: String,
+    rates: HashMap<String, f64>,
+}
+
+impl RateTable {
+    pub fn new(base: &str, rates: &HashMap<String, f64>) -> Self {
+        Self {
+            base: base.to_uppercase(),
+            rates: normalized(rates),
+        }
+    }
+
+    pub fn merge(&mut self, rates: HashMap<String, f64>) {
+        self.rates.extend(normalized(&rates));
+    }
+}
+
+// Upper-case codes, and only rates that can convert anything
+fn normalized(rates: &HashMap<String, f64>) -> HashMap<String, f64> {
+    rates
+        .iter()
+        .filter(|(_, r)| r.is_finite() && **r > 0.0)
+        .map(|(code, r)| (code.to_uppercase(), *r))
+        .collect()
+}
+
+impl Converter for RateTable {
+    fn convert(&self, amount_cents: i64, from: &str, to: &str) -> Option<i64> {
+        let (from, to) = (from.to_uppercase(), to.to_uppercase());
+        if from == to {
+            return Some(amount_cents);
+        }
+        if to != self.base {
+            return None;
+        }
+        let rate = self.rates.get(&from)?;
+        Some((amount_cents as f64 * rate).round() as i64)
+    }
+}
+
+#[derive(Deserialize)]
+struct OnlineRates {
+    rates: HashMap<String, f64>,
+}
+
+// Latest rates from open.er-api.com, inverted into RateTable's orientation
+pub async fn fetch_online_rates(client: &reqwest::Client, base: &str) -> Result<HashMap<String, f64>> {
+    let url = format!("https://open.er-api.com/v6/latest/{}", base.to_uppercase());
+    let resp = client.get(url).send().await?;
+    if !resp.status().is_success() {
+        anyhow::bail!("FX API error: {}", resp.status());
+    }
+    let online: OnlineRates = resp.json().await?;
+    Ok(online
+        .rates
+        .into_iter()
+        .filter(|(_, per_base)| per_base.is_finite() && *per_base > 0.0)
+        .map(|(code, per_base)| (code, 1.0 / per_base))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merged_rates_are_normalized_like_configured_ones() {
+        let mut table = RateTable::new("eur", &HashMap::from([("usd".to_string(), 0.9), ("GBP".to_string(), -1.0)]));
+        assert_eq!(table.convert(1_000, "USD", "EUR"), Some(900));
+        assert_eq!(table.convert(1_000, "GBP", "EUR"), None);
+
+        table.merge(HashMap::from([
+            ("gbp".to_string(), 1.2),
+            ("sek".to_string(), f64::NAN),
+            ("NOK".to_string(), 0.0),
+            ("USD".to_string(), f64::INFINITY),
+        ]));
+        assert_eq!(table.convert(1_000, "GBP", "EUR"), Some(1_200));
+        assert_eq!(table.convert(1_000, "sek", "EUR"), None);
+        assert_eq!(table.convert(1_000, "NOK", "EUR"), None);
+        // A bad rate doesn't replace a good one
+        assert_eq!(table.convert(1_000, "USD", "EUR"), Some(900));
+    }
+}
@@ -0,0 +1,133 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use crate::app::{ProviderStatus, FAILING_AFTER};
+use crate::config::in_quiet_hours;
+use crate::tz::DisplayTz;
+
+// Provider statuses as of their last change, each with how long it may go without a
+// successful poll. Evaluated when asked, so staleness keeps growing between polls
+#[derive(Debug, Clone, Default)]
+pub struct HealthCheck {
+    providers: Vec<(ProviderStatus, Duration)>,
+    quiet_hours: Option<((u8, u8), DisplayTz)>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProviderHealth {
+    pub provider: String,
+    // ok, quiet, muted, stale or failing
+    pub state: &'static str,
+    pub last_success: Option<DateTime<Utc>>,
+    pub consecutive_failures: u32,
+}
+
+// Not ok as soon as any unmuted provider is failing or stale
+#[derive(Debug, Serialize)]
+pub struct Health {
+    pub ok: bool,
+    pub providers: Vec<ProviderHealth>,
+}
+
+impl HealthCheck {
+    pub fn new(
+        statuses: &[ProviderStatus],
+        stale_after: impl Fn(&str) -> Duration,
+        quiet_hours: Option<((u8, u8), DisplayTz)>,
+    ) -> Self {
+        Self {
+            providers: statuses.iter().map(|s| (s.clone(), stale_after(&s.name))).collect(),
+            quiet_hours,
+        }
+    }
+
+    // Quiet hours excuse staleness, since nothing is polled then, but not failures
+    pub fn evaluate(&self, now: DateTime<Utc>) -> Health {
+        let quiet = self.quiet_hours.is_some_and(|(window, tz)| in_quiet_hours(window, tz.hour(now)));
+        let providers: Vec<ProviderHealth> = self
+            .providers
+            .iter()
+            .map(|(status, stale_after)| {
+                // A provider that never succeeded is stale once it's been watched that long
+                let heard_from = status.last_success.unwrap_or(status.since);
+                let state = if status.muted {
+                    "muted"
+                } else if status.consecutive_failures >= FAILING_AFTER {
+                    "failing"
+                } else if now - heard_from <= *stale_after {
+                    "ok"
+                } else if quiet {
+                    "quiet"
+                } else {
+                    "stale"
+                };
+                ProviderHealth {
+                    provider: status.name.clone(),
+                    state,
+                    last_success: status.last_success,
+                    consecutive_failures: status.consecutive_failures,
+                }
+            })
+            .collect();
+        Health {
+            ok: providers.iter().all(|p| matches!(p.state, "ok" | "quiet" | "muted")),
+            providers,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(name: &str, since_mins: i64, success_mins: Option<i64>, failures: u32, now: DateTime<Utc>) -> ProviderStatus {
+        ProviderStatus {
+            since: now - Duration::minutes(since_mins),
+            last_success: success_mins.map(|m| now - Duration::minutes(m)),
+            consecutive_failures: failures,
+            ..ProviderStatus::new(name)
+        }
+    }
+
+    fn states(health: &Health) -> Vec<(&str, &str)> {
+        health.providers.iter().map(|p| (p.provider.as_str(), p.state)).collect()
+    }
+
+    #[test]
+    fn health_turns_on_failures_and_how_long_since_the_last_success() {
+        let now: DateTime<Utc> = "2026-10-16T14:00:00Z".parse().unwrap();
+        let healthy = [status("Adyen", 60, Some(1), 0, now), status("Mock", 5, None, 0, now)];
+        let check = HealthCheck::new(&healthy, |_| Duration::minutes(10), None);
+        assert!(check.evaluate(now).ok);
+        assert_eq!(states(&check.evaluate(now)), [("Adyen", "ok"), ("Mock", "ok")]);
+        // Nothing new happens, but time passes: staleness is judged when asked
+        let later = check.evaluate(now + Duration::minutes(10));
+        assert!(!later.ok);
+        assert_eq!(states(&later), [("Adyen", "stale"), ("Mock", "stale")]);
+
+        let mut muted = status("Braintree", 60, Some(45), 0, now);
+        muted.muted = true;
+        let statuses = [
+            status("Adyen", 60, Some(9), 0, now),
+            status("PayPal", 60, Some(11), 0, now),
+            status("Coinbase", 60, Some(1), FAILING_AFTER, now),
+            status("GoCardless", 60, Some(1), FAILING_AFTER - 1, now),
+            muted,
+        ];
+        // The threshold is per provider
+        let stale_after = |name: &str| Duration::minutes(if name == "PayPal" { 15 } else { 10 });
+        let health = HealthCheck::new(&statuses, stale_after, None).evaluate(now);
+        assert!(!health.ok);
+        assert_eq!(
+            states(&health),
+            [("Adyen", "ok"), ("PayPal", "ok"), ("Coinbase", "failing"), ("GoCardless", "ok"), ("Braintree", "muted")]
+        );
+
+        // During quiet hours a stale provider is excused, a failing one isn't
+        let quiet = Some(((13, 15), DisplayTz::from_config(Some("UTC")).0));
+        let health = HealthCheck::new(&statuses[..2], |_| Duration::minutes(10), quiet).evaluate(now);
+        assert!(health.ok);
+        assert_eq!(states(&health), [("Adyen", "ok"), ("PayPal", "quiet")]);
+        assert!(!HealthCheck::new(&statuses[..3], |_| Duration::minutes(10), quiet).evaluate(now).ok);
+    }
+}
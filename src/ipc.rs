@@ -0,0 +1,133 @@
+use crate::app::SessionSummary;
+use crate::health::HealthCheck;
+use anyhow::{Context, Result};
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+
+// How long a connection has to send a command before it gets the summary
+const COMMAND_WAIT: Duration = Duration::from_millis(200);
+
+// The latest session summary as a JSON line, and the health check, shared between
+// the accounting code and the socket
+#[derive(Clone, Default)]
+pub struct SummaryFeed {
+    inner: Arc<Mutex<String>>,
+    health: Arc<Mutex<HealthCheck>>,
+}
+
+impl SummaryFeed {
+    pub fn publish(&self, summary: &SessionSummary) {
+        if let Ok(json) = serde_json::to_string(summary) {
+            *self.inner.lock().unwrap() = json;
+        }
+    }
+
+    pub fn publish_health(&self, check: HealthCheck) {
+        *self.health.lock().unwrap() = check;
+    }
+
+    fn line(&self) -> String {
+        format!("{}\n", self.inner.lock().unwrap())
+    }
+
+    // Judged now rather than when published, so a provider goes stale on time
+    fn health_line(&self) -> String {
+        let health = self.health.lock().unwrap().evaluate(chrono::Utc::now());
+        format!("{}\n", serde_json::to_string(&health).unwrap_or_default())
+    }
+}
+
+// Stops serving and removes the socket file when dropped
+pub struct IpcServer {
+    path: PathBuf,
+    handle: tokio::task::JoinHandle<()>,
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+// Each connection gets the current summary and is closed, or the health check
+// (with "ok": false if any provider is failing or stale) if it sends "health" first.
+// A socket left behind by an earlier run is replaced; any other file at the path is an error
+pub fn serve(path: &Path, feed: SummaryFeed) -> Result<IpcServer> {
+    if let Ok(meta) = std::fs::symlink_metadata(path) {
+        if !meta.file_type().is_socket() {
+            anyhow::bail!("{} exists and is not a socket", path.display());
+        }
+        std::fs::remove_file(path).with_context(|| format!("cannot remove stale socket {}", path.display()))?;
+    }
+    let listener = tokio::net::UnixListener::bind(path).with_context(|| format!("cannot bind {}", path.display()))?;
+    let handle = tokio::spawn(async move {
+        loop {
+            let Ok((mut stream, _)) = listener.accept().await else {
+                continue;
+            };
+            let feed = feed.clone();
+            tokio::spawn(async move {
+                let (read, mut write) = stream.split();
+                let mut command = String::new();
+                let mut read = tokio::io::BufReader::new(read);
+                tokio::time::timeout(COMMAND_WAIT, read.read_line(&mut command)).await.ok();
+                let line = if command.trim().eq_ignore_ascii_case("health") {
+                    feed.health_line()
+                } else {
+                    feed.line()
+                };
+                write.write_all(line.as_bytes()).await.ok();
+                write.shutdown().await.ok();
+            });
+        }
+    });
+    Ok(IpcServer {
+        path: path.to_path_buf(),
+        handle,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn request(path: &Path, command: &str) -> serde_json::Value {
+        let mut stream = tokio::net::UnixStream::connect(path).await.unwrap();
+        stream.write_all(command.as_bytes()).await.unwrap();
+        let mut reply = String::new();
+        tokio::io::AsyncReadExt::read_to_string(&mut stream, &mut reply).await.unwrap();
+        assert_eq!(reply.lines().count(), 1, "{:?}", reply);
+        serde_json::from_str(&reply).unwrap()
+    }
+
+    #[tokio::test]
+    async fn each_connection_gets_the_latest_summary_line() {
+        crate::config::use_temp_config_dir();
+        let mut app = crate::app::App::new();
+        app.config.currency = "EUR".to_string();
+        let now = chrono::Utc::now();
+        app.add_payment(crate::psp::ScriptedProvider::payment("Adyen", "a1", 2_500, now));
+        app.add_payment(crate::psp::ScriptedProvider::payment("PayPal", "p1", 1_000, now));
+
+        let path = std::env::temp_dir().join(format!("profit-cli-test-{}.sock", std::process::id()));
+        // A stale socket from an earlier run is replaced
+        drop(std::os::unix::net::UnixListener::bind(&path));
+        let server = serve(&path, app.ipc.clone()).unwrap();
+
+        let summary = request(&path, "").await;
+        assert_eq!((summary["currency"].as_str(), summary["total_cents"].as_i64()), (Some("EUR"), Some(3_500)));
+        assert_eq!(summary["providers"][0]["provider"], "Adyen");
+        assert_eq!(summary["providers"][1]["total_cents"], 1_000);
+
+        app.add_payment(crate::psp::ScriptedProvider::payment("PayPal", "p2", 4_000, now));
+        assert_eq!(request(&path, "").await["payment_count"], 3);
+        assert!(request(&path, "health\n").await.get("ok").is_some());
+
+        drop(server);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,96 @@
+use crate::psp::{parse_decimal_cents, Payment};
+
+// One space-separated term of a ledger query; every term has to match
+enum Term {
+    Provider(String),
+    Status(String),
+    Above(i64),
+    Below(i64),
+}
+
+// Terms that don't parse (">abc", "status:") are dropped rather than
+// failing the whole query, so a half-typed filter still shows something
+fn parse_query(query: &str) -> Vec<Term> {
+    query
+        .split_whitespace()
+        .filter_map(|word| {
+            if let Some(amount) = word.strip_prefix('>') {
+                parse_decimal_cents(amount).map(Term::Above)
+            } else if let Some(amount) = word.strip_prefix('<') {
+                parse_decimal_cents(amount).map(Term::Below)
+            } else if let Some(status) = word.strip_prefix("status:") {
+                (!status.is_empty()).then(|| Term::Status(status.to_lowercase()))
+            } else {
+                Some(Term::Provider(word.to_lowercase()))
+            }
+        })
+        .collect()
+}
+
+fn term_matches(term: &Term, payment: &Payment) -> bool {
+    match term {
+        Term::Provider(text) => payment.provider.to_lowercase().contains(text),
+        Term::Status(status) => payment.status.to_lowercase().contains(status),
+        Term::Above(cents) => payment.display_cents() > *cents,
+        Term::Below(cents) => payment.display_cents() < *cents,
+    }
+}
+
+pub fn matches(payment: &Payment, query: &str) -> bool {
+    parse_query(query).iter().all(|term| term_matches(term, payment))
+}
+
+// Bare text matches the provider, ">50"/"<50" the amount, "status:paid" the status
+pub fn filter_payments<'a>(payments: &'a [Payment], query: &str) -> Vec<&'a Payment> {
+    let terms = parse_query(query);
+    payments
+        .iter()
+        .filter(|p| terms.iter().all(|term| term_matches(term, p)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::psp::ScriptedProvider;
+
+    fn payments() -> Vec<Payment> {
+        let now = chrono::Utc::now();
+        let mut refunded = ScriptedProvider::payment("PayPal (US)", "3", 9_900, now);
+        refunded.status = "Refunded".to_string();
+        vec![
+            ScriptedProvider::payment("Adyen", "1", 2_500, now),
+            ScriptedProvider::payment("Adyen (EU)", "2", 7_500, now),
+            refunded,
+        ]
+    }
+
+    fn ids(payments: &[Payment], query: &str) -> Vec<String> {
+        filter_payments(payments, query).into_iter().map(|p| p.id.clone()).collect()
+    }
+
+    #[test]
+    fn each_term_filters_on_its_field() {
+        let payments = payments();
+        assert_eq!(ids(&payments, ""), ["1", "2", "3"]);
+        // Provider text is a case-insensitive substring
+        assert_eq!(ids(&payments, "adyen"), ["1", "2"]);
+        assert_eq!(ids(&payments, "US"), ["3"]);
+        assert_eq!(ids(&payments, ">25"), ["2", "3"]);
+        assert_eq!(ids(&payments, "<75.00"), ["1"]);
+        assert_eq!(ids(&payments, "status:refund"), ["3"]);
+    }
+
+    #[test]
+    fn combined_terms_must_all_match_and_unreadable_ones_are_ignored() {
+        let payments = payments();
+        assert_eq!(ids(&payments, "adyen >30"), ["2"]);
+        assert_eq!(ids(&payments, ">20 <80 status:succeeded"), ["1", "2"]);
+        assert!(ids(&payments, "coinbase >1").is_empty());
+        // Half-typed terms don't hide everything while typing
+        assert_eq!(ids(&payments, ">abc"), ["1", "2", "3"]);
+        assert_eq!(ids(&payments, "eu status:"), ["2"]);
+        assert!(matches(&payments[0], "ADYEN <30"));
+        assert!(!matches(&payments[0], "adyen >30"));
+    }
+}
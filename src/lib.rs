@@ -0,0 +1,32 @@
+// The polling, provider and config code, for the binary and for anyone embedding it
+pub mod app;
+pub mod bigtext;
+pub mod clipboard;
+pub mod config;
+pub mod export;
+pub mod fx;
+pub mod health;
+#[cfg(unix)]
+pub mod ipc;
+pub mod ledger;
+pub mod logging;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod milestones;
+pub mod money;
+pub mod notify;
+pub mod particles;
+pub mod poll;
+pub mod psp;
+pub mod reveal;
+pub mod secrets;
+pub mod state;
+pub mod stats;
+pub mod theme;
+pub mod tz;
+pub mod ui;
+pub mod vat;
+
+pub use app::App;
+pub use config::{load_config, save_config, AppConfig};
+pub use psp::{Payment, PaymentProvider, PspConfig};
@@ -0,0 +1,19 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::sync::Mutex;
+
+// Appends plain-text logs to `path`; without this nothing is logged at all
+pub fn init(path: &Path) -> Result<()> {
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("cannot open log file {}", path.display()))?;
+    tracing_subscriber::fmt()
+        .with_writer(Mutex::new(file))
+        .with_ansi(false)
+        .with_max_level(tracing::Level::DEBUG)
+        .try_init()
+        .map_err(|e| anyhow::anyhow!(e))?;
+    Ok(())
+}
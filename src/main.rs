@@ -1,168 +1,926 @@
-mod app;
-mod config;
-mod psp;
-mod ui;
+#[cfg(unix)]
+use profit_cli::ipc;
+#[cfg(feature = "metrics")]
+use profit_cli::metrics;
+use profit_cli::{app, clipboard, config, export, fx, logging, money, notify, poll, psp, reveal, secrets, state, ui};
 
 use anyhow::Result;
 use app::*;
+use clap::Parser;
 use config::*;
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind},
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     execute,
 };
+use poll::PollEvent;
 use psp::{PaymentProvider, PspConfig};
 use ratatui::prelude::*;
+use std::io::IsTerminal;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::mpsc;
-
-fn build_providers(configs: &[PspConfig]) -> Vec<Arc<dyn PaymentProvider>> {
-    let mut providers: Vec<Arc<dyn PaymentProvider>> = Vec::new();
-    for cfg in configs {
-        match cfg.provider.as_str() {
-            "Mock" => {
-                providers.push(Arc::new(psp::mock::MockProvider::new()));
-            }
-            "Adyen" => {
-                // Adyen needs merchant account — stored as "key|merchant"
-                let parts: Vec<&str> = cfg.api_key.splitn(2, '|').collect();
-                if parts.len() == 2 {
-                    providers.push(Arc::new(psp::adyen::AdyenProvider::new(
-                        parts[0].to_string(),
-                        parts[1].to_string(),
-                    )));
-                }
-            }
-            _ => {}
+use tokio::sync::{mpsc, Notify};
+
+#[derive(Parser)]
+#[command(
+    name = "profit-cli",
+    about = "Watch your revenue pile up in the terminal",
+    after_help = "Environment variables override config.json:\n  \
+        PROFIT_ADYEN_API_KEY, PROFIT_ADYEN_MERCHANT\n  \
+        PROFIT_PAYPAL_API_KEY, PROFIT_PAYPAL_SECRET\n  \
+        PROFIT_BRAINTREE_API_KEY, PROFIT_BRAINTREE_SECRET\n  \
+        PROFIT_GOCARDLESS_API_KEY, PROFIT_COINBASE_API_KEY\n\n\
+        PROFIT_CONFIG_DIR (or --config-dir) moves config.json, state and stats out of the platform config directory."
+)]
+struct Cli {
+    /// Try it out with the mock provider, skipping setup; nothing is saved
+    #[arg(long, visible_alias = "demo")]
+    simulate: bool,
+    /// Print payments to stdout instead of running the TUI
+    #[arg(long)]
+    headless: bool,
+    /// In headless mode, print each payment as a JSON line
+    #[arg(long, requires = "headless")]
+    json: bool,
+    /// Append each new payment as a JSON line to this file
+    #[arg(long, value_name = "PATH")]
+    json_out: Option<std::path::PathBuf>,
+    /// Keep API keys in config.json instead of the OS keyring
+    #[arg(long)]
+    no_keyring: bool,
+    /// Seed the mock provider so every run produces the same payments
+    #[arg(long, value_name = "N")]
+    seed: Option<u64>,
+    /// Start a new session instead of resuming the one saved at the last quit
+    #[arg(long)]
+    fresh: bool,
+    /// Print a JSON summary of the session to stdout on exit
+    #[arg(long)]
+    summary_json: bool,
+    /// Show a single status line instead of the bills (automatic in panes under 5 rows)
+    #[arg(long, conflicts_with = "headless")]
+    compact: bool,
+    /// Replay payments from a JSON array or exported CSV instead of polling
+    #[arg(long, value_name = "PATH", group = "replay", conflicts_with = "simulate")]
+    import: Option<std::path::PathBuf>,
+    /// Play back a session recorded with --record, at the pace it happened
+    #[arg(long, value_name = "PATH", group = "replay", conflicts_with = "simulate")]
+    play: Option<std::path::PathBuf>,
+    /// Play an import or recording this many times faster than real time
+    #[arg(long, value_name = "X", default_value_t = 1.0, requires = "replay", value_parser = parse_speed)]
+    import_speed: f64,
+    /// Keep the running total in this file for stream overlays (JSON with count and goal if it ends in .json)
+    #[arg(long, value_name = "PATH")]
+    overlay_file: Option<std::path::PathBuf>,
+    /// Append each counted payment and when it arrived to this .profitcast file
+    #[arg(long, value_name = "PATH")]
+    record: Option<std::path::PathBuf>,
+    /// Backfill payments from this far back at startup, e.g. 30m, 2h, 1d or an RFC 3339 time
+    #[arg(long, value_name = "WHEN", value_parser = parse_since)]
+    since: Option<chrono::DateTime<chrono::Utc>>,
+
+    /// Keep config, state and stats in this directory instead (overrides PROFIT_CONFIG_DIR)
+    #[arg(long, value_name = "PATH")]
+    config_dir: Option<std::path::PathBuf>,
+
+    /// Read the config from this .json or .toml file (default: config.toml if there is one, else config.json)
+    #[arg(long, value_name = "PATH")]
+    config: Option<std::path::PathBuf>,
+
+    /// Use config-NAME.json and its own state and stats instead of the default profile
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Write debug logs to this file (appended; nothing is logged without it)
+    #[arg(long, value_name = "PATH")]
+    log_file: Option<std::path::PathBuf>,
+
+    /// Fetch a day of payments from each provider, report OK/FAIL and exit with the failure count
+    #[arg(long, conflicts_with = "headless")]
+    check: bool,
+
+    /// Print where the config file lives and exit
+    #[arg(long)]
+    print_config_path: bool,
+
+    /// Print the config as it will run, after migrations and environment overrides, with secrets masked, and exit
+    #[arg(long)]
+    print_effective_config: bool,
+
+    /// Delete this profile's config, state and stats and the keys stored for it, after asking, and exit
+    #[arg(long, conflicts_with = "forget_provider")]
+    reset: bool,
+
+    /// Skip the confirmation --reset asks for
+    #[arg(long, requires = "reset")]
+    yes: bool,
+
+    /// Remove this provider (e.g. "Adyen (EU)") and its stored keys from the config, and exit
+    #[arg(long, value_name = "NAME")]
+    forget_provider: Option<String>,
+
+    /// Print each provider with the config fields and environment variables it needs, and exit
+    #[arg(long)]
+    list_providers: bool,
+
+    /// Answer each connection to this Unix socket with the session summary as a JSON line, or provider health if sent "health"
+    #[cfg(unix)]
+    #[arg(long, value_name = "PATH")]
+    ipc_socket: Option<std::path::PathBuf>,
+
+    /// Serve Prometheus metrics at http://HOST:PORT/metrics (headless only)
+    #[cfg(feature = "metrics")]
+    #[arg(long, value_name = "HOST:PORT", requires = "headless")]
+    metrics_addr: Option<std::net::SocketAddr>,
+}
+
+// One provider per line: "Adyen: api_key, merchant_account; env PROFIT_ADYEN_API_KEY, PROFIT_ADYEN_MERCHANT"
+fn list_providers() {
+    for name in psp::registry::registry().setup_names() {
+        let fields = credential_fields(name);
+        if fields.is_empty() {
+            println!("{}: none", name);
+            continue;
+        }
+        let keys: Vec<&str> = fields.iter().map(|(field, _)| *field).collect();
+        let vars: Vec<&str> = fields.iter().map(|(_, var)| var.as_str()).collect();
+        println!("{}: {}; env {}", name, keys.join(", "), vars.join(", "));
+    }
+}
+
+// Lists what --reset will delete and wants "yes" typed back unless --yes was given
+fn reset(yes: bool) -> Result<()> {
+    let present: Vec<_> = profile_data_files().into_iter().filter(|p| p.exists()).collect();
+    if !yes {
+        println!("This deletes the keys stored for every configured provider and:");
+        for path in &present {
+            println!("  {}", path.display());
+        }
+        print!("Type yes to continue: ");
+        std::io::Write::flush(&mut std::io::stdout())?;
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        if answer.trim() != "yes" {
+            println!("Nothing removed");
+            return Ok(());
         }
     }
-    providers
+    let removed = reset_profile()?;
+    if removed.is_empty() {
+        println!("Nothing to remove");
+    }
+    for what in removed {
+        println!("Removed {}", what);
+    }
+    Ok(())
+}
+
+// "2024-01-01T00:00:00Z", or a number with s/m/h/d counted back from now
+fn parse_since(value: &str) -> std::result::Result<chrono::DateTime<chrono::Utc>, String> {
+    if let Ok(at) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&chrono::Utc));
+    }
+    let invalid = || format!("'{}' is neither a duration like 30m, 2h or 1d nor an RFC 3339 time", value);
+    let split = value.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let (amount, unit) = value.split_at(split);
+    let amount: i64 = amount.parse().map_err(|_| invalid())?;
+    let back = match unit {
+        "s" => chrono::Duration::try_seconds(amount),
+        "m" => chrono::Duration::try_minutes(amount),
+        "h" => chrono::Duration::try_hours(amount),
+        "d" => chrono::Duration::try_days(amount),
+        _ => None,
+    }
+    .ok_or_else(invalid)?;
+    chrono::Utc::now().checked_sub_signed(back).ok_or_else(invalid)
+}
+
+// Slower than this a recording would take weeks; faster, everything is due at once anyway
+const MIN_IMPORT_SPEED: f64 = 0.001;
+const MAX_IMPORT_SPEED: f64 = 1_000_000.0;
+
+fn parse_speed(value: &str) -> std::result::Result<f64, String> {
+    let invalid = || format!("'{}' is not a speed between {} and {}", value, MIN_IMPORT_SPEED, MAX_IMPORT_SPEED);
+    let speed: f64 = value.trim().parse().map_err(|_| invalid())?;
+    if (MIN_IMPORT_SPEED..=MAX_IMPORT_SPEED).contains(&speed) {
+        Ok(speed)
+    } else {
+        Err(invalid())
+    }
+}
+
+fn simulated_config() -> AppConfig {
+    AppConfig {
+        currency: "EUR".to_string(),
+        currency_symbol: "€".to_string(),
+        providers: vec![PspConfig {
+            provider: "Mock".to_string(),
+            ..PspConfig::default()
+        }],
+        ephemeral: true,
+        ..AppConfig::default()
+    }
+}
+
+// The saved display settings with a replay of the file as the only provider
+fn import_config(path: &std::path::Path, speed: f64, cast: bool) -> AppConfig {
+    let base = resolve_config().ok().flatten().unwrap_or_else(simulated_config);
+    AppConfig {
+        providers: vec![PspConfig {
+            provider: "Replay".to_string(),
+            ..PspConfig::default()
+        }],
+        replay: Some(psp::replay::ReplaySource {
+            path: path.to_path_buf(),
+            speed,
+            cast,
+        }),
+        ephemeral: true,
+        ..base
+    }
+}
+
+// Config picked by the command line: simulation, an import, or the saved one
+fn cli_config(cli: &Cli) -> Result<Option<AppConfig>> {
+    if cli.simulate {
+        Ok(Some(simulated_config()))
+    } else if let Some(path) = cli.import.as_deref() {
+        Ok(Some(import_config(path, cli.import_speed, false)))
+    } else if let Some(path) = cli.play.as_deref() {
+        Ok(Some(import_config(path, cli.import_speed, true)))
+    } else {
+        resolve_config()
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    enable_raw_mode()?;
-    let mut stdout = std::io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    let cli = Cli::parse();
+    if let Some(path) = &cli.log_file {
+        logging::init(path)?;
+    }
+    set_config_dir(cli.config_dir.as_deref())?;
+    if let Some(name) = &cli.profile {
+        set_profile(name)?;
+    }
+    if let Some(path) = &cli.config {
+        set_config_file(path)?;
+    }
+    if cli.print_config_path {
+        println!("{}", config_path().display());
+        return Ok(());
+    }
+    if cli.list_providers {
+        list_providers();
+        return Ok(());
+    }
+    if cli.no_keyring {
+        secrets::disable_keyring();
+    }
+
+    if cli.reset {
+        return reset(cli.yes);
+    }
+    if let Some(name) = &cli.forget_provider {
+        let (name, cleared) = forget_provider(name)?;
+        println!("Removed {} from {}", name, config_path().display());
+        for account in cleared {
+            println!("Removed keyring entry {}", account);
+        }
+        return Ok(());
+    }
+
+    if cli.print_effective_config {
+        let Some(cfg) = cli_config(&cli)? else {
+            anyhow::bail!("No configuration found — run profit-cli once to set it up");
+        };
+        println!("{}", serde_json::to_string_pretty(&cfg.redacted())?);
+        return Ok(());
+    }
 
-    let result = run_app(&mut terminal).await;
+    if cli.check {
+        let Some(cfg) = cli_config(&cli)? else {
+            anyhow::bail!("No configuration found — run profit-cli once to set it up");
+        };
+        let failures = run_check(&cfg).await?;
+        std::process::exit(failures.min(255) as i32);
+    }
+
+    if choose_mode(cli.headless, std::io::stdout().is_terminal(), enter_tui) == Mode::Headless {
+        return headless(&cli).await;
+    }
+    let mut terminal = Terminal::new(CrosstermBackend::new(std::io::stdout()))?;
+
+    let result = run_app(&mut terminal, &cli).await;
 
     disable_raw_mode()?;
     execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
     terminal.show_cursor()?;
 
-    if let Err(e) = result {
-        eprintln!("Error: {}", e);
+    match result {
+        Ok(summary) if cli.summary_json => print_summary(&summary)?,
+        Ok(_) => {}
+        Err(e) => {
+            tracing::error!("exited with error: {:#}", e);
+            eprintln!("Error: {:#}", e);
+        }
     }
 
     Ok(())
 }
 
-async fn run_app(terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>) -> Result<()> {
-    let simulate = std::env::args().any(|a| a == "--simulate");
+#[derive(Debug, PartialEq, Eq)]
+enum Mode {
+    Tui,
+    Headless,
+}
 
-    let mut app = if simulate {
-        let cfg = AppConfig {
-            currency: "EUR".to_string(),
-            currency_symbol: "€".to_string(),
-            providers: vec![PspConfig {
-                provider: "Mock".to_string(),
-                api_key: String::new(),
-            }],
-        };
-        App::from_config(cfg)
-    } else {
-        match load_config() {
-            Some(cfg) => App::from_config(cfg),
-            None => App::new(),
+// Piped output, or a terminal without raw mode or an alternate screen, gets the plain
+// output. The terminal is only switched over once nothing else rules the TUI out
+fn choose_mode(headless: bool, is_terminal: bool, enter_tui: impl FnOnce() -> std::io::Result<()>) -> Mode {
+    if headless {
+        return Mode::Headless;
+    }
+    if !is_terminal {
+        eprintln!("stdout is not a terminal, running headless");
+        return Mode::Headless;
+    }
+    if let Err(e) = enter_tui() {
+        eprintln!("Can't start the TUI ({}), running headless", e);
+        return Mode::Headless;
+    }
+    Mode::Tui
+}
+
+// A half-enabled raw mode is undone on failure
+fn enter_tui() -> std::io::Result<()> {
+    enable_raw_mode()?;
+    execute!(std::io::stdout(), EnterAlternateScreen).inspect_err(|_| {
+        disable_raw_mode().ok();
+    })
+}
+
+async fn headless(cli: &Cli) -> Result<()> {
+    match cli_config(cli)? {
+        Some(mut cfg) if !cfg.providers.is_empty() => {
+            cfg.mock_seed = cli.seed;
+            run_headless(cfg, cli).await
+        }
+        _ => anyhow::bail!("No configuration found — run profit-cli once in a terminal to set it up"),
+    }
+}
+
+async fn run_headless(cfg: AppConfig, cli: &Cli) -> Result<()> {
+    let mut app = App::from_config(cfg);
+    if let Some(problems) = app.error_message.take() {
+        eprintln!("Config: {}", problems);
+    }
+    resume_session(&mut app, cli);
+    #[cfg(unix)]
+    let _ipc = serve_ipc(&app, cli)?;
+    let mut outputs = Outputs::open(cli)?;
+    load_online_rates(&mut app).await;
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<PollEvent>();
+    let refresh = Arc::new(Notify::new());
+    let poll_handle = app.start_polling(tx.clone(), refresh.clone())?;
+    for err in app.recent_errors.drain(..) {
+        eprintln!("{}: {}", err.provider, err.message);
+    }
+    let Some(mut poll_handle) = poll_handle else {
+        anyhow::bail!("No providers could be started; check the provider entries in {}", config_path().display());
+    };
+    if let Some(watching) = app.status_message.take() {
+        eprintln!("{}", watching);
+    }
+    #[cfg(feature = "metrics")]
+    let metrics_handle = match cli.metrics_addr {
+        Some(addr) => Some(metrics::serve(addr, app.metrics.clone()).await?),
+        None => None,
+    };
+
+    // Picks up overlay changes held back by the write throttle, and output retries
+    let mut output_tick = tokio::time::interval(export::OVERLAY_MIN_GAP);
+    let mut backoff = poll::RestartBackoff::new();
+    // One listener for the whole run; a fresh one per pass would miss a signal sent between passes
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+    loop {
+        tokio::select! {
+            received = rx.recv() => match received {
+                Some(event) => handle_headless_event(&mut app, cli, &mut outputs, event)?,
+                None => break,
+            },
+            // A poller that dies is restarted, with backoff, rather than left dead
+            joined = &mut poll_handle => {
+                let reason = poll::crash_reason(joined).unwrap_or_else(|| "returned".to_string());
+                let delay = backoff.crashed();
+                tracing::error!("poller stopped ({}), restarting in {:?}", reason, delay);
+                eprintln!("Poller stopped ({}); restarting in {}s", reason, delay.as_secs());
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = &mut shutdown => break,
+                }
+                backoff.started();
+                match app.start_polling(tx.clone(), refresh.clone())? {
+                    Some(handle) => poll_handle = handle,
+                    None => anyhow::bail!("No providers could be started; check the provider entries in {}", config_path().display()),
+                }
+            },
+            _ = output_tick.tick() => print_notices(outputs.tick(&app)),
+            _ = &mut shutdown => break,
+        }
+    }
+
+    // Whatever already arrived still counts
+    poll_handle.abort();
+    #[cfg(feature = "metrics")]
+    if let Some(h) = metrics_handle {
+        h.abort();
+    }
+    while let Ok(event) = rx.try_recv() {
+        handle_headless_event(&mut app, cli, &mut outputs, event)?;
+    }
+    finish_session(&app, cli, &mut outputs)?;
+    if cli.summary_json {
+        print_summary(&app.session_summary(chrono::Utc::now()))?;
+    }
+    Ok(())
+}
+
+fn handle_headless_event(
+    app: &mut App,
+    cli: &Cli,
+    outputs: &mut Outputs,
+    event: PollEvent,
+) -> Result<()> {
+    // Provider statuses feed the health check on the IPC socket
+    let mut payments = match event {
+        PollEvent::Payments { provider, payments } => {
+            app.record_poll_success(&provider);
+            payments
+        }
+        PollEvent::Error { provider, message } => {
+            eprintln!("Poll error from {}: {}", provider, message);
+            app.record_poll_failure(&provider, &message);
+            app.push_error(provider, message);
+            return Ok(());
+        }
+        PollEvent::RateLimit { provider, limit } => {
+            app.record_rate_limit(&provider, limit);
+            return Ok(());
+        }
+    };
+    psp::sort_chronologically(&mut payments);
+    for p in payments {
+        let payment = p.clone();
+        if !app.add_payment(p) {
+            continue;
+        }
+        notify_if_large(&app.config, &payment);
+        if cli.json {
+            export::write_json_line(&mut std::io::stdout().lock(), &payment)?;
+        } else {
+            println!(
+                "[{}] +{} total={}",
+                payment.provider,
+                money::format_money(payment.display_cents(), &app.config.money_format()),
+                money::format_money(app.total_cents, &app.config.money_format()),
+            );
+        }
+        outputs.write(app, &payment);
+    }
+    print_notices(outputs.tick(app));
+    // Nothing animates the bills here
+    app.pending_bills.clear();
+    if let Err(e) = app.save_stats() {
+        tracing::error!("saving stats failed: {:#}", e);
+        eprintln!("Saving stats failed: {:#}", e);
+    }
+    Ok(())
+}
+
+// Pick up the session saved at the last quit, unless asked for a fresh one,
+// and apply --since, which holds either way
+fn resume_session(app: &mut App, cli: &Cli) {
+    if let Some(since) = cli.since {
+        app.backfill_since = Some(since);
+    }
+    // Seeded runs replay the same mock IDs, which a resumed session would drop as already seen
+    let keeps_session = cli.seed.is_none() && !app.config.ephemeral;
+    let resumed = keeps_session && !cli.fresh && app.phase == AppPhase::Running && match state::load_state() {
+        Some(saved) => {
+            app.restore_session(saved);
+            true
         }
+        None => false,
     };
+    if keeps_session {
+        app.keep_journal(state::Journal::new(state::journal_path(), resumed));
+    }
+}
+
+#[cfg(unix)]
+fn serve_ipc(app: &App, cli: &Cli) -> Result<Option<ipc::IpcServer>> {
+    let Some(path) = cli.ipc_socket.as_deref() else {
+        return Ok(None);
+    };
+    app.publish_summary();
+    ipc::serve(path, app.ipc.clone()).map(Some)
+}
+
+// Files each counted payment is also written to
+struct Outputs {
+    // --json-out: one JSON line per payment
+    json: Option<export::AppendSink>,
+    // --record: each payment with its offset into the session, for --play
+    cast: Option<export::AppendSink>,
+    overlay: Option<export::OverlayFile>,
+    // A CSV export still being retried
+    export: Option<export::CsvExport>,
+}
+
+// Something an output wants the user to know about
+enum Notice {
+    Warning(String),
+    Status(String),
+}
+
+impl Outputs {
+    fn open(cli: &Cli) -> Result<Self> {
+        Ok(Self {
+            json: cli.json_out.as_deref().map(|p| export::AppendSink::open(p, "JSON output")).transpose()?,
+            cast: cli.record.as_deref().map(|p| export::AppendSink::open(p, "recording")).transpose()?,
+            overlay: cli.overlay_file.as_deref().map(export::OverlayFile::new),
+            export: None,
+        })
+    }
+
+    // Queued only; the next tick writes it out
+    fn write(&mut self, app: &App, payment: &psp::Payment) {
+        if let Some(out) = self.json.as_mut() {
+            if let Err(e) = export::write_json_line(out.queue(), payment) {
+                tracing::error!("JSON output: {:#}", e);
+            }
+        }
+        if let Some(out) = self.cast.as_mut() {
+            if let Err(e) = export::write_cast_line(out.queue(), app.session_elapsed(), payment) {
+                tracing::error!("recording: {:#}", e);
+            }
+        }
+    }
+
+    fn export_csv(&mut self, app: &App, path: std::path::PathBuf) {
+        self.export = Some(export::CsvExport::new(app.all_session_payments(), app.tz, path));
+    }
+
+    // Writes whatever is due and retries whatever failed. Cheap when nothing
+    // changed, so it's called on every tick; it never blocks or gives up on the session
+    fn tick(&mut self, app: &App) -> Vec<Notice> {
+        let mut notices = Vec::new();
+        for out in [&mut self.json, &mut self.cast].into_iter().flatten() {
+            if let export::WriteOutcome::Failing(message) = out.flush(false) {
+                notices.push(Notice::Warning(message));
+            }
+        }
+        if let Some(overlay) = self.overlay.as_mut() {
+            let contents = app.overlay_contents(overlay.wants_json());
+            if let export::WriteOutcome::Failing(message) = overlay.update(contents, false) {
+                notices.push(Notice::Warning(message));
+            }
+        }
+        if let Some(job) = self.export.as_mut() {
+            match job.attempt() {
+                export::WriteOutcome::Written => {
+                    notices.push(Notice::Status(format!("Exported to {}", job.path.display())));
+                    self.export = None;
+                }
+                _ => {
+                    if let Some(err) = job.gave_up() {
+                        notices.push(Notice::Warning(format!("Export failed: {}", err)));
+                        self.export = None;
+                    }
+                }
+            }
+        }
+        notices
+    }
 
-    let (tx, mut rx) = mpsc::unbounded_channel::<Vec<psp::Payment>>();
+    // A last try at everything still queued, then flushed to disk
+    fn finish(&mut self, app: &App) -> Result<()> {
+        if let Some(overlay) = self.overlay.as_mut() {
+            let contents = app.overlay_contents(overlay.wants_json());
+            overlay.update(contents, true);
+        }
+        for out in [&mut self.json, &mut self.cast].into_iter().flatten() {
+            out.flush(true);
+            out.sync()?;
+        }
+        Ok(())
+    }
+}
+
+fn show_notices(app: &mut App, notices: Vec<Notice>) {
+    for notice in notices {
+        match notice {
+            Notice::Warning(message) => app.push_error("Output".to_string(), message),
+            Notice::Status(message) => {
+                app.error_message = None;
+                app.status_message = Some(message);
+            }
+        }
+    }
+}
+
+fn print_notices(notices: Vec<Notice>) {
+    for notice in notices {
+        match notice {
+            Notice::Warning(message) | Notice::Status(message) => eprintln!("{}", message),
+        }
+    }
+}
+
+// Persist the session and stats and flush the output files on the way out
+fn finish_session(app: &App, cli: &Cli, outputs: &mut Outputs) -> Result<()> {
+    // A seeded run neither resumed a session nor leaves its replayable one behind
+    if app.has_session() && cli.seed.is_none() {
+        app.save_state()?;
+    }
+    app.save_stats()?;
+    outputs.finish(app)
+}
+
+// Ctrl-C, or SIGTERM on Unix. The Unix handlers are installed on the call rather than
+// on the first poll, so a signal sent before the future is awaited still counts
+fn shutdown_signal() -> impl std::future::Future<Output = ()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let signals = signal(SignalKind::interrupt()).and_then(|int| Ok((int, signal(SignalKind::terminate())?)));
+        async move {
+            match signals {
+                Ok((mut int, mut term)) => {
+                    tokio::select! {
+                        _ = int.recv() => {}
+                        _ = term.recv() => {}
+                    }
+                }
+                Err(_) => {
+                    tokio::signal::ctrl_c().await.ok();
+                }
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    async {
+        tokio::signal::ctrl_c().await.ok();
+    }
+}
+
+async fn run_app(terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>, cli: &Cli) -> Result<SessionSummary> {
+    let mut app = match cli_config(cli) {
+        Ok(Some(cfg)) => App::from_config(cfg),
+        Ok(None) => App::new(),
+        // Set up from scratch, but keep the broken file until setup is saved
+        Err(e) => {
+            tracing::error!("config could not be loaded: {:#}", e);
+            let mut app = App::new();
+            app.config_load_error = Some(format!("{:#}", e));
+            app
+        }
+    };
+    app.config.mock_seed = cli.seed;
+    app.compact = cli.compact;
+    app.welcome_first_run();
+    resume_session(&mut app, cli);
+    #[cfg(unix)]
+    let _ipc = serve_ipc(&app, cli)?;
+
+    let mut outputs = Outputs::open(cli)?;
+    load_online_rates(&mut app).await;
+
+    // Raw mode swallows Ctrl-C as a key; SIGTERM still arrives as a signal
+    let terminated = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    {
+        let terminated = terminated.clone();
+        let shutdown = shutdown_signal();
+        tokio::spawn(async move {
+            shutdown.await;
+            terminated.store(true, std::sync::atomic::Ordering::Relaxed);
+        });
+    }
+
+    let (mut tx, mut rx) = mpsc::unbounded_channel::<PollEvent>();
     let mut poll_handle: Option<tokio::task::JoinHandle<()>> = None;
+    let mut start_pending = true;
+    // A poller that died gets restarted at this time rather than left dead
+    let mut poll_restart: Option<std::time::Instant> = None;
+    let mut backoff = poll::RestartBackoff::new();
+    let refresh = Arc::new(Notify::new());
     let mut tick_count: u32 = 0;
+    let mut last_height = app.stack_height(terminal.size()?.height);
+    // A restored stack was laid out for whatever terminal it was saved from
+    app.drop_overflowing_bills(last_height);
+    app.reflow_bills(last_height);
+    let mut was_celebrating = false;
 
     loop {
+        if terminated.load(std::sync::atomic::Ordering::Relaxed) {
+            break;
+        }
+
+        // Bills keep the targets they were spawned with, so restack on resize
+        let height = app.stack_height(terminal.size()?.height);
+        if height != last_height {
+            app.reflow_bills(height);
+            last_height = height;
+        }
+
         terminal.draw(|f| ui::draw(f, &app))?;
 
-        // Handle incoming payments from background task
-        while let Ok(payments) = rx.try_recv() {
-            for p in payments {
-                app.add_payment(p);
+        // Handle incoming payments and errors from background task; every batch
+        // drained this tick is ingested together so they can be put in order
+        let mut incoming = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                PollEvent::Payments { provider, payments } => {
+                    app.record_poll_success(&provider);
+                    incoming.extend(payments);
+                }
+                PollEvent::Error { provider, message } => {
+                    app.record_poll_failure(&provider, &message);
+                    app.push_error(provider, message);
+                }
+                PollEvent::RateLimit { provider, limit } => app.record_rate_limit(&provider, limit),
             }
         }
+        if app.paused {
+            app.held_payments.extend(incoming);
+        } else if !incoming.is_empty() {
+            ingest_payments(&mut app, incoming, &mut outputs);
+        }
+        let notices = outputs.tick(&app);
+        show_notices(&mut app, notices);
 
         // Spawn pending bills with stagger
-        if tick_count % 3 == 0 && !app.pending_bills.is_empty() && app.phase == AppPhase::Running {
-            let h = terminal.size()?.height;
-            app.spawn_next_bill(h);
+        if tick_count.is_multiple_of(app.config.spawn_every()) && !app.pending_bills.is_empty() && app.phase == AppPhase::Running {
+            let h = app.stack_height(terminal.size()?.height);
+            let no_bills = app.is_compact(terminal.size()?.height) || (app.on_full() == OnFull::Stop && app.is_screen_full(h));
+            if no_bills {
+                // Money still counts; only the bills are skipped
+                app.drop_pending_bills();
+            } else {
+                for _ in 0..app.bills_per_spawn() {
+                    app.spawn_next_bill(h);
+                }
+            }
         }
 
         // Tick animations
         if app.phase == AppPhase::Running {
             app.tick_animations();
 
-            // Check if screen is full
-            let h = terminal.size()?.height;
-            if app.is_screen_full(h) && app.pending_bills.is_empty() {
-                app.phase = AppPhase::Celebration;
-                app.celebration_tick = 0;
+            if !app.is_compact(terminal.size()?.height) {
+                app.handle_full_screen(app.stack_height(terminal.size()?.height));
             }
         }
 
-        // Celebration timer
-        if app.phase == AppPhase::Celebration {
-            app.celebration_tick += 1;
-            if app.celebration_tick > 100 {
-                app.reset_session();
-            }
+        if app.phase == AppPhase::Celebration && !was_celebrating && app.config.sound {
+            notify::chime(notify::Cue::Celebration);
         }
+        was_celebrating = app.phase == AppPhase::Celebration;
 
-        // Waiting animation tick
-        if app.phase == AppPhase::Running && app.bills.is_empty() {
-            app.celebration_tick = tick_count; // reuse for dots animation
-        }
+        app.tick_timers();
 
         tick_count = tick_count.wrapping_add(1);
 
         // Poll events with short timeout for smooth animation
-        if event::poll(Duration::from_millis(50))? {
+        if event::poll(TICK)? {
             if let Event::Key(key) = event::read()? {
                 if key.kind != KeyEventKind::Press {
                     continue;
                 }
+                if is_quit_chord(&key) {
+                    break;
+                }
 
                 match app.phase {
                     AppPhase::Setup => {
                         if handle_setup_input(&mut app, key.code) {
-                            // Setup complete — save config and start polling
-                            save_config(&app.config)?;
-                            app.phase = AppPhase::Running;
-                            app.start_time = chrono::Utc::now();
-
-                            let providers = build_providers(&app.config.providers);
-                            let tx2 = tx.clone();
-                            let since = app.start_time;
-                            poll_handle = Some(tokio::spawn(async move {
-                                poll_payments(providers, tx2, since).await;
-                            }));
+                            if let Err(errors) = app.config.validate() {
+                                app.error_message = Some(describe_errors(&errors));
+                                continue;
+                            }
+                            // Check credentials before committing to them
+                            app.error_message = None;
+                            app.status_message = Some("Validating credentials…".to_string());
+                            terminal.draw(|f| ui::draw(f, &app))?;
+                            let accepted = match psp::registry::build_providers(&app.config) {
+                                Ok((providers, problems)) => credentials_accepted(&mut app, &providers, problems).await,
+                                Err(e) => {
+                                    app.error_message = Some(format!("{:#}", e));
+                                    false
+                                }
+                            };
+                            app.status_message = None;
+                            if !accepted {
+                                continue;
+                            }
+
+                            // Setup complete — save config and (re)start polling
+                            app.complete_setup(&FileConfigStore::default(), secrets::active_store().as_ref());
+
+                            // A fresh channel so results from the old providers can't leak in
+                            if let Some(h) = poll_handle.take() {
+                                h.abort();
+                            }
+                            (tx, rx) = mpsc::unbounded_channel();
+                            start_pending = false;
+                            poll_handle = app.start_polling(tx.clone(), refresh.clone())?;
                         }
-                        if matches!(key.code, KeyCode::Char('q')) && matches!(app.setup_step, SetupStep::Currency | SetupStep::ProviderSelect) {
+                        if quits_setup(&app.setup_step, key.code) {
                             break;
                         }
                     }
+                    AppPhase::Running if app.show_stats => match key.code {
+                        KeyCode::Char('s') | KeyCode::Esc => app.toggle_stats(),
+                        KeyCode::Char('o') => open_config_dir(&mut app),
+                        KeyCode::Char('q') => break,
+                        _ => {}
+                    },
+                    AppPhase::Running if app.show_ledger && app.ledger_filter_editing => match key.code {
+                        KeyCode::Char(c) => app.edit_ledger_query(|q| q.push(c)),
+                        KeyCode::Backspace => app.edit_ledger_query(|q| {
+                            q.pop();
+                        }),
+                        KeyCode::Enter => app.ledger_filter_editing = false,
+                        KeyCode::Esc => {
+                            app.edit_ledger_query(String::clear);
+                            app.ledger_filter_editing = false;
+                        }
+                        _ => {}
+                    },
+                    AppPhase::Running if app.show_ledger => match key.code {
+                        KeyCode::Char('l') | KeyCode::Esc => app.toggle_ledger(),
+                        KeyCode::Char('/') => app.ledger_filter_editing = true,
+                        KeyCode::Char('q') => break,
+                        KeyCode::Up => app.scroll_ledger(-1),
+                        KeyCode::Down => app.scroll_ledger(1),
+                        KeyCode::PageUp => app.scroll_ledger(-(LEDGER_PAGE as isize)),
+                        KeyCode::PageDown => app.scroll_ledger(LEDGER_PAGE as isize),
+                        _ => {}
+                    },
                     AppPhase::Running => {
                         if matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
                             break;
                         }
+                        if key.code == KeyCode::Char('l') {
+                            app.toggle_ledger();
+                        }
+                        if key.code == KeyCode::Char('s') {
+                            app.toggle_stats();
+                        }
+                        if key.code == KeyCode::Char('c') {
+                            app.enter_edit_mode();
+                        }
+                        if key.code == KeyCode::Char('p') {
+                            let held = app.toggle_pause();
+                            ingest_payments(&mut app, held, &mut outputs);
+                        }
+                        if key.code == KeyCode::Char('r') && app.request_refresh() {
+                            refresh.notify_one();
+                        }
+                        if key.code == KeyCode::Char('d') {
+                            app.recent_errors.clear();
+                            app.status_message = None;
+                        }
+                        if let KeyCode::Char(c @ '1'..='9') = key.code {
+                            app.toggle_provider(c as usize - '1' as usize);
+                        }
+                        if key.code == KeyCode::Char('y') {
+                            copy_summary(&mut app);
+                        }
+                        if key.code == KeyCode::Char('x') {
+                            app.celebrate_now();
+                        }
+                        if key.code == KeyCode::Char('o') {
+                            open_config_dir(&mut app);
+                        }
+                        if key.code == KeyCode::Char('e') {
+                            let path = config_dir().join(format!(
+                                "export-{}.csv",
+                                app.tz.format(chrono::Utc::now(), "%Y%m%d-%H%M%S")
+                            ));
+                            outputs.export_csv(&app, path);
+                        }
                     }
                     AppPhase::Celebration => {
                         if matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
                             break;
                         }
+                        if key.code == KeyCode::Char('y') {
+                            copy_summary(&mut app);
+                        }
                         if matches!(key.code, KeyCode::Enter | KeyCode::Char(' ')) {
                             app.reset_session();
                         }
@@ -171,81 +929,296 @@ async fn run_app(terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>) ->
             }
         }
 
-        // Start polling if we transitioned to Running from a loaded config
-        if app.phase == AppPhase::Running && poll_handle.is_none() && !app.config.providers.is_empty() {
-            let providers = build_providers(&app.config.providers);
-            let tx2 = tx.clone();
-            let since = app.start_time;
-            poll_handle = Some(tokio::spawn(async move {
-                poll_payments(providers, tx2, since).await;
-            }));
+        // Start polling if we transitioned to Running from a loaded config; once,
+        // so a config with nothing buildable reports that instead of retrying every frame
+        if start_pending && app.phase == AppPhase::Running && !app.config.providers.is_empty() {
+            start_pending = false;
+            poll_handle = app.start_polling(tx.clone(), refresh.clone())?;
+        }
+
+        if let Some(h) = poll_handle.take_if(|h| h.is_finished()) {
+            if let Some(reason) = poll::crash_reason(h.await) {
+                let delay = backoff.crashed();
+                tracing::error!("poller stopped ({}), restarting in {:?}", reason, delay);
+                app.push_error("Poller".to_string(), format!("stopped ({}); restarting in {}s", reason, delay.as_secs()));
+                poll_restart = Some(std::time::Instant::now() + delay);
+                // The panic message went straight to the terminal; repaint over it
+                terminal.clear()?;
+            }
+        }
+        if poll_restart.is_some_and(|at| at <= std::time::Instant::now()) {
+            poll_restart = None;
+            if app.phase != AppPhase::Setup && poll_handle.is_none() {
+                backoff.started();
+                poll_handle = app.start_polling(tx.clone(), refresh.clone())?;
+            }
         }
     }
 
+    // Count what already arrived, held payments included, before saving
     if let Some(h) = poll_handle {
         h.abort();
     }
+    let mut remaining = std::mem::take(&mut app.held_payments);
+    while let Ok(event) = rx.try_recv() {
+        if let PollEvent::Payments { payments, .. } = event {
+            remaining.extend(payments);
+        }
+    }
+    ingest_payments(&mut app, remaining, &mut outputs);
+    let saved = if app.config_unsaved {
+        app.persist_config(&FileConfigStore::default(), secrets::active_store().as_ref())
+    } else {
+        Ok(())
+    };
+    finish_session(&app, cli, &mut outputs)?;
+    saved.map(|()| app.session_summary(chrono::Utc::now()))
+}
 
+fn print_summary(summary: &SessionSummary) -> Result<()> {
+    println!("{}", serde_json::to_string(summary)?);
     Ok(())
 }
 
+fn open_config_dir(app: &mut App) {
+    let dir = config_dir();
+    match reveal::open_dir(&dir) {
+        Ok(()) => app.status_message = Some(format!("Opened {}", dir.display())),
+        Err(e) => app.error_message = Some(format!("Open failed: {}", e)),
+    }
+}
+
+fn copy_summary(app: &mut App) {
+    match clipboard::copy(&app.summary_text()) {
+        Ok(()) => {
+            app.error_message = None;
+            app.status_message = Some("Copied summary to clipboard".to_string());
+        }
+        Err(e) => app.error_message = Some(format!("Copy failed: {}", e)),
+    }
+}
+
+// Failures stay on the confirm screen, so setup can't complete with bad credentials
+async fn credentials_accepted(app: &mut App, providers: &[Arc<dyn PaymentProvider>], mut problems: Vec<String>) -> bool {
+    problems.extend(validate_providers(providers).await);
+    if problems.is_empty() {
+        return true;
+    }
+    app.error_message = Some(problems.join("; "));
+    false
+}
+
+// One validating fetch per real provider; returns a message per failure
+async fn validate_providers(providers: &[Arc<dyn PaymentProvider>]) -> Vec<String> {
+    let mut failures = Vec::new();
+    for provider in providers.iter().filter(|p| p.name() != "Mock") {
+        let check = tokio::time::timeout(
+            Duration::from_secs(15),
+            provider.fetch_recent_payments(chrono::Utc::now()),
+        )
+        .await;
+        match check {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => failures.push(format!("{}: {}", provider.name(), e)),
+            Err(_) => failures.push(format!("{}: timed out", provider.name())),
+        }
+    }
+    failures
+}
+
+// One fetch per provider, as a scriptable credentials check. Returns how many failed
+async fn run_check(config: &AppConfig) -> Result<usize> {
+    let (providers, problems) = psp::registry::build_providers(config)?;
+    for problem in &problems {
+        println!("FAIL {}", problem);
+    }
+    let mut failures = problems.len();
+    let since = chrono::Utc::now() - chrono::Duration::days(1);
+    for provider in &providers {
+        let check = tokio::time::timeout(Duration::from_secs(15), provider.fetch_recent_payments(since)).await;
+        match check {
+            Ok(Ok(payments)) => println!("OK   {}: {} payments", provider.name(), payments.len()),
+            Ok(Err(e)) => {
+                failures += 1;
+                println!("FAIL {}: {:#}", provider.name(), e);
+            }
+            Err(_) => {
+                failures += 1;
+                println!("FAIL {}: timed out", provider.name());
+            }
+        }
+    }
+    Ok(failures)
+}
+
+fn ingest_payments(app: &mut App, mut payments: Vec<psp::Payment>, outputs: &mut Outputs) {
+    psp::sort_chronologically(&mut payments);
+    let mut new_payments = 0;
+    for p in payments {
+        let payment = p.clone();
+        if app.add_payment(p) {
+            new_payments += 1;
+            notify_if_large(&app.config, &payment);
+            outputs.write(app, &payment);
+        }
+    }
+    if new_payments == 0 {
+        return;
+    }
+    if let Err(e) = app.save_stats() {
+        tracing::error!("saving stats failed: {:#}", e);
+        app.error_message = Some(format!("Saving stats failed: {}", e));
+    }
+    if let Some(cue) = notify::payment_cue(app.config.sound, new_payments) {
+        notify::chime(cue);
+    }
+}
+
+fn notify_if_large(config: &AppConfig, payment: &psp::Payment) {
+    if config.should_notify(payment.display_cents()) {
+        notify::large_payment(payment, money::format_money(payment.display_cents(), &config.money_format()));
+    }
+}
+
+// Merge live FX rates over the configured ones, if enabled
+async fn load_online_rates(app: &mut App) {
+    if !app.config.fx_online {
+        return;
+    }
+    let fetched = match psp::http_client(app.config.proxy.as_deref(), None) {
+        Ok(client) => tokio::time::timeout(
+            Duration::from_secs(5),
+            fx::fetch_online_rates(&client, &app.config.currency),
+        )
+        .await
+        .unwrap_or_else(|_| Err(anyhow::anyhow!("timed out"))),
+        Err(e) => Err(e),
+    };
+    match fetched {
+        Ok(rates) => app.fx.merge(rates),
+        Err(e) => app.push_error("FX".to_string(), format!("{:#}", e)),
+    }
+}
+
+const GOAL_STEP_CENTS: i64 = 10_000;
+
+// Rows moved by PageUp/PageDown in the ledger overlay
+const LEDGER_PAGE: usize = 10;
+
+// Ctrl-C quits from anywhere; it's checked before any handler could take the c as typing
+fn is_quit_chord(key: &KeyEvent) -> bool {
+    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)
+}
+
+// q quits setup too, except on the steps where it's just a letter
+fn quits_setup(step: &SetupStep, key: KeyCode) -> bool {
+    key == KeyCode::Char('q') && !step.is_text_entry()
+}
+
 fn handle_setup_input(app: &mut App, key: KeyCode) -> bool {
     match app.setup_step {
+        SetupStep::Welcome => {
+            if key == KeyCode::Enter {
+                app.config.seen_welcome = true;
+                app.goto_step(SetupStep::Currency);
+            }
+        }
         SetupStep::Currency => {
             match key {
-                KeyCode::Up => {
-                    if app.setup_currency_idx > 0 {
-                        app.setup_currency_idx -= 1;
+                KeyCode::Up if app.setup_currency_idx > 0 => {
+                    app.setup_currency_idx -= 1;
+                }
+                // One past the options is the "Custom…" row
+                KeyCode::Down if app.setup_currency_idx < app.currency_options().len() => {
+                    app.setup_currency_idx += 1;
+                }
+                KeyCode::Enter => match app.currency_options().get(app.setup_currency_idx) {
+                    Some(currency) => {
+                        app.select_currency(currency);
+                        app.goto_step(SetupStep::Theme);
                     }
+                    None => app.goto_step(SetupStep::CustomCurrency),
+                },
+                // The first step backs out of editing, if there's an edit to back out of
+                KeyCode::Esc if !app.step_back() => {
+                    app.cancel_edit();
                 }
-                KeyCode::Down => {
-                    if app.setup_currency_idx < CURRENCIES.len() - 1 {
-                        app.setup_currency_idx += 1;
+                _ => {}
+            }
+        }
+        SetupStep::CustomCurrency => {
+            match key {
+                KeyCode::Char(c) => {
+                    app.setup_input.push(c);
+                }
+                KeyCode::Backspace => {
+                    app.setup_input.pop();
+                }
+                KeyCode::Enter => {
+                    let input = app.setup_input.clone();
+                    match app.add_custom_currency(&input) {
+                        Ok(()) => {
+                            app.setup_input.clear();
+                            app.error_message = None;
+                            app.setup_currency_idx = app.currency_options().len() - 1;
+                            app.setup_step = SetupStep::Theme;
+                        }
+                        Err(e) => app.error_message = Some(e),
                     }
                 }
+                KeyCode::Esc => {
+                    app.step_back();
+                }
+                _ => {}
+            }
+        }
+        SetupStep::Theme => {
+            match key {
+                KeyCode::Up => app.step_theme(false),
+                KeyCode::Down => app.step_theme(true),
                 KeyCode::Enter => {
-                    let (code, sym) = CURRENCIES[app.setup_currency_idx];
-                    app.config.currency = code.to_string();
-                    app.config.currency_symbol = sym.to_string();
-                    app.setup_step = SetupStep::ProviderSelect;
+                    app.goto_step(SetupStep::ProviderSelect);
                     app.setup_cursor = 0;
                 }
+                KeyCode::Esc => {
+                    app.step_back();
+                }
                 _ => {}
             }
         }
         SetupStep::ProviderSelect => {
             match key {
-                KeyCode::Up => {
-                    if app.setup_cursor > 0 {
-                        app.setup_cursor -= 1;
-                    }
+                KeyCode::Up if app.setup_cursor > 0 => {
+                    app.setup_cursor -= 1;
                 }
-                KeyCode::Down => {
-                    if app.setup_cursor < app.provider_configs.len() - 1 {
-                        app.setup_cursor += 1;
-                    }
+                KeyCode::Down if app.setup_cursor < app.provider_configs.len() - 1 => {
+                    app.setup_cursor += 1;
                 }
                 KeyCode::Char(' ') => {
                     app.provider_configs[app.setup_cursor].enabled =
                         !app.provider_configs[app.setup_cursor].enabled;
                 }
+                KeyCode::Char('a') => {
+                    app.goto_step(SetupStep::ProviderLabel);
+                }
                 KeyCode::Enter => {
                     let any_enabled = app.provider_configs.iter().any(|p| p.enabled);
                     if any_enabled {
                         // Find first enabled provider that needs API key (Mock doesn't)
-                        if let Some(idx) = app.provider_configs.iter().position(|p| p.enabled && p.name != "Mock" && p.api_key.is_empty()) {
-                            app.current_provider_idx = idx;
-                            app.setup_input.clear();
-                            app.setup_step = SetupStep::ProviderApiKey;
+                        if let Some(idx) = (0..app.provider_configs.len()).find(|i| app.needs_credentials(*i)) {
+                            app.goto_provider_step(SetupStep::ProviderApiKey, idx);
                         } else {
-                            app.setup_step = SetupStep::Confirm;
+                            app.goto_step(SetupStep::Confirm);
                         }
                     }
                 }
+                KeyCode::Esc => {
+                    app.step_back();
+                }
                 _ => {}
             }
         }
-        SetupStep::ProviderApiKey => {
+        SetupStep::ProviderLabel => {
             match key {
                 KeyCode::Char(c) => {
                     app.setup_input.push(c);
@@ -253,71 +1226,121 @@ fn handle_setup_input(app: &mut App, key: KeyCode) -> bool {
                 KeyCode::Backspace => {
                     app.setup_input.pop();
                 }
-                KeyCode::Enter => {
-                    if !app.setup_input.is_empty() {
-                        app.provider_configs[app.current_provider_idx].api_key = app.setup_input.clone();
-                        app.setup_input.clear();
-
-                        // Adyen needs merchant account
-                        if app.provider_configs[app.current_provider_idx].name == "Adyen" {
-                            app.setup_step = SetupStep::ProviderMerchantAccount;
-                        } else {
-                            // Check for more providers needing keys
-                            advance_to_next_provider_or_confirm(app);
-                        }
+                KeyCode::Enter if !app.setup_input.trim().is_empty() => {
+                    let name = &app.provider_configs[app.setup_cursor].name;
+                    let label = app.setup_input.trim();
+                    if app.provider_configs.iter().any(|p| p.name == *name && p.label == label) {
+                        app.error_message = Some(format!("{} already has an account labelled {}", name, label));
+                    } else {
+                        let label = label.to_string();
+                        app.add_provider_instance(&label);
+                        app.step_back();
                     }
                 }
                 KeyCode::Esc => {
-                    app.setup_input.clear();
-                    app.setup_step = SetupStep::ProviderSelect;
+                    app.step_back();
                 }
                 _ => {}
             }
         }
-        SetupStep::ProviderMerchantAccount => {
+        SetupStep::ProviderApiKey => {
             match key {
-                KeyCode::Char(c) => {
+                // Pasted text can carry newlines and other control characters along
+                KeyCode::Char(c) if !c.is_control() => {
                     app.setup_input.push(c);
                 }
                 KeyCode::Backspace => {
                     app.setup_input.pop();
                 }
-                KeyCode::Enter => {
-                    if !app.setup_input.is_empty() {
-                        app.provider_configs[app.current_provider_idx].merchant_account = app.setup_input.clone();
-                        app.setup_input.clear();
+                KeyCode::Tab => {
+                    app.reveal_input = !app.reveal_input;
+                }
+                KeyCode::Enter if !app.setup_input.trim().is_empty() => {
+                    let name = app.provider_configs[app.current_provider_idx].name.clone();
+                    let key = app.setup_input.trim().to_string();
+                    let warning = key_format_warning(&name, &key);
+                    app.provider_configs[app.current_provider_idx].api_key = key;
+                    app.setup_input.clear();
+
+                    // Some providers need a second credential
+                    if credential_prompts(&name).second.is_some() {
+                        app.goto_step(SetupStep::ProviderSecondCredential);
+                    } else {
+                        // Check for more providers needing keys
                         advance_to_next_provider_or_confirm(app);
                     }
+                    app.error_message = warning;
                 }
                 KeyCode::Esc => {
+                    app.step_back();
+                }
+                _ => {}
+            }
+        }
+        SetupStep::ProviderSecondCredential => {
+            match key {
+                KeyCode::Char(c) if !c.is_control() => {
+                    app.setup_input.push(c);
+                }
+                KeyCode::Backspace => {
+                    app.setup_input.pop();
+                }
+                KeyCode::Tab => {
+                    app.reveal_input = !app.reveal_input;
+                }
+                KeyCode::Enter if !app.setup_input.trim().is_empty() => {
+                    app.provider_configs[app.current_provider_idx].second_credential = app.setup_input.trim().to_string();
                     app.setup_input.clear();
-                    app.setup_step = SetupStep::ProviderApiKey;
+                    advance_to_next_provider_or_confirm(app);
+                }
+                KeyCode::Esc => {
+                    app.step_back();
                 }
                 _ => {}
             }
         }
         SetupStep::Confirm => {
             match key {
+                KeyCode::Left | KeyCode::Char('-') if app.config.poll_interval_secs > MIN_POLL_INTERVAL_SECS => {
+                    app.config.poll_interval_secs -= 1;
+                }
+                KeyCode::Right | KeyCode::Char('+') => {
+                    app.config.poll_interval_secs += 1;
+                }
+                // Goal moves in steps of 100 whole units; zero means no goal
+                KeyCode::Up => {
+                    app.config.goal_cents = Some(app.config.goal_cents.unwrap_or(0) + GOAL_STEP_CENTS);
+                }
+                KeyCode::Down => {
+                    let goal = app.config.goal_cents.unwrap_or(0) - GOAL_STEP_CENTS;
+                    app.config.goal_cents = (goal > 0).then_some(goal);
+                }
                 KeyCode::Enter => {
-                    // Build final config
-                    app.config.providers.clear();
+                    // Build final config, keeping settings setup doesn't ask about
+                    let previous = std::mem::take(&mut app.config.providers);
                     for prov in &app.provider_configs {
                         if prov.enabled {
-                            let api_key = if prov.name == "Adyen" {
-                                format!("{}|{}", prov.api_key, prov.merchant_account)
-                            } else {
-                                prov.api_key.clone()
-                            };
+                            let label = Some(prov.label.clone()).filter(|l| !l.is_empty());
+                            let existing = previous
+                                .iter()
+                                .find(|p| p.provider == prov.name && p.label == label)
+                                .cloned()
+                                .unwrap_or_default();
+                            let second = Some(prov.second_credential.clone());
                             app.config.providers.push(PspConfig {
                                 provider: prov.name.clone(),
-                                api_key,
+                                api_key: prov.api_key.clone(),
+                                merchant_account: second.clone().filter(|_| prov.name == "Adyen"),
+                                api_secret: second.filter(|_| SECRET_PROVIDERS.contains(&prov.name.as_str())),
+                                label,
+                                ..existing
                             });
                         }
                     }
                     return true; // Setup complete
                 }
                 KeyCode::Esc => {
-                    app.setup_step = SetupStep::ProviderSelect;
+                    app.step_back();
                 }
                 _ => {}
             }
@@ -328,36 +1351,261 @@ fn handle_setup_input(app: &mut App, key: KeyCode) -> bool {
 
 fn advance_to_next_provider_or_confirm(app: &mut App) {
     let start = app.current_provider_idx + 1;
-    if let Some(idx) = app.provider_configs[start..].iter().position(|p| p.enabled && p.name != "Mock" && p.api_key.is_empty()) {
-        app.current_provider_idx = start + idx;
-        app.setup_input.clear();
-        app.setup_step = SetupStep::ProviderApiKey;
+    if let Some(idx) = (start..app.provider_configs.len()).find(|i| app.needs_credentials(*i)) {
+        app.goto_provider_step(SetupStep::ProviderApiKey, idx);
     } else {
-        app.setup_step = SetupStep::Confirm;
+        app.goto_step(SetupStep::Confirm);
     }
 }
 
-async fn poll_payments(
-    providers: Vec<Arc<dyn PaymentProvider>>,
-    tx: mpsc::UnboundedSender<Vec<psp::Payment>>,
-    since: chrono::DateTime<chrono::Utc>,
-) {
-    let mut interval = tokio::time::interval(Duration::from_secs(10));
-    loop {
-        interval.tick().await;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        for provider in &providers {
-            match provider.fetch_recent_payments(since).await {
-                Ok(payments) if !payments.is_empty() => {
-                    if tx.send(payments).is_err() {
-                        return;
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Poll error from {}: {}", provider.name(), e);
-                }
-                _ => {}
-            }
+    #[test]
+    fn since_takes_an_rfc3339_time_or_a_duration_back_from_now() {
+        let at = parse_since("2024-01-01T00:00:00Z").unwrap();
+        assert_eq!(at, chrono::DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap());
+        assert_eq!(parse_since("2024-01-01T02:00:00+02:00").unwrap(), at);
+
+        for (value, secs) in [("45s", 45), ("30m", 1_800), ("2h", 7_200), ("1d", 86_400)] {
+            let before = chrono::Utc::now();
+            let since = parse_since(value).unwrap();
+            let back = (before - since).num_seconds();
+            assert!((secs - 1..=secs).contains(&back), "{} went back {}s", value, back);
+        }
+    }
+
+    #[test]
+    fn since_rejects_garbage() {
+        for garbage in ["", "2", "h", "2w", "2 h", "-2h", "2h30m", "1.5h", "yesterday", "2024-13-01T00:00:00Z", "99999999999d"] {
+            let err = parse_since(garbage).unwrap_err();
+            assert!(err.contains("neither a duration"), "{:?}: {}", garbage, err);
+        }
+    }
+
+    fn app() -> App {
+        let dir = std::env::temp_dir().join(format!("profit-cli-bin-test-{}", std::process::id()));
+        config::set_config_dir(Some(&dir)).unwrap();
+        App::new()
+    }
+
+    #[test]
+    fn welcome_advances_on_enter_and_is_only_shown_once() {
+        let mut app = app();
+        app.welcome_first_run();
+        assert_eq!(app.setup_step, SetupStep::Welcome);
+        handle_setup_input(&mut app, KeyCode::Char('x'));
+        assert_eq!(app.setup_step, SetupStep::Welcome);
+        handle_setup_input(&mut app, KeyCode::Enter);
+        assert_eq!(app.setup_step, SetupStep::Currency);
+        assert!(app.config.seen_welcome);
+
+        // The next launch loads seen_welcome and starts at the currency picker
+        let mut next = App::new();
+        next.config.seen_welcome = app.config.seen_welcome;
+        next.welcome_first_run();
+        assert_eq!(next.setup_step, SetupStep::Currency);
+
+        // A config that failed to load isn't a first run either
+        let mut broken = App::new();
+        broken.config_load_error = Some("expected value at line 1".to_string());
+        broken.welcome_first_run();
+        assert_eq!(broken.setup_step, SetupStep::Currency);
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            handle_setup_input(app, KeyCode::Char(c));
+        }
+    }
+
+    #[test]
+    fn walking_setup_backward_keeps_what_was_entered() {
+        let mut app = app();
+        let adyen = app.provider_configs.iter().position(|p| p.name == "Adyen").unwrap();
+        handle_setup_input(&mut app, KeyCode::Down);
+        handle_setup_input(&mut app, KeyCode::Enter);
+        assert_eq!(app.setup_step, SetupStep::Theme);
+        handle_setup_input(&mut app, KeyCode::Down);
+        let theme = app.config.theme.clone();
+        handle_setup_input(&mut app, KeyCode::Enter);
+        assert_eq!(app.setup_step, SetupStep::ProviderSelect);
+        app.setup_cursor = adyen;
+        handle_setup_input(&mut app, KeyCode::Char(' '));
+        handle_setup_input(&mut app, KeyCode::Enter);
+        assert_eq!((app.setup_step.clone(), app.current_provider_idx), (SetupStep::ProviderApiKey, adyen));
+        type_text(&mut app, "AQEkey");
+        handle_setup_input(&mut app, KeyCode::Enter);
+        assert_eq!(app.setup_step, SetupStep::ProviderSecondCredential);
+        type_text(&mut app, "AcmeECOM");
+        handle_setup_input(&mut app, KeyCode::Enter);
+        assert_eq!(app.setup_step, SetupStep::Confirm);
+
+        // Back through every step, each reopening with what it had
+        handle_setup_input(&mut app, KeyCode::Esc);
+        assert_eq!((app.setup_step.clone(), app.setup_input.as_str()), (SetupStep::ProviderSecondCredential, "AcmeECOM"));
+        handle_setup_input(&mut app, KeyCode::Esc);
+        assert_eq!((app.setup_step.clone(), app.setup_input.as_str()), (SetupStep::ProviderApiKey, "AQEkey"));
+        handle_setup_input(&mut app, KeyCode::Esc);
+        assert_eq!(app.setup_step, SetupStep::ProviderSelect);
+        assert!(app.provider_configs[adyen].enabled);
+        handle_setup_input(&mut app, KeyCode::Esc);
+        assert_eq!(app.setup_step, SetupStep::Theme);
+        assert_eq!(app.config.theme, theme);
+        handle_setup_input(&mut app, KeyCode::Esc);
+        assert_eq!(app.setup_step, SetupStep::Currency);
+        assert_eq!(app.setup_currency_idx, 1);
+        assert!(app.setup_history.is_empty());
+    }
+
+    #[test]
+    fn pasted_keys_lose_control_characters_and_surrounding_whitespace() {
+        let mut app = app();
+        let gocardless = app.provider_configs.iter().position(|p| p.name == "GoCardless").unwrap();
+        app.provider_configs[gocardless].enabled = true;
+        app.goto_provider_step(SetupStep::ProviderApiKey, gocardless);
+        type_text(&mut app, "  live_abc\u{1b}[201~\r\n\t ");
+        assert_eq!(app.setup_input, "  live_abc[201~ ");
+        app.setup_input = "  live_abc \n".to_string();
+        handle_setup_input(&mut app, KeyCode::Enter);
+        assert_eq!(app.provider_configs[gocardless].api_key, "live_abc");
+        assert_eq!(app.error_message, None);
+
+        // A key in an unexpected format is stored anyway, with a warning
+        app.goto_provider_step(SetupStep::ProviderApiKey, gocardless);
+        app.setup_input = " test_abc".to_string();
+        handle_setup_input(&mut app, KeyCode::Enter);
+        assert_eq!(app.provider_configs[gocardless].api_key, "test_abc");
+        assert!(app.error_message.as_deref().is_some_and(|w| w.contains("live_ or sandbox_")));
+    }
+
+    #[tokio::test]
+    async fn fixing_credentials_in_the_editor_restarts_polling() {
+        let mut app = app();
+        app.config.providers = vec![PspConfig {
+            provider: "Adyen".to_string(),
+            api_key: "AQEkey".to_string(),
+            ..PspConfig::default()
+        }];
+        app.finish_setup();
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let refresh = Arc::new(Notify::new());
+        // No merchant account, so nothing can be polled
+        assert!(app.start_polling(tx.clone(), refresh.clone()).unwrap().is_none());
+        assert!(app.error_message.as_deref().is_some_and(|e| e.contains("press c")));
+
+        app.enter_edit_mode();
+        for key in [KeyCode::Enter, KeyCode::Enter, KeyCode::Enter] {
+            handle_setup_input(&mut app, key);
+        }
+        // The key that's there is offered again, and the missing merchant account asked for
+        assert_eq!((app.setup_step.clone(), app.setup_input.as_str()), (SetupStep::ProviderApiKey, "AQEkey"));
+        handle_setup_input(&mut app, KeyCode::Enter);
+        assert_eq!(app.setup_step, SetupStep::ProviderSecondCredential);
+        type_text(&mut app, "AcmeECOM");
+        handle_setup_input(&mut app, KeyCode::Enter);
+        assert!(handle_setup_input(&mut app, KeyCode::Enter));
+        app.complete_setup(&config::InMemoryConfigStore::new(), &secrets::InMemorySecretStore::new());
+
+        let (providers, problems) = psp::registry::build_providers(&app.config).unwrap();
+        assert_eq!((providers.len(), problems.len()), (1, 0));
+        let poller = app.start_polling(tx, refresh).unwrap().expect("polling restarts");
+        assert!(!poller.is_finished());
+        poller.abort();
+    }
+
+    #[test]
+    fn only_a_working_terminal_gets_the_tui() {
+        let entered = std::cell::Cell::new(0);
+        let enter = || {
+            entered.set(entered.get() + 1);
+            Ok(())
+        };
+        assert_eq!(choose_mode(false, true, enter), Mode::Tui);
+        assert_eq!(choose_mode(false, false, enter), Mode::Headless);
+        assert_eq!(choose_mode(true, true, enter), Mode::Headless);
+        // The terminal is left alone unless the TUI is actually wanted
+        assert_eq!(entered.get(), 1);
+
+        let unsupported = || Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "no alternate screen"));
+        assert_eq!(choose_mode(false, true, unsupported), Mode::Headless);
+    }
+
+    #[test]
+    fn demo_starts_running_with_only_the_mock_provider() {
+        let cli = Cli::parse_from(["profit-cli", "--demo"]);
+        let app = App::from_config(cli_config(&cli).unwrap().unwrap());
+        assert_eq!(app.phase, AppPhase::Running);
+        let providers: Vec<&str> = app.config.providers.iter().map(|p| p.provider.as_str()).collect();
+        assert_eq!(providers, ["Mock"]);
+        assert!(app.config.ephemeral);
+    }
+
+    #[test]
+    fn the_quit_chord_works_mid_key_without_eating_letters() {
+        let mut app = app();
+        let gocardless = app.provider_configs.iter().position(|p| p.name == "GoCardless").unwrap();
+        app.provider_configs[gocardless].enabled = true;
+        app.goto_provider_step(SetupStep::ProviderApiKey, gocardless);
+
+        // q and a plain c are part of the key
+        type_text(&mut app, "live_qc");
+        assert!(!quits_setup(&app.setup_step, KeyCode::Char('q')));
+        assert!(!is_quit_chord(&KeyEvent::new(KeyCode::Char('c'), KeyModifiers::NONE)));
+        assert!(!is_quit_chord(&KeyEvent::new(KeyCode::Char('q'), KeyModifiers::CONTROL)));
+        assert_eq!(app.setup_input, "live_qc");
+
+        // Ctrl-C quits from here, as it does from any step
+        assert!(is_quit_chord(&KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)));
+        assert!(is_quit_chord(&KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL | KeyModifiers::SHIFT)));
+
+        // Steps without text entry still quit on q
+        for step in [SetupStep::Welcome, SetupStep::Currency, SetupStep::ProviderSelect, SetupStep::Theme, SetupStep::Confirm] {
+            assert!(quits_setup(&step, KeyCode::Char('q')), "{:?}", step);
+        }
+        for step in [SetupStep::CustomCurrency, SetupStep::ProviderLabel, SetupStep::ProviderSecondCredential] {
+            assert!(!quits_setup(&step, KeyCode::Char('q')), "{:?}", step);
+        }
+    }
+
+    // Turns down every key, the way a provider answers a typo'd credential
+    struct RejectingProvider;
+
+    #[async_trait::async_trait]
+    impl PaymentProvider for RejectingProvider {
+        fn name(&self) -> &str {
+            "Adyen"
+        }
+
+        async fn fetch_recent_payments(&self, _since: chrono::DateTime<chrono::Utc>) -> Result<Vec<psp::Payment>> {
+            anyhow::bail!("401 Unauthorized")
+        }
+    }
+
+    #[tokio::test]
+    async fn a_rejected_credential_keeps_setup_on_the_confirm_step() {
+        let mut app = app();
+        app.setup_step = SetupStep::Confirm;
+        let mock: Arc<dyn PaymentProvider> = Arc::new(psp::mock::MockProvider::new());
+        assert!(credentials_accepted(&mut app, std::slice::from_ref(&mock), Vec::new()).await);
+        assert_eq!(app.error_message, None);
+
+        let rejecting: Arc<dyn PaymentProvider> = Arc::new(RejectingProvider);
+        assert!(!credentials_accepted(&mut app, &[mock, rejecting], Vec::new()).await);
+        assert_eq!(app.error_message.as_deref(), Some("Adyen: 401 Unauthorized"));
+        assert!(matches!(app.phase, AppPhase::Setup));
+        assert_eq!(app.setup_step, SetupStep::Confirm);
+    }
+
+    #[test]
+    fn import_speed_must_be_a_sane_positive_number() {
+        assert_eq!(parse_speed("1"), Ok(1.0));
+        assert_eq!(parse_speed("0.5"), Ok(0.5));
+        assert_eq!(parse_speed("60"), Ok(60.0));
+        for bad in ["0", "-2", "1e-300", "1e12", "NaN", "inf", "fast"] {
+            assert!(parse_speed(bad).is_err(), "{} accepted", bad);
         }
+        assert_eq!(parse_speed("0").unwrap_err(), "'0' is not a speed between 0.001 and 1000000");
     }
 }
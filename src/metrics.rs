@@ -0,0 +1,136 @@
+use anyhow::Result;
+use http_body_util::Full;
+use hyper::body::Bytes;
+use hyper::{Request, Response, StatusCode};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+// Counters shared between the headless loop and the /metrics server
+#[derive(Clone, Default)]
+pub struct Metrics {
+    inner: Arc<Mutex<Counters>>,
+}
+
+#[derive(Default)]
+struct Counters {
+    total_cents: i64,
+    payments: BTreeMap<String, u64>,
+    errors: BTreeMap<String, u64>,
+}
+
+impl Metrics {
+    pub fn set_total(&self, total_cents: i64) {
+        self.inner.lock().unwrap().total_cents = total_cents;
+    }
+
+    pub fn record_payment(&self, provider: &str, total_cents: i64) {
+        let mut counters = self.inner.lock().unwrap();
+        counters.total_cents = total_cents;
+        *counters.payments.entry(provider.to_string()).or_default() += 1;
+    }
+
+    pub fn record_error(&self, provider: &str) {
+        *self.inner.lock().unwrap().errors.entry(provider.to_string()).or_default() += 1;
+    }
+
+    // Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let counters = self.inner.lock().unwrap();
+        let mut out = String::new();
+        writeln!(out, "# HELP profit_total_cents Session total in minor currency units.").ok();
+        writeln!(out, "# TYPE profit_total_cents gauge").ok();
+        writeln!(out, "profit_total_cents {}", counters.total_cents).ok();
+        writeln!(out, "# HELP profit_payments_total Payments counted this session.").ok();
+        writeln!(out, "# TYPE profit_payments_total counter").ok();
+        for (provider, count) in &counters.payments {
+            writeln!(out, "profit_payments_total{{provider=\"{}\"}} {}", escape_label(provider), count).ok();
+        }
+        writeln!(out, "# HELP profit_provider_errors_total Failed provider fetches.").ok();
+        writeln!(out, "# TYPE profit_provider_errors_total counter").ok();
+        for (provider, count) in &counters.errors {
+            writeln!(out, "profit_provider_errors_total{{provider=\"{}\"}} {}", escape_label(provider), count).ok();
+        }
+        out
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn respond(metrics: &Metrics, req: &Request<hyper::body::Incoming>) -> Response<Full<Bytes>> {
+    if req.uri().path() != "/metrics" {
+        let mut not_found = Response::new(Full::new(Bytes::from_static(b"not found\n")));
+        *not_found.status_mut() = StatusCode::NOT_FOUND;
+        return not_found;
+    }
+    let mut response = Response::new(Full::new(Bytes::from(metrics.render())));
+    response.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("text/plain; version=0.0.4"),
+    );
+    response
+}
+
+// Binds before returning so a bad address fails at startup; abort the handle to stop serving
+pub async fn serve(addr: SocketAddr, metrics: Metrics) -> Result<tokio::task::JoinHandle<()>> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    Ok(tokio::spawn(async move {
+        loop {
+            let Ok((stream, _)) = listener.accept().await else {
+                continue;
+            };
+            let metrics = metrics.clone();
+            tokio::spawn(async move {
+                let service = hyper::service::service_fn(move |req| {
+                    let response = respond(&metrics, &req);
+                    async move { Ok::<_, std::convert::Infallible>(response) }
+                });
+                hyper::server::conn::http1::Builder::new()
+                    .serve_connection(hyper_util::rt::TokioIo::new(stream), service)
+                    .await
+                    .ok();
+            });
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn handler_serves_prometheus_text() {
+        let metrics = Metrics::default();
+        metrics.record_payment("Adyen", 1_250);
+        metrics.record_payment("Adyen", 2_000);
+        metrics.record_payment("PayPal \"US\"", 2_500);
+        metrics.record_error("Mock");
+
+        let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let handle = serve(addr, metrics).await.unwrap();
+        let client = reqwest::Client::new();
+        let resp = client.get(format!("http://{}/metrics", addr)).send().await.unwrap();
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers()["content-type"], "text/plain; version=0.0.4");
+        let body = resp.text().await.unwrap();
+        let samples: Vec<&str> = body.lines().filter(|l| !l.starts_with('#')).collect();
+        assert_eq!(
+            samples,
+            [
+                "profit_total_cents 2500",
+                "profit_payments_total{provider=\"Adyen\"} 2",
+                "profit_payments_total{provider=\"PayPal \\\"US\\\"\"} 1",
+                "profit_provider_errors_total{provider=\"Mock\"} 1",
+            ]
+        );
+        assert!(body.contains("# TYPE profit_total_cents gauge\n"));
+        assert!(body.contains("# TYPE profit_payments_total counter\n"));
+
+        let missing = client.get(format!("http://{}/", addr)).send().await.unwrap();
+        assert_eq!(missing.status(), 404);
+        handle.abort();
+    }
+}
@@ -0,0 +1,53 @@
+// Session milestones worth a toast: the first payment, every so much revenue, every so
+// many payments, and a new largest payment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Milestone {
+    FirstPayment,
+    // The multiple of milestone_every_cents the total just passed
+    Total(i64),
+    Count(usize),
+    Largest(i64),
+}
+
+// The session before or after a payment is counted
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Progress {
+    pub total_cents: i64,
+    pub count: usize,
+    pub largest_cents: i64,
+}
+
+// Unset thresholds give no toasts of that kind
+#[derive(Debug, Clone, Copy)]
+pub struct Thresholds {
+    pub every_cents: Option<i64>,
+    pub every_payments: Option<usize>,
+}
+
+// A new largest payment only counts once there's something to compare it with
+pub const LARGEST_AFTER: usize = 10;
+
+// What one payment crossed, each at most once. A payment that passes several multiples
+// of every_cents at once reports only the highest
+pub fn crossed(before: Progress, after: Progress, thresholds: Thresholds) -> Vec<Milestone> {
+    let mut crossed = Vec::new();
+    if before.count == 0 && after.count > 0 {
+        crossed.push(Milestone::FirstPayment);
+    }
+    if let Some(every) = thresholds.every_cents.filter(|e| *e > 0) {
+        let reached = after.total_cents.div_euclid(every);
+        if reached > before.total_cents.div_euclid(every) && reached > 0 {
+            crossed.push(Milestone::Total(reached.saturating_mul(every)));
+        }
+    }
+    if let Some(every) = thresholds.every_payments.filter(|e| *e > 0) {
+        let reached = after.count / every;
+        if reached > before.count / every {
+            crossed.push(Milestone::Count(reached * every));
+        }
+    }
+    if before.count >= LARGEST_AFTER && after.largest_cents > before.largest_cents {
+        crossed.push(Milestone::Largest(after.largest_cents));
+    }
+    crossed
+}
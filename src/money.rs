@@ -0,0 +1,113 @@
+// Laying out amounts in the configured currency, shared by the app model and the UI
+use crate::config::SymbolPosition;
+
+// Everything format_money needs to lay out an amount
+pub struct MoneyFormat<'a> {
+    pub symbol: &'a str,
+    pub position: SymbolPosition,
+    pub decimal_separator: &'a str,
+    pub thousands_separator: &'a str,
+    // Zero shows whole units; amounts are kept in hundredths, so digits past the second are zeros
+    pub decimals: u32,
+}
+
+pub fn format_money(cents: i64, money: &MoneyFormat) -> String {
+    let whole = if money.decimals == 0 { cents.saturating_add(cents.signum() * 50) / 100 } else { cents / 100 };
+    let frac = (cents % 100).abs();
+    // Add thousand separators
+    let whole_str = {
+        let s = whole.to_string();
+        let mut result = String::new();
+        for (i, c) in s.chars().rev().enumerate() {
+            if i > 0 && i % 3 == 0 && c != '-' {
+                result.push_str(&money.thousands_separator.chars().rev().collect::<String>());
+            }
+            result.push(c);
+        }
+        result.chars().rev().collect::<String>()
+    };
+    if money.decimals == 0 {
+        return with_symbol(whole_str, money);
+    }
+    let padding = "0".repeat(money.decimals.saturating_sub(2) as usize);
+    with_symbol(format!("{}{}{:02}{}", whole_str, money.decimal_separator, frac, padding), money)
+}
+
+// "€12.3M" for amounts of a thousand or more, for when the full figure won't fit
+pub fn abbreviate_money(cents: i64, money: &MoneyFormat) -> String {
+    const SUFFIXES: &[(f64, &str)] = &[(1e9, "B"), (1e6, "M"), (1e3, "K")];
+    let units = cents as f64 / 100.0;
+    let Some((scale, suffix)) = SUFFIXES.iter().find(|(scale, _)| units.abs() >= *scale) else {
+        return format_money(cents, money);
+    };
+    let scaled = format!("{:.1}", units / scale);
+    let scaled = scaled.strip_suffix(".0").unwrap_or(&scaled).replace('.', money.decimal_separator);
+    with_symbol(format!("{}{}", scaled, suffix), money)
+}
+
+pub fn with_symbol(number: String, money: &MoneyFormat) -> String {
+    match money.position {
+        SymbolPosition::Prefix => format!("{}{}", money.symbol, number),
+        SymbolPosition::Suffix => format!("{} {}", number, money.symbol),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+
+    fn layout<'a>(symbol: &'a str, position: SymbolPosition, decimal: &'a str, thousands: &'a str) -> MoneyFormat<'a> {
+        MoneyFormat { symbol, position, decimal_separator: decimal, thousands_separator: thousands, decimals: 2 }
+    }
+
+    #[test]
+    fn default_layout_is_prefix_comma_dot() {
+        let eur = layout("€", SymbolPosition::Prefix, ".", ",");
+        assert_eq!(format_money(123_456_789, &eur), "€1,234,567.89");
+        assert_eq!(format_money(5, &eur), "€0.05");
+    }
+
+    #[test]
+    fn suffix_symbol_with_space_thousands_and_comma_decimals() {
+        let nok = layout("kr", SymbolPosition::Suffix, ",", " ");
+        assert_eq!(format_money(123_456, &nok), "1 234,56 kr");
+        assert_eq!(format_money(99, &nok), "0,99 kr");
+        assert_eq!(abbreviate_money(1_250_000, &nok), "12,5K kr");
+    }
+
+    #[test]
+    fn abbreviations_step_up_at_each_thousand() {
+        let eur = layout("€", SymbolPosition::Prefix, ".", ",");
+        assert_eq!(abbreviate_money(99_999, &eur), "€999.99");
+        assert_eq!(abbreviate_money(100_000, &eur), "€1K");
+        assert_eq!(abbreviate_money(123_456, &eur), "€1.2K");
+        assert_eq!(abbreviate_money(100_000_000, &eur), "€1M");
+        assert_eq!(abbreviate_money(1_234_567_890, &eur), "€12.3M");
+        assert_eq!(abbreviate_money(250_000_000_000, &eur), "€2.5B");
+        assert_eq!(abbreviate_money(-150_000, &eur), "€-1.5K");
+    }
+
+    #[test]
+    fn locale_layouts_over_the_same_amount() {
+        let de = layout("€", SymbolPosition::Suffix, ",", ".");
+        let fr = layout("€", SymbolPosition::Suffix, ",", " ");
+        let en = layout("€", SymbolPosition::Prefix, ".", ",");
+        assert_eq!(format_money(123_456, &de), "1.234,56 €");
+        assert_eq!(format_money(123_456, &fr), "1 234,56 €");
+        assert_eq!(format_money(123_456, &en), "€1,234.56");
+    }
+
+    #[test]
+    fn minor_units_decide_the_fraction() {
+        let config = |code: &str| AppConfig { currency: code.to_string(), ..AppConfig::default() };
+        let jpy = config("JPY");
+        assert_eq!(jpy.money_format().decimals, 0);
+        assert_eq!(format_money(500_050, &jpy.money_format()), "€5,001");
+        let kwd = config("KWD");
+        assert_eq!(kwd.money_format().decimals, 3);
+        assert_eq!(format_money(123_456, &kwd.money_format()), "€1,234.560");
+        let custom = AppConfig { currency_decimals: 0, ..config("EUR") };
+        assert_eq!(format_money(123_456, &custom.money_format()), "€1,235");
+    }
+}
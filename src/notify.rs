@@ -0,0 +1,66 @@
+use crate::psp::Payment;
+use std::io::Write;
+
+// Desktop notification for a large payment. Fire-and-forget: without the
+// `notifications` feature, or without a notification daemon, this does nothing.
+#[cfg(feature = "notifications")]
+pub fn large_payment(payment: &Payment, amount: String) {
+    let body = format!("{} from {}", amount, payment.provider);
+    tokio::task::spawn_blocking(move || {
+        notify_rust::Notification::new()
+            .summary("profit-cli: big payment!")
+            .body(&body)
+            .show()
+            .ok();
+    });
+}
+
+#[cfg(not(feature = "notifications"))]
+pub fn large_payment(_payment: &Payment, _amount: String) {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cue {
+    Payment,
+    Celebration,
+}
+
+// One chime per batch that counted anything, however many bills it turns into;
+// duplicates and muted payments ring nothing
+pub fn payment_cue(sound: bool, new_payments: usize) -> Option<Cue> {
+    (sound && new_payments > 0).then_some(Cue::Payment)
+}
+
+// Terminal bell; the celebration rings twice so it stands out
+pub fn chime(cue: Cue) {
+    let bells = match cue {
+        Cue::Payment => "\x07",
+        Cue::Celebration => "\x07\x07",
+    };
+    let mut out = std::io::stdout();
+    out.write_all(bells.as_bytes()).ok();
+    out.flush().ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::App;
+    use crate::psp::ScriptedProvider;
+
+    #[test]
+    fn chimes_once_per_batch_of_new_payments() {
+        crate::config::use_temp_config_dir();
+        let mut app = App::new();
+        let now = chrono::Utc::now();
+        let mut ingest = |ids: &[&str]| {
+            let new = ids.iter().filter(|id| app.add_payment(ScriptedProvider::payment("Mock", id, 5_000, now))).count();
+            payment_cue(true, new)
+        };
+        // A €50 payment is several bills but one cue
+        assert_eq!(ingest(&["a"]), Some(Cue::Payment));
+        assert_eq!(ingest(&["b", "c"]), Some(Cue::Payment));
+        // Seen again through the poll overlap
+        assert_eq!(ingest(&["a", "c"]), None);
+        assert_eq!(payment_cue(false, 3), None);
+    }
+}
@@ -0,0 +1,76 @@
+use rand::Rng;
+use ratatui::style::Color;
+
+// Positions are fractions of the screen so particles survive resizes
+#[derive(Debug, Clone)]
+pub struct Particle {
+    pub x: f64,
+    pub y: f64,
+    pub vx: f64,
+    pub vy: f64,
+    pub glyph: &'static str,
+    pub color: Color,
+}
+
+const GLYPHS: &[&str] = &["*", "•", "+", "✦", "▪", "~"];
+
+// Per tick, in screen heights
+const GRAVITY: f64 = 0.0004;
+
+// Starts above the screen so the confetti rains in rather than popping up
+pub fn spawn_confetti(count: usize, colors: &[Color]) -> Vec<Particle> {
+    let mut rng = rand::thread_rng();
+    (0..count)
+        .map(|_| Particle {
+            x: rng.gen_range(0.0..1.0),
+            y: rng.gen_range(-1.0..0.0),
+            vx: rng.gen_range(-0.003..0.003),
+            vy: rng.gen_range(0.002..0.01),
+            glyph: GLYPHS[rng.gen_range(0..GLYPHS.len())],
+            color: colors[rng.gen_range(0..colors.len())],
+        })
+        .collect()
+}
+
+// Steps the physics by dt ticks and drops whatever fell off the bottom
+pub fn advance_particles(particles: &mut Vec<Particle>, dt: f64) {
+    for p in particles.iter_mut() {
+        p.vy += GRAVITY * dt;
+        p.x = (p.x + p.vx * dt).rem_euclid(1.0);
+        p.y += p.vy * dt;
+    }
+    particles.retain(|p| p.y < 1.0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn particle(y: f64, vy: f64) -> Particle {
+        Particle { x: 0.5, y, vx: 0.0, vy, glyph: "*", color: Color::Yellow }
+    }
+
+    #[test]
+    fn particles_fall_and_speed_up() {
+        let mut particles = vec![particle(0.2, 0.01)];
+        advance_particles(&mut particles, 1.0);
+        let p = &particles[0];
+        assert!(p.y > 0.2 && p.vy > 0.01);
+        assert_eq!(p.x, 0.5);
+    }
+
+    #[test]
+    fn particles_off_the_bottom_are_dropped() {
+        let mut particles = vec![particle(0.995, 0.01), particle(-0.5, 0.01)];
+        advance_particles(&mut particles, 1.0);
+        assert_eq!(particles.len(), 1);
+        assert!(particles[0].y < 0.0);
+    }
+
+    #[test]
+    fn confetti_starts_above_the_screen() {
+        let confetti = spawn_confetti(50, &[Color::Red, Color::Green]);
+        assert_eq!(confetti.len(), 50);
+        assert!(confetti.iter().all(|p| p.y < 0.0 && (0.0..1.0).contains(&p.x)));
+    }
+}
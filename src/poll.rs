@@ -0,0 +1,816 @@
+use futures_util::stream::{BoxStream, StreamExt};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Notify};
+
+use crate::config::in_quiet_hours;
+use crate::psp::{Payment, PaymentProvider, RateLimit};
+use crate::tz::DisplayTz;
+
+#[derive(Debug)]
+pub enum PollEvent {
+    // Sent after every successful fetch, even when nothing new came in
+    Payments { provider: String, payments: Vec<Payment> },
+    Error { provider: String, message: String },
+    // Sent before a fetch's result when the provider's response reported its quota
+    RateLimit { provider: String, limit: RateLimit },
+}
+
+// How the poller paces itself beyond each provider's own interval
+#[derive(Debug, Clone, Copy)]
+pub struct PollSettings {
+    // With bounds, the per-provider intervals only set where each provider starts
+    pub adaptive: Option<(Duration, Duration)>,
+    // How far behind the newest payment each next window starts
+    pub overlap: chrono::Duration,
+    // Start and end hour in `tz` during which scheduled fetches are skipped
+    pub quiet_hours: Option<(u8, u8)>,
+    pub tz: DisplayTz,
+}
+
+impl PollSettings {
+    fn is_quiet(&self) -> bool {
+        self.quiet_hours.is_some_and(|window| in_quiet_hours(window, self.tz.hour(chrono::Utc::now())))
+    }
+}
+
+// `windows` holds where each provider's first fetch starts and `intervals` how often it's polled
+pub fn spawn_poller(
+    providers: Vec<Arc<dyn PaymentProvider>>,
+    windows: Vec<chrono::DateTime<chrono::Utc>>,
+    intervals: Vec<Duration>,
+    settings: PollSettings,
+    tx: mpsc::UnboundedSender<PollEvent>,
+    refresh: Arc<Notify>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        // Stream forwarders live in the set, so aborting this task stops them too
+        let mut streams = tokio::task::JoinSet::new();
+        let mut polled = Vec::new();
+        let mut polled_windows = Vec::new();
+        let mut polled_intervals = Vec::new();
+        for ((provider, since), every) in providers.into_iter().zip(windows).zip(intervals) {
+            match provider.payment_stream() {
+                Some(stream) => {
+                    streams.spawn(forward_stream(provider.name().to_string(), stream, tx.clone()));
+                }
+                None => {
+                    polled.push(provider);
+                    polled_windows.push(since);
+                    polled_intervals.push(Cadence::new(every, settings.adaptive));
+                }
+            }
+        }
+        poll_payments(Poller::new(polled), tx, polled_windows, polled_intervals, settings, refresh).await;
+    })
+}
+
+// Longest wait before restarting a crashed poller
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(60);
+
+// Paces restarts of a poller that keeps panicking: 1s, 2s, 4s… up to
+// MAX_RESTART_DELAY, starting over once a run outlasts that
+pub struct RestartBackoff {
+    crashes: u32,
+    started: std::time::Instant,
+}
+
+impl Default for RestartBackoff {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RestartBackoff {
+    pub fn new() -> Self {
+        Self {
+            crashes: 0,
+            started: std::time::Instant::now(),
+        }
+    }
+
+    pub fn started(&mut self) {
+        self.started = std::time::Instant::now();
+    }
+
+    // How long to wait before the next start
+    pub fn crashed(&mut self) -> Duration {
+        if self.started.elapsed() >= MAX_RESTART_DELAY {
+            self.crashes = 0;
+        }
+        self.crashes += 1;
+        Duration::from_secs(1 << (self.crashes - 1).min(6)).min(MAX_RESTART_DELAY)
+    }
+}
+
+// What a poller task that stopped on its own died of; None when it simply returned
+pub fn crash_reason(result: Result<(), tokio::task::JoinError>) -> Option<String> {
+    let err = result.err()?;
+    if !err.is_panic() {
+        return Some(err.to_string());
+    }
+    let payload = err.into_panic();
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string());
+    Some(format!("panicked: {}", message))
+}
+
+// Each streamed payment goes out as its own batch, exactly like a poll result
+async fn forward_stream(
+    provider: String,
+    mut stream: BoxStream<'static, anyhow::Result<Payment>>,
+    tx: mpsc::UnboundedSender<PollEvent>,
+) {
+    while let Some(item) = stream.next().await {
+        let event = match item {
+            Ok(payment) => PollEvent::Payments {
+                provider: provider.clone(),
+                payments: vec![payment],
+            },
+            Err(e) => {
+                tracing::warn!(provider = %provider, "stream error: {:#}", e);
+                PollEvent::Error {
+                    provider: provider.clone(),
+                    message: format!("{:#}", e),
+                }
+            }
+        };
+        if tx.send(event).is_err() {
+            return;
+        }
+    }
+}
+
+// Empty polls in a row before an adaptive interval lengthens
+const QUIET_POLLS: u32 = 3;
+
+// A provider's poll interval. Adaptive ones halve whenever payments come in and
+// double after QUIET_POLLS empty polls, staying within (floor, ceiling)
+pub struct Cadence {
+    every: Duration,
+    bounds: Option<(Duration, Duration)>,
+    quiet: u32,
+    newest: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl Cadence {
+    pub fn new(every: Duration, bounds: Option<(Duration, Duration)>) -> Self {
+        let every = match bounds {
+            Some((floor, ceiling)) => every.clamp(floor, ceiling),
+            None => every,
+        };
+        Self {
+            every,
+            bounds,
+            quiet: 0,
+            newest: None,
+        }
+    }
+
+    pub fn every(&self) -> Duration {
+        self.every
+    }
+
+    // Failed fetches say nothing about volume, so only successful ones count. The
+    // window overlap fetches payments again, so only ones newer than any seen are news
+    pub fn record(&mut self, newest: Option<chrono::DateTime<chrono::Utc>>) {
+        let Some((floor, ceiling)) = self.bounds else {
+            return;
+        };
+        let news = newest.is_some_and(|at| self.newest.is_none_or(|seen| at > seen));
+        self.newest = self.newest.max(newest);
+        if news {
+            self.quiet = 0;
+            self.every = (self.every / 2).max(floor);
+        } else {
+            self.quiet += 1;
+            if self.quiet >= QUIET_POLLS {
+                self.quiet = 0;
+                self.every = (self.every * 2).min(ceiling);
+            }
+        }
+    }
+}
+
+// What came of one provider's turn
+enum Outcome {
+    // With the newest payment's creation time, if there was any
+    Fetched(Option<chrono::DateTime<chrono::Utc>>),
+    // Failed, or skipped because the previous fetch was still running
+    NoFetch,
+    // The receiving side has gone away
+    Closed,
+}
+
+// Each provider's fetch runs on its own, so a slow one doesn't hold up the others and a
+// refresh that lands while one is still running is dropped for that provider
+async fn poll_payments(
+    poller: Poller,
+    tx: mpsc::UnboundedSender<PollEvent>,
+    mut windows: Vec<chrono::DateTime<chrono::Utc>>,
+    mut cadences: Vec<Cadence>,
+    settings: PollSettings,
+    refresh: Arc<Notify>,
+) {
+    // Every provider keeps its own schedule; the first fetch is immediate
+    let mut due = vec![tokio::time::Instant::now(); poller.providers.len()];
+    let mut running = futures_util::stream::FuturesUnordered::new();
+    loop {
+        let next = due.iter().min().copied();
+        let wake = async move {
+            match next {
+                Some(at) => tokio::time::sleep_until(at).await,
+                // Only streamed providers; nothing to poll, but stay alive for them
+                None => std::future::pending().await,
+            }
+        };
+        // A manual refresh fetches everything out of band without moving the schedule
+        let refreshed = tokio::select! {
+            _ = wake => false,
+            _ = refresh.notified() => true,
+            Some((i, outcome, since)) = running.next(), if !running.is_empty() => {
+                let i: usize = i;
+                let now = tokio::time::Instant::now();
+                match outcome {
+                    Outcome::Closed => return,
+                    Outcome::Fetched(newest) => {
+                        windows[i] = since;
+                        cadences[i].record(newest);
+                    }
+                    Outcome::NoFetch => {}
+                }
+                let provider = &poller.providers[i];
+                if let Some(wait) = provider.rate_limit().and_then(|limit| quota_wait(limit, cadences[i].every())) {
+                    tracing::debug!(provider = provider.name(), ?wait, "slowing down: quota running low");
+                    due[i] = due[i].max(now + wait);
+                }
+                continue;
+            }
+        };
+
+        let now = tokio::time::Instant::now();
+        // Quiet hours only hold back the schedule; a manual refresh still fetches
+        let quiet = !refreshed && settings.is_quiet();
+        for (i, provider) in poller.providers.iter().enumerate() {
+            let is_due = due[i] <= now;
+            if !is_due && !refreshed {
+                continue;
+            }
+            if is_due {
+                due[i] = (due[i] + cadences[i].every()).max(now);
+            }
+            if quiet {
+                tracing::debug!(provider = provider.name(), "skipped: quiet hours");
+                due[i] = now + cadences[i].every();
+                continue;
+            }
+            let (tx, since, overlap) = (&tx, windows[i], settings.overlap);
+            let poller = &poller;
+            running.push(async move {
+                let (outcome, since) = poller.poll_provider(i, tx, since, overlap).await;
+                (i, outcome, since)
+            });
+        }
+    }
+}
+
+// Longest a low quota holds a provider back
+const MAX_QUOTA_WAIT: Duration = Duration::from_secs(300);
+
+// How long to leave a provider whose quota is running low rather than poll into a 429:
+// as long as it asked for, or else twice its interval
+pub fn quota_wait(limit: RateLimit, every: Duration) -> Option<Duration> {
+    if !limit.is_low() {
+        return None;
+    }
+    Some(limit.retry_after.unwrap_or(every * 2).min(MAX_QUOTA_WAIT))
+}
+
+// The next window starts `overlap` before the newest payment seen, and never moves back
+pub fn advance_window(
+    since: chrono::DateTime<chrono::Utc>,
+    payments: &[Payment],
+    overlap: chrono::Duration,
+) -> chrono::DateTime<chrono::Utc> {
+    match payments.iter().map(|p| p.created_at).max() {
+        Some(newest) => since.max(newest - overlap),
+        None => since,
+    }
+}
+
+// The providers one poller fetches from, each with a flag that's set while its fetch runs
+pub struct Poller {
+    providers: Vec<Arc<dyn PaymentProvider>>,
+    in_flight: Vec<AtomicBool>,
+}
+
+// Held while a provider's fetch runs; dropping it lets the next one through
+struct FetchGuard<'a>(&'a AtomicBool);
+
+impl Drop for FetchGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+impl Poller {
+    pub fn new(providers: Vec<Arc<dyn PaymentProvider>>) -> Self {
+        let in_flight = providers.iter().map(|_| AtomicBool::new(false)).collect();
+        Self { providers, in_flight }
+    }
+
+    pub fn providers(&self) -> &[Arc<dyn PaymentProvider>] {
+        &self.providers
+    }
+
+    fn try_acquire(&self, i: usize) -> Option<FetchGuard<'_>> {
+        let flag = &self.in_flight[i];
+        flag.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed).ok()?;
+        Some(FetchGuard(flag))
+    }
+
+    // One pass over every provider, independent of any timer. `windows` holds
+    // each provider's `since`, advanced after every successful fetch. A provider
+    // whose previous fetch hasn't finished is skipped rather than fetched twice.
+    // Returns false once the receiving side has gone away.
+    pub async fn poll_once(
+        &self,
+        tx: &mpsc::UnboundedSender<PollEvent>,
+        windows: &mut [chrono::DateTime<chrono::Utc>],
+        overlap: chrono::Duration,
+    ) -> bool {
+        for (i, since) in windows.iter_mut().enumerate().take(self.providers.len()) {
+            let (outcome, next) = self.poll_provider(i, tx, *since, overlap).await;
+            match outcome {
+                Outcome::Closed => return false,
+                Outcome::Fetched(_) => *since = next,
+                Outcome::NoFetch => {}
+            }
+        }
+        true
+    }
+
+    // A single provider's turn, with where its next window starts
+    async fn poll_provider(
+        &self,
+        i: usize,
+        tx: &mpsc::UnboundedSender<PollEvent>,
+        since: chrono::DateTime<chrono::Utc>,
+        overlap: chrono::Duration,
+    ) -> (Outcome, chrono::DateTime<chrono::Utc>) {
+        let provider = &self.providers[i];
+        let Some(_guard) = self.try_acquire(i) else {
+            tracing::debug!(provider = provider.name(), "skipped: previous fetch still running");
+            return (Outcome::NoFetch, since);
+        };
+        let result = provider.fetch_recent_payments(since).await;
+        if let Some(limit) = provider.rate_limit() {
+            let event = PollEvent::RateLimit {
+                provider: provider.name().to_string(),
+                limit,
+            };
+            if tx.send(event).is_err() {
+                return (Outcome::Closed, since);
+            }
+        }
+        let (event, outcome, next) = match result {
+            Ok(payments) => {
+                tracing::debug!(provider = provider.name(), since = %since, count = payments.len(), "fetched");
+                let newest = payments.iter().map(|p| p.created_at).max();
+                let next = advance_window(since, &payments, overlap);
+                let event = PollEvent::Payments {
+                    provider: provider.name().to_string(),
+                    payments,
+                };
+                (event, Outcome::Fetched(newest), next)
+            }
+            Err(e) => {
+                tracing::warn!(provider = provider.name(), since = %since, "fetch failed: {:#}", e);
+                let event = PollEvent::Error {
+                    provider: provider.name().to_string(),
+                    message: format!("{:#}", e),
+                };
+                (event, Outcome::NoFetch, since)
+            }
+        };
+        if tx.send(event).is_err() {
+            return (Outcome::Closed, since);
+        }
+        (outcome, next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::psp::ScriptedProvider;
+    use crate::App;
+    use std::sync::atomic::AtomicUsize;
+    use tokio::sync::Barrier;
+
+    // Holds every fetch until the test meets it at the barrier
+    struct BlockingProvider {
+        fetches: AtomicUsize,
+        started: Notify,
+        release: Barrier,
+    }
+
+    impl BlockingProvider {
+        fn new() -> Arc<Self> {
+            Arc::new(Self {
+                fetches: AtomicUsize::new(0),
+                started: Notify::new(),
+                release: Barrier::new(2),
+            })
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl PaymentProvider for BlockingProvider {
+        fn name(&self) -> &str {
+            "Slow"
+        }
+
+        async fn fetch_recent_payments(&self, _since: chrono::DateTime<chrono::Utc>) -> anyhow::Result<Vec<Payment>> {
+            self.fetches.fetch_add(1, Ordering::SeqCst);
+            self.started.notify_one();
+            self.release.wait().await;
+            Ok(Vec::new())
+        }
+    }
+
+    fn fetched_batches(rx: &mut mpsc::UnboundedReceiver<PollEvent>) -> usize {
+        std::iter::from_fn(|| rx.try_recv().ok()).filter(|e| matches!(e, PollEvent::Payments { .. })).count()
+    }
+
+    #[tokio::test]
+    async fn overlapping_pass_is_dropped_not_doubled() {
+        let provider = BlockingProvider::new();
+        let poller = Poller::new(vec![provider.clone()]);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut first_windows = vec![chrono::Utc::now()];
+        let mut second_windows = first_windows.clone();
+
+        let first = poller.poll_once(&tx, &mut first_windows, chrono::Duration::zero());
+        let second = async {
+            provider.started.notified().await;
+            let open = poller.poll_once(&tx, &mut second_windows, chrono::Duration::zero()).await;
+            provider.release.wait().await;
+            open
+        };
+        assert_eq!(tokio::join!(first, second), (true, true));
+        assert_eq!(provider.fetches.load(Ordering::SeqCst), 1);
+        assert_eq!(fetched_batches(&mut rx), 1);
+    }
+
+    #[tokio::test]
+    async fn refresh_during_a_fetch_is_dropped() {
+        let provider = BlockingProvider::new();
+        let refresh = Arc::new(Notify::new());
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let settings = PollSettings {
+            adaptive: None,
+            overlap: chrono::Duration::zero(),
+            quiet_hours: None,
+            tz: DisplayTz::Local,
+        };
+        let poller = spawn_poller(
+            vec![provider.clone()],
+            vec![chrono::Utc::now()],
+            vec![Duration::from_secs(3600)],
+            settings,
+            tx,
+            refresh.clone(),
+        );
+        provider.started.notified().await;
+        refresh.notify_one();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        provider.release.wait().await;
+        assert!(matches!(rx.recv().await, Some(PollEvent::Payments { .. })));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(provider.fetches.load(Ordering::SeqCst), 1);
+        poller.abort();
+    }
+
+    fn drain(rx: &mut mpsc::UnboundedReceiver<PollEvent>) -> Vec<PollEvent> {
+        std::iter::from_fn(|| rx.try_recv().ok()).collect()
+    }
+
+    #[tokio::test]
+    async fn polled_payments_are_counted_once() {
+        let start = chrono::Utc::now() - chrono::Duration::minutes(10);
+        let at = |minutes| start + chrono::Duration::minutes(minutes);
+        let payment = |id, cents, minutes| ScriptedProvider::payment("Scripted", id, cents, at(minutes));
+        let first = vec![payment("a", 500, 1), payment("b", 250, 2)];
+        // The window overlap hands "b" back on the next fetch
+        let second = vec![payment("b", 250, 2), payment("c", 100, 3)];
+        let provider = Arc::new(ScriptedProvider::new("Scripted", vec![Ok(first), Ok(second)]));
+        let poller = Poller::new(vec![provider.clone()]);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut windows = vec![start];
+        let overlap = chrono::Duration::minutes(1);
+
+        crate::config::use_temp_config_dir();
+        let mut app = App::new();
+        for _ in 0..2 {
+            assert!(poller.poll_once(&tx, &mut windows, overlap).await);
+            for event in drain(&mut rx) {
+                let PollEvent::Payments { payments, .. } = event else { panic!("unexpected {:?}", event) };
+                for payment in payments {
+                    app.add_payment(payment);
+                }
+            }
+        }
+        assert_eq!(app.total_cents, 850);
+        assert_eq!(app.payment_count, 3);
+        // Each window starts `overlap` before the newest payment, and never moves back
+        assert_eq!(*provider.fetched_since.lock().unwrap(), vec![start, at(1)]);
+        assert_eq!(windows, vec![at(2)]);
+    }
+
+    #[tokio::test]
+    async fn fetch_errors_are_surfaced_and_keep_the_window() {
+        let start = chrono::Utc::now() - chrono::Duration::minutes(10);
+        let later = vec![ScriptedProvider::payment("Scripted", "a", 500, start + chrono::Duration::minutes(1))];
+        let script = vec![Err("503 Service Unavailable".to_string()), Ok(later)];
+        let provider = Arc::new(ScriptedProvider::new("Scripted", script));
+        let poller = Poller::new(vec![provider.clone()]);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut windows = vec![start];
+
+        assert!(poller.poll_once(&tx, &mut windows, chrono::Duration::zero()).await);
+        let events = drain(&mut rx);
+        let [PollEvent::Error { provider: name, message }] = &events[..] else { panic!("expected an error, got {:?}", events) };
+        assert_eq!((name.as_str(), message.as_str()), ("Scripted", "503 Service Unavailable"));
+        assert_eq!(windows, vec![start]);
+
+        assert!(poller.poll_once(&tx, &mut windows, chrono::Duration::zero()).await);
+        assert!(matches!(&drain(&mut rx)[..], [PollEvent::Payments { payments, .. }] if payments.len() == 1));
+        assert_eq!(provider.fetched_since.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn a_closed_receiver_stops_the_pass() {
+        let provider = Arc::new(ScriptedProvider::new("Scripted", vec![Ok(Vec::new())]));
+        let poller = Poller::new(vec![provider]);
+        let (tx, rx) = mpsc::unbounded_channel();
+        drop(rx);
+        assert!(!poller.poll_once(&tx, &mut [chrono::Utc::now()], chrono::Duration::zero()).await);
+    }
+
+    #[test]
+    fn window_advances_past_a_poll_with_an_overlap() {
+        let since = chrono::Utc::now() - chrono::Duration::hours(1);
+        let at = |minutes| since + chrono::Duration::minutes(minutes);
+        let overlap = chrono::Duration::seconds(30);
+        let batch = [ScriptedProvider::payment("Adyen", "a", 100, at(20)), ScriptedProvider::payment("Adyen", "b", 100, at(5))];
+
+        let next = advance_window(since, &batch, overlap);
+        assert_eq!(next, at(20) - overlap);
+        // The next window still covers the newest payment it already has
+        assert!(next > since && next < at(20));
+        // An empty poll, or only payments from inside the overlap, leave it where it is
+        assert_eq!(advance_window(next, &[], overlap), next);
+        assert_eq!(advance_window(next, &batch[1..], overlap), next);
+    }
+
+    // Pushes its payments as a stream; being polled is a bug
+    struct StreamingProvider;
+
+    #[async_trait::async_trait]
+    impl PaymentProvider for StreamingProvider {
+        fn name(&self) -> &str {
+            "Stream"
+        }
+
+        async fn fetch_recent_payments(&self, _since: chrono::DateTime<chrono::Utc>) -> anyhow::Result<Vec<Payment>> {
+            panic!("a streaming provider shouldn't be polled");
+        }
+
+        fn payment_stream(&self) -> Option<BoxStream<'static, anyhow::Result<Payment>>> {
+            let now = chrono::Utc::now();
+            let items = vec![
+                Ok(ScriptedProvider::payment("Stream", "a", 700, now)),
+                Err(anyhow::anyhow!("connection reset")),
+                Ok(ScriptedProvider::payment("Stream", "b", 300, now)),
+            ];
+            Some(futures_util::stream::iter(items).boxed())
+        }
+    }
+
+    #[tokio::test]
+    async fn streamed_payments_reach_the_app() {
+        let settings = PollSettings { adaptive: None, overlap: chrono::Duration::zero(), quiet_hours: None, tz: DisplayTz::Local };
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let handle = spawn_poller(
+            vec![Arc::new(StreamingProvider)],
+            vec![chrono::Utc::now()],
+            vec![Duration::from_secs(1)],
+            settings,
+            tx,
+            Arc::new(Notify::new()),
+        );
+
+        crate::config::use_temp_config_dir();
+        let mut app = App::new();
+        let mut errors = Vec::new();
+        for _ in 0..3 {
+            match tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap() {
+                PollEvent::Payments { provider, payments } => {
+                    assert_eq!((provider.as_str(), payments.len()), ("Stream", 1));
+                    payments.into_iter().for_each(|p| assert!(app.add_payment(p)));
+                }
+                PollEvent::Error { message, .. } => errors.push(message),
+                event => panic!("unexpected {:?}", event),
+            }
+        }
+        assert_eq!(app.total_cents, 1_000);
+        assert_eq!(errors, ["connection reset"]);
+        handle.abort();
+    }
+
+    // Collects formatted log output for assertions
+    #[derive(Clone, Default)]
+    struct CapturedLog(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLog {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn fetch_errors_are_logged_with_provider_and_window() {
+        let log = CapturedLog::default();
+        let writer = log.clone();
+        let subscriber = tracing_subscriber::fmt().with_writer(move || writer.clone()).with_ansi(false).finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let provider = Arc::new(ScriptedProvider::new("Adyen (EU)", vec![Err("401 Unauthorized".to_string())]));
+        let poller = Poller::new(vec![provider]);
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let since = chrono::Utc::now() - chrono::Duration::minutes(5);
+        assert!(poller.poll_once(&tx, &mut [since], chrono::Duration::zero()).await);
+
+        let output = String::from_utf8(log.0.lock().unwrap().clone()).unwrap();
+        let line = output.lines().find(|l| l.contains("fetch failed")).unwrap_or_else(|| panic!("no error logged in {:?}", output));
+        assert!(line.contains("WARN"), "{}", line);
+        assert!(line.contains("provider=\"Adyen (EU)\""), "{}", line);
+        assert!(line.contains(&format!("since={}", since)), "{}", line);
+        assert!(line.contains("401 Unauthorized"), "{}", line);
+    }
+
+    // Counts fetches and never has anything to report
+    struct CountingProvider {
+        name: &'static str,
+        fetches: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl PaymentProvider for CountingProvider {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn fetch_recent_payments(&self, _since: chrono::DateTime<chrono::Utc>) -> anyhow::Result<Vec<Payment>> {
+            self.fetches.fetch_add(1, Ordering::SeqCst);
+            Ok(Vec::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn each_provider_is_polled_on_its_own_interval() {
+        let fast = Arc::new(CountingProvider { name: "Mock", fetches: AtomicUsize::new(0) });
+        let slow = Arc::new(CountingProvider { name: "Adyen", fetches: AtomicUsize::new(0) });
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let settings = PollSettings {
+            adaptive: None,
+            overlap: chrono::Duration::zero(),
+            quiet_hours: None,
+            tz: DisplayTz::Local,
+        };
+        let now = chrono::Utc::now();
+        let poller = spawn_poller(
+            vec![fast.clone(), slow.clone()],
+            vec![now, now],
+            vec![Duration::from_millis(100), Duration::from_millis(500)],
+            settings,
+            tx,
+            Arc::new(Notify::new()),
+        );
+        tokio::time::sleep(Duration::from_millis(1_250)).await;
+        poller.abort();
+
+        // Both fetch straight away, then every 100ms and every 500ms
+        let (fast, slow) = (fast.fetches.load(Ordering::SeqCst), slow.fetches.load(Ordering::SeqCst));
+        assert!((9..=14).contains(&fast), "fast provider fetched {} times", fast);
+        assert_eq!(slow, 3);
+    }
+
+    #[tokio::test]
+    async fn adaptive_cadence_contracts_in_a_burst_and_expands_when_quiet() {
+        let at = |secs: i64| chrono::DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap();
+        let paid = |id: &str, secs: i64| Ok(vec![ScriptedProvider::payment("Mock", id, 500, at(secs))]);
+        // A burst, a failed fetch, the overlap fetching the last payment again, then nothing
+        let provider = ScriptedProvider::new(
+            "Mock",
+            vec![paid("a", 1), paid("b", 2), paid("c", 3), Err("timed out".to_string()), paid("c", 3)],
+        );
+        let mut cadence = Cadence::new(Duration::from_secs(20), Some((Duration::from_secs(5), Duration::from_secs(60))));
+        let mut intervals = Vec::new();
+        for _ in 0..19 {
+            if let Ok(payments) = provider.fetch_recent_payments(at(0)).await {
+                cadence.record(payments.iter().map(|p| p.created_at).max());
+            }
+            intervals.push(cadence.every().as_secs());
+        }
+        assert_eq!(intervals, [10, 5, 5, 5, 5, 5, 10, 10, 10, 20, 20, 20, 40, 40, 40, 60, 60, 60, 60]);
+
+        // Fixed mode ignores volume, and a start outside the bounds is pulled inside them
+        let mut fixed = Cadence::new(Duration::from_secs(20), None);
+        fixed.record(Some(at(10)));
+        assert_eq!(fixed.every(), Duration::from_secs(20));
+        let clamped = Cadence::new(Duration::from_secs(2), Some((Duration::from_secs(5), Duration::from_secs(60))));
+        assert_eq!(clamped.every(), Duration::from_secs(5));
+    }
+
+    // Dies on its first fetch, like a provider with a stray unwrap
+    struct PanickingProvider;
+
+    #[async_trait::async_trait]
+    impl PaymentProvider for PanickingProvider {
+        fn name(&self) -> &str {
+            "Broken"
+        }
+
+        async fn fetch_recent_payments(&self, _since: chrono::DateTime<chrono::Utc>) -> anyhow::Result<Vec<Payment>> {
+            panic!("unwrap on a None");
+        }
+    }
+
+    #[tokio::test]
+    async fn a_panicking_provider_is_reported_and_restarts_back_off() {
+        let settings = PollSettings { adaptive: None, overlap: chrono::Duration::zero(), quiet_hours: None, tz: DisplayTz::Local };
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let handle = spawn_poller(
+            vec![Arc::new(PanickingProvider)],
+            vec![chrono::Utc::now()],
+            vec![Duration::from_secs(1)],
+            settings,
+            tx,
+            Arc::new(Notify::new()),
+        );
+        let joined = tokio::time::timeout(Duration::from_secs(5), handle).await.expect("the poller kept running");
+        assert_eq!(crash_reason(joined).as_deref(), Some("panicked: unwrap on a None"));
+
+        // A poller that returned, or was stopped on purpose, isn't a crash to report as a panic
+        assert_eq!(crash_reason(tokio::spawn(async {}).await), None);
+        let aborted = tokio::spawn(std::future::pending::<()>());
+        aborted.abort();
+        assert!(crash_reason(aborted.await).is_some_and(|r| !r.starts_with("panicked")));
+
+        // Each crash in a row waits twice as long, up to the cap
+        let mut backoff = RestartBackoff::new();
+        let delays: Vec<u64> = (0..8).map(|_| backoff.crashed().as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 8, 16, 32, 60, 60]);
+    }
+
+    #[tokio::test]
+    async fn a_refresh_fetches_once_more_without_waiting_for_the_interval() {
+        let provider = Arc::new(CountingProvider { name: "Mock", fetches: AtomicUsize::new(0) });
+        let refresh = Arc::new(Notify::new());
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let settings = PollSettings {
+            adaptive: None,
+            overlap: chrono::Duration::zero(),
+            quiet_hours: None,
+            tz: DisplayTz::Local,
+        };
+        let poller = spawn_poller(
+            vec![provider.clone()],
+            vec![chrono::Utc::now()],
+            vec![Duration::from_secs(3600)],
+            settings,
+            tx,
+            refresh.clone(),
+        );
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(provider.fetches.load(Ordering::SeqCst), 1);
+
+        refresh.notify_one();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(provider.fetches.load(Ordering::SeqCst), 2);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(provider.fetches.load(Ordering::SeqCst), 2);
+        poller.abort();
+    }
+}
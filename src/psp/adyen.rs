@@ -1,4 +1,4 @@
-use super::{Payment, PaymentProvider};
+use super::{api_error, hundredths_from_minor, send_with_retry, Payment, PaymentProvider, RateLimit, RateLimitCell};
 use anyhow::Result;
 use async_trait::async_trait;
 use serde::Deserialize;
@@ -6,9 +6,15 @@ use serde::Deserialize;
 pub struct AdyenProvider {
     api_key: String,
     merchant_account: String,
+    statuses: Vec<String>,
     client: reqwest::Client,
+    limits: RateLimitCell,
 }
 
+// Authorised payments can still fail to capture; stricter setups count
+// SettleScheduled/Settled instead
+pub const DEFAULT_STATUSES: &[&str] = &["Authorised"];
+
 #[derive(Deserialize)]
 struct AdyenPaymentList {
     #[serde(default)]
@@ -31,12 +37,24 @@ struct AdyenAmount {
 }
 
 impl AdyenProvider {
-    pub fn new(api_key: String, merchant_account: String) -> Self {
-        Self {
+    // Both the key and the merchant account are needed for every request
+    pub fn try_new(api_key: String, merchant_account: String, statuses: Option<Vec<String>>, client: reqwest::Client) -> Result<Self> {
+        if api_key.trim().is_empty() {
+            anyhow::bail!("Adyen API key is empty");
+        }
+        if merchant_account.trim().is_empty() {
+            anyhow::bail!("Adyen merchant account is empty; set merchant_account in the provider entry");
+        }
+        let statuses = statuses
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| DEFAULT_STATUSES.iter().map(|s| s.to_string()).collect());
+        Ok(Self {
             api_key,
             merchant_account,
-            client: reqwest::Client::new(),
-        }
+            statuses,
+            client,
+            limits: RateLimitCell::default(),
+        })
     }
 }
 
@@ -46,49 +64,157 @@ impl PaymentProvider for AdyenProvider {
         "Adyen"
     }
 
+    fn rate_limit(&self) -> Option<RateLimit> {
+        self.limits.get()
+    }
+
     async fn fetch_recent_payments(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<Payment>> {
         let body = serde_json::json!({
             "merchantAccountCode": self.merchant_account,
             "createdSince": since.to_rfc3339(),
             "createdUntil": chrono::Utc::now().to_rfc3339(),
-            "status": "Authorised",
+            "status": self.statuses,
             "limit": 100,
         });
 
-        let resp = self.client
-            .post("https://management-test.adyen.com/v3/payments")
-            .header("X-API-Key", &self.api_key)
-            .header("Content-Type", "application/json")
-            .json(&body)
-            .send()
-            .await?;
+        let resp = send_with_retry(
+            self.client
+                .post("https://management-test.adyen.com/v3/payments")
+                .header("X-API-Key", &self.api_key)
+                .header("Content-Type", "application/json")
+                .json(&body),
+        )
+        .await?;
+        self.limits.record(&resp);
 
         if !resp.status().is_success() {
-            anyhow::bail!("Adyen API error: {}", resp.status());
+            return Err(api_error("Adyen API error", resp).await);
         }
 
         let list: AdyenPaymentList = resp.json().await?;
-        let mut payments = Vec::new();
-
-        for ap in list.data {
-            let created = chrono::DateTime::parse_from_rfc3339(&ap.creation_date)
-                .unwrap_or_else(|_| chrono::Utc::now().into())
-                .with_timezone(&chrono::Utc);
-
-            if created < since {
-                continue;
-            }
-
-            payments.push(Payment {
-                id: ap.psp_reference,
-                amount_cents: ap.amount.value,
-                currency: ap.amount.currency,
-                status: ap.status,
-                created_at: created,
-                provider: "Adyen".to_string(),
-            });
+        Ok(accepted_payments(list.data, &self.statuses, since))
+    }
+}
+
+// Payments from `since` on whose status is one of `statuses`
+fn accepted_payments(data: Vec<AdyenPayment>, statuses: &[String], since: chrono::DateTime<chrono::Utc>) -> Vec<Payment> {
+    let mut payments = Vec::new();
+    for ap in data {
+        // Skipped rather than dated "now", which would slip it past the since filter
+        let Ok(created) = chrono::DateTime::parse_from_rfc3339(&ap.creation_date) else {
+            tracing::warn!("Adyen payment {} has an unreadable creationDate {:?}, skipped", ap.psp_reference, ap.creation_date);
+            continue;
+        };
+        let created = created.with_timezone(&chrono::Utc);
+
+        if created < since || !statuses.contains(&ap.status) {
+            continue;
         }
 
-        Ok(payments)
+        payments.push(Payment {
+            id: ap.psp_reference,
+            amount_cents: hundredths_from_minor(ap.amount.value, &ap.amount.currency),
+            currency: ap.amount.currency,
+            settled: matches!(ap.status.as_str(), "SentForSettle" | "Settled" | "SettledInInstallments"),
+            status: ap.status,
+            created_at: created,
+            provider: "Adyen".to_string(),
+            converted_cents: None,
+        });
+    }
+    payments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(data: serde_json::Value) -> Vec<AdyenPayment> {
+        serde_json::from_value::<AdyenPaymentList>(serde_json::json!({ "data": data })).unwrap().data
+    }
+
+    fn adyen_payment(reference: &str, status: &str, creation_date: &str) -> serde_json::Value {
+        serde_json::json!({
+            "pspReference": reference,
+            "amount": { "value": 1250, "currency": "EUR" },
+            "status": status,
+            "creationDate": creation_date
+        })
+    }
+
+    fn since() -> chrono::DateTime<chrono::Utc> {
+        "2026-10-01T00:00:00Z".parse().unwrap()
+    }
+
+    #[test]
+    fn only_accepted_statuses_are_mapped() {
+        let data = serde_json::json!([
+            adyen_payment("AUTH", "Authorised", "2026-10-01T10:00:00+02:00"),
+            adyen_payment("SETTLED", "Settled", "2026-10-01T10:01:00Z"),
+            adyen_payment("REFUSED", "Refused", "2026-10-01T10:02:00Z")
+        ]);
+        let references = |statuses: &[&str]| -> Vec<String> {
+            let statuses: Vec<String> = statuses.iter().map(|s| s.to_string()).collect();
+            accepted_payments(list(data.clone()), &statuses, since()).into_iter().map(|p| p.id).collect()
+        };
+        assert_eq!(references(DEFAULT_STATUSES), ["AUTH"]);
+        assert_eq!(references(&["SettleScheduled", "Settled"]), ["SETTLED"]);
+
+        let statuses = vec!["Authorised".to_string(), "Settled".to_string()];
+        let payments = accepted_payments(list(data), &statuses, since());
+        assert_eq!(payments.len(), 2);
+        assert!(!payments[0].settled && payments[1].settled);
+        assert_eq!((payments[0].amount_cents, payments[0].created_at), (1250, "2026-10-01T08:00:00Z".parse().unwrap()));
+    }
+
+    #[test]
+    fn payments_with_unreadable_dates_are_skipped_and_the_rest_kept() {
+        let data = serde_json::json!([
+            adyen_payment("BEFORE", "Authorised", "2026-10-01T10:00:00Z"),
+            adyen_payment("GARBLED", "Authorised", "01/10/2026 10:00"),
+            adyen_payment("EMPTY", "Authorised", ""),
+            adyen_payment("AFTER", "Authorised", "2026-10-01T10:05:00Z")
+        ]);
+        let statuses = vec!["Authorised".to_string()];
+        let ids: Vec<String> = accepted_payments(list(data), &statuses, since()).into_iter().map(|p| p.id).collect();
+        assert_eq!(ids, ["BEFORE", "AFTER"]);
+    }
+
+    #[test]
+    fn minor_unit_amounts_map_to_hundredths_per_currency() {
+        let paid = |reference: &str, value: i64, currency: &str| {
+            serde_json::json!({
+                "pspReference": reference,
+                "amount": { "value": value, "currency": currency },
+                "status": "Authorised",
+                "creationDate": "2026-10-01T10:00:00Z"
+            })
+        };
+        let data = serde_json::json!([paid("JPY", 5000, "JPY"), paid("EUR", 5000, "EUR"), paid("KWD", 1235, "KWD")]);
+        let statuses = vec!["Authorised".to_string()];
+        let amounts: Vec<(String, i64)> =
+            accepted_payments(list(data), &statuses, since()).into_iter().map(|p| (p.id, p.amount_cents)).collect();
+        // ¥5000, €50.00 and 1.235 KWD, rounded to 1.24
+        assert_eq!(amounts, [("JPY".to_string(), 500_000), ("EUR".to_string(), 5_000), ("KWD".to_string(), 124)]);
+    }
+
+    #[test]
+    fn construction_names_the_missing_credential() {
+        let build = |key: &str, merchant: &str| {
+            AdyenProvider::try_new(key.to_string(), merchant.to_string(), None, reqwest::Client::new())
+        };
+        assert_eq!(build("", "AcmeECOM").err().unwrap().to_string(), "Adyen API key is empty");
+        assert_eq!(build("  ", "AcmeECOM").err().unwrap().to_string(), "Adyen API key is empty");
+        assert_eq!(
+            build("AQEkey", " ").err().unwrap().to_string(),
+            "Adyen merchant account is empty; set merchant_account in the provider entry"
+        );
+
+        let adyen = build("AQEkey", "AcmeECOM").unwrap();
+        assert_eq!((adyen.api_key.as_str(), adyen.merchant_account.as_str()), ("AQEkey", "AcmeECOM"));
+        assert_eq!(adyen.statuses, DEFAULT_STATUSES);
+        // An empty status list means the defaults too
+        let adyen = AdyenProvider::try_new("k".to_string(), "m".to_string(), Some(Vec::new()), reqwest::Client::new()).unwrap();
+        assert_eq!(adyen.statuses, DEFAULT_STATUSES);
     }
 }
@@ -0,0 +1,226 @@
+use super::{api_error, parse_decimal_cents, send_with_retry, Payment, PaymentProvider, RateLimit, RateLimitCell};
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+
+const GRAPHQL_URL: &str = "https://payments.braintree-api.com/graphql";
+// Braintree pins the schema by date
+const API_VERSION: &str = "2019-01-01";
+
+// A session never gets near this many pages of 50
+const MAX_PAGES: u32 = 10;
+
+const SEARCH_QUERY: &str = "query Search($input: TransactionSearchInput!, $after: String) {
+  search {
+    transactions(input: $input, first: 50, after: $after) {
+      pageInfo { hasNextPage endCursor }
+      edges { node { id amount { value currencyCode } status createdAt } }
+    }
+  }
+}";
+
+pub struct BraintreeProvider {
+    public_key: String,
+    private_key: String,
+    client: reqwest::Client,
+    limits: RateLimitCell,
+}
+
+#[derive(Deserialize)]
+struct GraphQlResponse {
+    data: Option<SearchData>,
+    #[serde(default)]
+    errors: Vec<GraphQlError>,
+}
+
+#[derive(Deserialize)]
+struct GraphQlError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct SearchData {
+    search: Search,
+}
+
+#[derive(Deserialize)]
+struct Search {
+    transactions: TransactionConnection,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TransactionConnection {
+    page_info: PageInfo,
+    #[serde(default)]
+    edges: Vec<Edge>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PageInfo {
+    has_next_page: bool,
+    end_cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Edge {
+    node: Transaction,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Transaction {
+    id: String,
+    amount: BraintreeAmount,
+    status: String,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BraintreeAmount {
+    value: String,
+    currency_code: String,
+}
+
+impl BraintreeProvider {
+    pub fn new(public_key: String, private_key: String, client: reqwest::Client) -> Self {
+        Self {
+            public_key,
+            private_key,
+            client,
+            limits: RateLimitCell::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for BraintreeProvider {
+    fn name(&self) -> &str {
+        "Braintree"
+    }
+
+    fn rate_limit(&self) -> Option<RateLimit> {
+        self.limits.get()
+    }
+
+    async fn fetch_recent_payments(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<Payment>> {
+        let mut payments = Vec::new();
+        let mut after: Option<String> = None;
+
+        for _ in 0..MAX_PAGES {
+            let body = json!({
+                "query": SEARCH_QUERY,
+                "variables": {
+                    "input": {
+                        "createdAt": { "greaterThanOrEqualTo": since.to_rfc3339() },
+                        "status": { "in": ["SETTLED", "SETTLING"] },
+                    },
+                    "after": after,
+                },
+            });
+            let resp = send_with_retry(
+                self.client
+                    .post(GRAPHQL_URL)
+                    .basic_auth(&self.public_key, Some(&self.private_key))
+                    .header("Braintree-Version", API_VERSION)
+                    .json(&body),
+            )
+            .await?;
+            self.limits.record(&resp);
+
+            if !resp.status().is_success() {
+                return Err(api_error("Braintree API error", resp).await);
+            }
+
+            let (page, next) = settled_payments(resp.json().await?)?;
+            payments.extend(page);
+            match next {
+                Some(cursor) => after = Some(cursor),
+                None => break,
+            }
+        }
+
+        Ok(payments)
+    }
+}
+
+// One page's settled and settling transactions, and the cursor of the next page if there is one
+fn settled_payments(result: GraphQlResponse) -> Result<(Vec<Payment>, Option<String>)> {
+    // GraphQL reports failures with a 200 and an errors list
+    let Some(data) = result.data else {
+        let messages: Vec<_> = result.errors.into_iter().map(|e| e.message).collect();
+        anyhow::bail!("Braintree API error: {}", messages.join("; "));
+    };
+    let connection = data.search.transactions;
+    let mut payments = Vec::new();
+    for edge in connection.edges {
+        let tx = edge.node;
+        if !matches!(tx.status.as_str(), "SETTLED" | "SETTLING") {
+            continue;
+        }
+        let Some(amount_cents) = parse_decimal_cents(&tx.amount.value).filter(|c| *c > 0) else {
+            continue;
+        };
+        payments.push(Payment {
+            id: tx.id,
+            amount_cents,
+            currency: tx.amount.currency_code,
+            settled: tx.status == "SETTLED",
+            status: tx.status.to_lowercase(),
+            created_at: tx.created_at,
+            provider: "Braintree".to_string(),
+            converted_cents: None,
+        });
+    }
+    Ok((payments, connection.page_info.end_cursor.filter(|_| connection.page_info.has_next_page)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction(id: &str, value: &str, status: &str) -> serde_json::Value {
+        json!({ "node": { "id": id, "amount": { "value": value, "currencyCode": "USD" }, "status": status, "createdAt": "2026-10-01T12:00:00Z" } })
+    }
+
+    #[test]
+    fn search_response_maps_settled_transactions() {
+        let response: GraphQlResponse = serde_json::from_value(json!({
+            "data": { "search": { "transactions": {
+                "pageInfo": { "hasNextPage": true, "endCursor": "Y3Vyc29y" },
+                "edges": [
+                    transaction("dHJhbnNhY3Rpb25fMQ", "12.50", "SETTLED"),
+                    transaction("dHJhbnNhY3Rpb25fMg", "8.00", "SETTLING"),
+                    transaction("dHJhbnNhY3Rpb25fMw", "99.00", "VOIDED")
+                ]
+            } } },
+            "extensions": { "requestId": "abc" }
+        }))
+        .unwrap();
+        let (payments, next) = settled_payments(response).unwrap();
+        assert_eq!(next.as_deref(), Some("Y3Vyc29y"));
+        assert_eq!(payments.len(), 2);
+        let p = &payments[0];
+        assert_eq!((p.id.as_str(), p.amount_cents, p.currency.as_str(), p.status.as_str()), ("dHJhbnNhY3Rpb25fMQ", 1_250, "USD", "settled"));
+        assert_eq!(p.created_at, "2026-10-01T12:00:00Z".parse::<chrono::DateTime<chrono::Utc>>().unwrap());
+        assert!(p.settled && !payments[1].settled);
+    }
+
+    #[test]
+    fn graphql_errors_fail_the_fetch_and_the_last_page_stops() {
+        let failed: GraphQlResponse =
+            serde_json::from_value(json!({ "data": null, "errors": [{ "message": "Unknown field" }, { "message": "Bad input" }] })).unwrap();
+        let error = settled_payments(failed).unwrap_err();
+        assert_eq!(error.to_string(), "Braintree API error: Unknown field; Bad input");
+
+        let last: GraphQlResponse = serde_json::from_value(json!({
+            "data": { "search": { "transactions": { "pageInfo": { "hasNextPage": false, "endCursor": "Y3Vyc29y" }, "edges": [] } } }
+        }))
+        .unwrap();
+        assert_eq!(settled_payments(last).unwrap().1, None);
+        assert!(crate::psp::registry::registry().setup_names().contains(&"Braintree"));
+    }
+}
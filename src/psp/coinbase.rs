@@ -0,0 +1,216 @@
+use super::{api_error, parse_decimal_cents, send_with_retry, Payment, PaymentProvider, RateLimit, RateLimitCell};
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Deserialize;
+
+const BASE_URL: &str = "https://api.commerce.coinbase.com";
+const API_VERSION: &str = "2018-03-22";
+
+// Charges come newest first; don't page further back than this per poll
+const MAX_PAGES: usize = 10;
+
+pub struct CoinbaseCommerceProvider {
+    api_key: String,
+    client: reqwest::Client,
+    limits: RateLimitCell,
+}
+
+#[derive(Deserialize)]
+struct ChargeList {
+    #[serde(default)]
+    data: Vec<Charge>,
+    #[serde(default)]
+    pagination: Option<Pagination>,
+}
+
+#[derive(Deserialize)]
+struct Pagination {
+    next_uri: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Charge {
+    code: String,
+    created_at: String,
+    pricing: Pricing,
+    #[serde(default)]
+    timeline: Vec<TimelineEvent>,
+}
+
+// Amounts are in the merchant's local fiat currency, not the crypto paid
+#[derive(Deserialize)]
+struct Pricing {
+    local: LocalPrice,
+}
+
+#[derive(Deserialize)]
+struct LocalPrice {
+    amount: String,
+    currency: String,
+}
+
+#[derive(Deserialize)]
+struct TimelineEvent {
+    status: String,
+    time: String,
+}
+
+impl CoinbaseCommerceProvider {
+    pub fn new(api_key: String, client: reqwest::Client) -> Self {
+        Self {
+            api_key,
+            client,
+            limits: RateLimitCell::default(),
+        }
+    }
+}
+
+fn parse_time(value: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|d| d.with_timezone(&chrono::Utc))
+}
+
+#[async_trait]
+impl PaymentProvider for CoinbaseCommerceProvider {
+    fn name(&self) -> &str {
+        "Coinbase"
+    }
+
+    fn rate_limit(&self) -> Option<RateLimit> {
+        self.limits.get()
+    }
+
+    async fn fetch_recent_payments(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<Payment>> {
+        let mut payments = Vec::new();
+        let mut url = format!("{}/charges?limit=100&order=desc", BASE_URL);
+
+        for _ in 0..MAX_PAGES {
+            let resp = send_with_retry(
+                self.client
+                    .get(&url)
+                    .header("X-CC-Api-Key", &self.api_key)
+                    .header("X-CC-Version", API_VERSION),
+            )
+            .await?;
+            self.limits.record(&resp);
+
+            if !resp.status().is_success() {
+                return Err(api_error("Coinbase Commerce API error", resp).await);
+            }
+
+            let list: ChargeList = resp.json().await?;
+            let (confirmed, reached_since) = confirmed_payments(list.data, since);
+            payments.extend(confirmed);
+
+            match list.pagination.and_then(|p| p.next_uri) {
+                Some(next) if !reached_since => url = next,
+                _ => break,
+            }
+        }
+
+        Ok(payments)
+    }
+}
+
+// Charges confirmed from `since` on, and whether the page reached charges created before it
+fn confirmed_payments(charges: Vec<Charge>, since: chrono::DateTime<chrono::Utc>) -> (Vec<Payment>, bool) {
+    let mut payments = Vec::new();
+    let mut reached_since = false;
+    for charge in charges {
+        if parse_time(&charge.created_at).is_some_and(|t| t < since) {
+            reached_since = true;
+        }
+        let Some(confirmed) = charge.timeline.iter().find(|e| e.status == "CONFIRMED") else {
+            continue;
+        };
+        let Some(confirmed_at) = parse_time(&confirmed.time) else {
+            tracing::warn!("Coinbase charge {} has an unreadable confirmation time {:?}, skipped", charge.code, confirmed.time);
+            continue;
+        };
+        if confirmed_at < since {
+            continue;
+        }
+        let Some(amount_cents) = parse_decimal_cents(&charge.pricing.local.amount) else {
+            tracing::warn!("Coinbase charge {} has an unreadable amount {:?}, skipped", charge.code, charge.pricing.local.amount);
+            continue;
+        };
+
+        payments.push(Payment {
+            id: charge.code,
+            amount_cents,
+            currency: charge.pricing.local.currency,
+            status: "confirmed".to_string(),
+            created_at: confirmed_at,
+            provider: "Coinbase".to_string(),
+            converted_cents: None,
+            settled: true,
+        });
+    }
+    (payments, reached_since)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn charge(code: &str, amount: &str, created_at: &str, timeline: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({
+            "code": code,
+            "created_at": created_at,
+            "pricing": {
+                "local": { "amount": amount, "currency": "USD" },
+                "bitcoin": { "amount": "0.00012345", "currency": "BTC" }
+            },
+            "timeline": timeline
+        })
+    }
+
+    #[test]
+    fn confirmed_charges_are_mapped() {
+        let list: ChargeList = serde_json::from_value(serde_json::json!({
+            "data": [
+                charge("66BEOV2A", "100.005", "2026-10-01T10:00:00Z", serde_json::json!([
+                    { "status": "NEW", "time": "2026-10-01T10:00:00Z" },
+                    { "status": "CONFIRMED", "time": "2026-10-01T10:05:00Z" }
+                ])),
+                charge("UNPAID", "20.00", "2026-10-01T10:01:00Z", serde_json::json!([
+                    { "status": "NEW", "time": "2026-10-01T10:01:00Z" }
+                ])),
+                charge("OLDER", "5.00", "2026-09-30T08:00:00Z", serde_json::json!([
+                    { "status": "CONFIRMED", "time": "2026-09-30T08:01:00Z" }
+                ]))
+            ],
+            "pagination": { "next_uri": "https://api.commerce.coinbase.com/charges?starting_after=OLDER" }
+        }))
+        .unwrap();
+        let since = parse_time("2026-10-01T00:00:00Z").unwrap();
+        let (payments, reached_since) = confirmed_payments(list.data, since);
+        assert!(reached_since);
+        assert_eq!(payments.len(), 1);
+        let p = &payments[0];
+        assert_eq!((p.id.as_str(), p.amount_cents, p.currency.as_str()), ("66BEOV2A", 10_001, "USD"));
+        assert_eq!(p.created_at, parse_time("2026-10-01T10:05:00Z").unwrap());
+    }
+
+    #[test]
+    fn paging_stops_only_once_a_page_reaches_since() {
+        let page = |created: &[&str]| -> Vec<Charge> {
+            let data: Vec<serde_json::Value> = created
+                .iter()
+                .enumerate()
+                .map(|(i, at)| charge(&format!("C{}", i), "1.00", at, serde_json::json!([{ "status": "CONFIRMED", "time": at }])))
+                .collect();
+            serde_json::from_value(serde_json::Value::Array(data)).unwrap()
+        };
+        let since = parse_time("2026-10-01T00:00:00Z").unwrap();
+        let (payments, reached_since) = confirmed_payments(page(&["2026-10-01T12:00:00Z", "2026-10-01T11:00:00Z"]), since);
+        assert_eq!((payments.len(), reached_since), (2, false));
+
+        // Newest first, so the first charge before since ends the search
+        let (payments, reached_since) =
+            confirmed_payments(page(&["2026-10-01T10:00:00Z", "2026-09-30T23:59:59Z", "2026-09-30T20:00:00Z"]), since);
+        assert_eq!((payments.len(), reached_since), (1, true));
+        assert_eq!(payments[0].id, "C0");
+    }
+}
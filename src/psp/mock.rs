@@ -1,13 +1,113 @@
 use super::{Payment, PaymentProvider};
-use anyhow::Result;
+use anyhow::{Context, Result};
 use async_trait::async_trait;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
 
-pub struct MockProvider;
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MockDistribution {
+    // Whole amounts spread evenly over min..=max
+    Uniform,
+    // Mostly small payments with the odd big one, like real traffic
+    Realistic,
+}
+
+// (weight in percent, cents range) for the realistic distribution
+const REALISTIC_BUCKETS: [(u32, std::ops::RangeInclusive<i64>); 3] = [
+    (70, 100..=2_000),
+    (25, 2_000..=20_000),
+    (5, 20_000..=500_000),
+];
+
+fn realistic_cents(rng: &mut impl Rng) -> i64 {
+    let mut roll = rng.gen_range(0..100);
+    for (weight, range) in REALISTIC_BUCKETS {
+        if roll < weight {
+            return rng.gen_range(range);
+        }
+        roll -= weight;
+    }
+    unreachable!("bucket weights add up to 100")
+}
+
+pub struct MockProvider {
+    distribution: MockDistribution,
+    min_units: i64,
+    max_units: i64,
+    currency: String,
+    // Seeded runs replay the same amounts and IDs
+    seed: Option<(u64, Mutex<StdRng>)>,
+    counter: AtomicU64,
+}
+
+impl Default for MockProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl MockProvider {
     pub fn new() -> Self {
-        Self
+        Self::with_params(2, 15, "EUR")
+    }
+
+    pub fn with_params(min_units: i64, max_units: i64, currency: &str) -> Self {
+        Self {
+            distribution: MockDistribution::Uniform,
+            min_units,
+            max_units,
+            currency: currency.to_uppercase(),
+            seed: None,
+            counter: AtomicU64::new(0),
+        }
+    }
+
+    pub fn seeded(mut self, seed: u64) -> Self {
+        self.seed = Some((seed, Mutex::new(StdRng::seed_from_u64(seed))));
+        self
+    }
+
+    // Mock has no credentials, so its api_key may carry "min-max:CUR" (e.g. "50-500:USD")
+    // or "realistic:CUR"; either part can be left out and empty keeps the defaults
+    pub fn from_spec(spec: &str) -> Result<Self> {
+        let mut mock = Self::new();
+        let spec = spec.trim();
+        if spec.is_empty() {
+            return Ok(mock);
+        }
+        let (range, currency) = match spec.split_once(':') {
+            Some((range, currency)) => (range, Some(currency)),
+            None if spec.contains('-') || spec.eq_ignore_ascii_case("realistic") => (spec, None),
+            None => ("", Some(spec)),
+        };
+        if range.trim().eq_ignore_ascii_case("realistic") {
+            mock.distribution = MockDistribution::Realistic;
+        } else if !range.is_empty() {
+            let (min, max) = range
+                .split_once('-')
+                .with_context(|| format!("Mock range '{}' should look like min-max", range))?;
+            mock.min_units = min.trim().parse().with_context(|| format!("invalid Mock minimum '{}'", min))?;
+            mock.max_units = max.trim().parse().with_context(|| format!("invalid Mock maximum '{}'", max))?;
+            if mock.min_units < 1 || mock.min_units > mock.max_units {
+                anyhow::bail!("Mock range {}-{} must satisfy 1 <= min <= max", mock.min_units, mock.max_units);
+            }
+        }
+        if let Some(currency) = currency.map(str::trim).filter(|c| !c.is_empty()) {
+            if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_alphabetic()) {
+                anyhow::bail!("Mock spec '{}' should look like min-max:CUR, with a 3-letter currency", spec);
+            }
+            mock.currency = currency.to_uppercase();
+        }
+        Ok(mock)
+    }
+
+    fn draw_cents(&self, rng: &mut impl Rng) -> i64 {
+        match self.distribution {
+            MockDistribution::Uniform => rng.gen_range(self.min_units..=self.max_units) * 100,
+            MockDistribution::Realistic => realistic_cents(rng),
+        }
     }
 }
 
@@ -18,19 +118,87 @@ impl PaymentProvider for MockProvider {
     }
 
     async fn fetch_recent_payments(&self, _since: chrono::DateTime<chrono::Utc>) -> Result<Vec<Payment>> {
-        let mut rng = rand::thread_rng();
-        let amount_units: i64 = rng.gen_range(2..=15);
-        let amount_cents = amount_units * 100;
+        let (amount_cents, id) = match &self.seed {
+            Some((seed, rng)) => {
+                let n = self.counter.fetch_add(1, Ordering::Relaxed);
+                let cents = self.draw_cents(&mut *rng.lock().unwrap_or_else(|e| e.into_inner()));
+                (cents, format!("mock_{}_{}", seed, n))
+            }
+            None => (
+                self.draw_cents(&mut rand::thread_rng()),
+                format!("mock_{}", chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0)),
+            ),
+        };
 
         let payment = Payment {
-            id: format!("mock_{}", chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0)),
+            id,
             amount_cents,
-            currency: "EUR".to_string(),
+            currency: self.currency.clone(),
             status: "paid".to_string(),
             created_at: chrono::Utc::now(),
             provider: "Mock".to_string(),
+            converted_cents: None,
+            settled: true,
         };
 
         Ok(vec![payment])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn realistic_amounts_cover_every_bucket_within_bounds() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let samples: Vec<i64> = (0..10_000).map(|_| realistic_cents(&mut rng)).collect();
+        assert!(samples.iter().all(|c| (100..=500_000).contains(c)));
+        let mut counts = [0usize; 3];
+        for cents in &samples {
+            let bucket = REALISTIC_BUCKETS.iter().position(|(_, range)| range.contains(cents)).unwrap();
+            counts[bucket] += 1;
+        }
+        // 70/25/5, give or take sampling noise
+        for ((weight, _), count) in REALISTIC_BUCKETS.iter().zip(counts) {
+            let percent = count as f64 / samples.len() as f64 * 100.0;
+            assert!((percent - *weight as f64).abs() < 3.0, "{}% bucket drew {:.1}%", weight, percent);
+        }
+    }
+
+    async fn draw(mock: &MockProvider, polls: usize) -> Vec<Payment> {
+        let mut payments = Vec::new();
+        for _ in 0..polls {
+            payments.extend(mock.fetch_recent_payments(chrono::Utc::now()).await.unwrap());
+        }
+        payments
+    }
+
+    #[tokio::test]
+    async fn amounts_stay_within_the_configured_range_and_currency() {
+        let payments = draw(&MockProvider::with_params(50, 60, "usd"), 500).await;
+        assert!(payments.iter().all(|p| (5_000..=6_000).contains(&p.amount_cents) && p.amount_cents % 100 == 0));
+        assert!(payments.iter().all(|p| p.currency == "USD"));
+        // Both ends come up
+        assert!(payments.iter().any(|p| p.amount_cents == 5_000) && payments.iter().any(|p| p.amount_cents == 6_000));
+
+        let payments = draw(&MockProvider::from_spec("7-7:nok").unwrap(), 20).await;
+        assert!(payments.iter().all(|p| p.amount_cents == 700 && p.currency == "NOK"));
+        let payments = draw(&MockProvider::new(), 200).await;
+        assert!(payments.iter().all(|p| (200..=1_500).contains(&p.amount_cents) && p.currency == "EUR"));
+
+        for bad in ["9-3", "0-5", "five-9:EUR", "12", "5:EURO", "5-9:EURO", "2-3:€"] {
+            assert!(MockProvider::from_spec(bad).is_err(), "{} accepted", bad);
+        }
+    }
+
+    #[tokio::test]
+    async fn the_same_seed_replays_the_same_payments() {
+        let key = |payments: Vec<Payment>| payments.into_iter().map(|p| (p.id, p.amount_cents)).collect::<Vec<_>>();
+        let first = key(draw(&MockProvider::new().seeded(42), 50).await);
+        assert_eq!(first, key(draw(&MockProvider::new().seeded(42), 50).await));
+        assert_eq!(first[0].0, "mock_42_0");
+        assert_eq!(first[49].0, "mock_42_49");
+        assert_ne!(first, key(draw(&MockProvider::new().seeded(43), 50).await));
+    }
+}
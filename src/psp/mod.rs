@@ -1,9 +1,18 @@
 pub mod adyen;
+pub mod braintree;
+pub mod coinbase;
+pub mod gocardless;
 pub mod mock;
+pub mod paypal;
+pub mod registry;
+pub mod replay;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use async_trait::async_trait;
+use futures_util::stream::{BoxStream, StreamExt};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Payment {
@@ -13,16 +22,624 @@ pub struct Payment {
     pub status: String,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub provider: String,
+    // Amount in the configured display currency, when it had to be converted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub converted_cents: Option<i64>,
+    // False while the money is only authorised; recordings from before this field count as settled
+    #[serde(default = "default_settled")]
+    pub settled: bool,
+}
+
+fn default_settled() -> bool {
+    true
+}
+
+impl Payment {
+    pub fn dedup_key(&self) -> String {
+        format!("{}:{}", self.provider, self.id)
+    }
+
+    pub fn display_cents(&self) -> i64 {
+        self.converted_cents.unwrap_or(self.amount_cents)
+    }
+}
+
+// Oldest first, ties broken by provider and ID so the order never depends on arrival
+pub fn sort_chronologically(payments: &mut [Payment]) {
+    payments.sort_by(|a, b| {
+        a.created_at
+            .cmp(&b.created_at)
+            .then_with(|| a.provider.cmp(&b.provider))
+            .then_with(|| a.id.cmp(&b.id))
+    });
+}
+
+// ISO 4217 digits after the decimal point; everything not listed has two
+pub fn minor_units(currency: &str) -> u32 {
+    const ZERO: &[&str] = &["BIF", "CLP", "DJF", "GNF", "ISK", "JPY", "KMF", "KRW", "PYG", "RWF", "UGX", "UYI", "VND", "VUV", "XAF", "XOF", "XPF"];
+    const THREE: &[&str] = &["BHD", "IQD", "JOD", "KWD", "LYD", "OMR", "TND"];
+    let code = currency.to_ascii_uppercase();
+    if ZERO.contains(&code.as_str()) {
+        0
+    } else if THREE.contains(&code.as_str()) {
+        3
+    } else {
+        2
+    }
+}
+
+// Providers that report in the currency's own minor unit (¥5000 as 5000, 1.234 KWD as
+// 1234) mapped onto the hundredths every Payment holds; a third decimal rounds half away
+pub fn hundredths_from_minor(value: i64, currency: &str) -> i64 {
+    match minor_units(currency) {
+        0 => value.saturating_mul(100),
+        3 => ((value as i128 + value.signum() as i128 * 5) / 10) as i64,
+        _ => value,
+    }
+}
+
+// "19.99" → 1999 without going through floats; None for anything malformed. Digits past
+// the hundredths (crypto charges, three-decimal currencies) round half away from zero
+pub fn parse_decimal_cents(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value),
+    };
+    let (whole, frac) = digits.split_once('.').unwrap_or((digits, ""));
+    if whole.is_empty() || !(whole.chars().chain(frac.chars())).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (hundredths, rest) = frac.split_at(frac.len().min(2));
+    let frac_cents: i64 = format!("{:0<2}", hundredths).parse().ok()?;
+    let round_up = rest.starts_with(['5', '6', '7', '8', '9']);
+    let cents = whole.parse::<i64>().ok()?.checked_mul(100)?.checked_add(frac_cents + round_up as i64)?;
+    Some(if negative { -cents } else { cents })
+}
+
+const HTTP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+// One client for every provider so connection pools and DNS caches are shared.
+// Without an explicit proxy, reqwest falls back to HTTPS_PROXY/HTTP_PROXY/NO_PROXY.
+pub fn http_client(proxy: Option<&str>, ca_bundle: Option<&Path>) -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder().timeout(HTTP_TIMEOUT);
+    if let Some(url) = proxy {
+        let proxy = reqwest::Proxy::all(url).with_context(|| format!("invalid proxy URL {:?}", url))?;
+        builder = builder.proxy(proxy);
+    }
+    if let Some(path) = ca_bundle {
+        let pem = std::fs::read(path)
+            .with_context(|| format!("failed to read CA bundle {}", path.display()))?;
+        let cert = reqwest::Certificate::from_pem(&pem)
+            .with_context(|| format!("CA bundle {} is not a valid PEM certificate", path.display()))?;
+        builder = builder.add_root_certificate(cert);
+    }
+    builder.build().context("failed to build HTTP client")
+}
+
+const MAX_RETRIES: u32 = 3;
+const MAX_RETRY_AFTER: std::time::Duration = std::time::Duration::from_secs(30);
+
+fn is_retryable(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 429 | 500 | 502 | 503 | 504)
+}
+
+// Send a request, retrying transient failures (429/5xx and network errors)
+// with jittered exponential backoff. Other statuses come back to the caller
+// untouched so it can report them.
+pub async fn send_with_retry(request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+    let mut attempt = 0;
+    loop {
+        let req = request
+            .try_clone()
+            .context("request body can't be retried")?;
+        let delay = match req.send().await {
+            Ok(resp) if attempt < MAX_RETRIES && is_retryable(resp.status()) => {
+                retry_after(&resp).unwrap_or_else(|| backoff(attempt))
+            }
+            Ok(resp) => return Ok(resp),
+            Err(e) if attempt < MAX_RETRIES && (e.is_connect() || e.is_timeout() || e.is_request()) => {
+                backoff(attempt)
+            }
+            Err(e) => return Err(e.into()),
+        };
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+fn backoff(attempt: u32) -> std::time::Duration {
+    let base = 500u64 * 2u64.pow(attempt);
+    let jitter = rand::thread_rng().gen_range(0..250);
+    std::time::Duration::from_millis(base + jitter)
+}
+
+fn retry_after(resp: &reqwest::Response) -> Option<std::time::Duration> {
+    if resp.status() != reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+    let secs: u64 = resp
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(std::time::Duration::from_secs(secs).min(MAX_RETRY_AFTER))
+}
+
+// Quota a provider reported on its latest response; any part may be missing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub remaining: Option<u64>,
+    pub limit: Option<u64>,
+    pub retry_after: Option<std::time::Duration>,
+}
+
+// Below this share of the limit, polling slows down before the provider starts refusing
+const LOW_QUOTA_PERCENT: u64 = 10;
+
+impl RateLimit {
+    // RateLimit-* or X-RateLimit-* and Retry-After. None when the response carried
+    // none of them, or nothing readable in them
+    pub fn from_headers(headers: &reqwest::header::HeaderMap) -> Option<Self> {
+        let number = |names: &[&str]| names.iter().find_map(|name| header_number(headers, name));
+        let limit = Self {
+            remaining: number(&["ratelimit-remaining", "x-ratelimit-remaining"]),
+            limit: number(&["ratelimit-limit", "x-ratelimit-limit"]),
+            retry_after: header_number(headers, "retry-after").map(std::time::Duration::from_secs),
+        };
+        (limit.remaining.is_some() || limit.limit.is_some() || limit.retry_after.is_some()).then_some(limit)
+    }
+
+    // Out of quota, told to wait, or under LOW_QUOTA_PERCENT of the limit
+    pub fn is_low(&self) -> bool {
+        match (self.remaining, self.limit) {
+            _ if self.retry_after.is_some() => true,
+            (Some(0), _) => true,
+            (Some(remaining), Some(limit)) => remaining.saturating_mul(100) < limit.saturating_mul(LOW_QUOTA_PERCENT),
+            _ => false,
+        }
+    }
+}
+
+// The leading number of a header, so "100;w=60" reads as 100. HTTP-date Retry-After
+// values and anything garbled read as nothing
+fn header_number(headers: &reqwest::header::HeaderMap, name: &str) -> Option<u64> {
+    let value = headers.get(name)?.to_str().ok()?;
+    value.split([',', ';']).next()?.trim().parse().ok()
+}
+
+// What a provider's latest response said about its quota, kept for the poller to read
+#[derive(Debug, Default)]
+pub struct RateLimitCell(std::sync::Mutex<Option<RateLimit>>);
+
+impl RateLimitCell {
+    pub fn record(&self, resp: &reqwest::Response) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = RateLimit::from_headers(resp.headers());
+    }
+
+    pub fn get(&self) -> Option<RateLimit> {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// Only this much of an error body is read, however big the page is
+const MAX_ERROR_BODY_BYTES: usize = 8 * 1024;
+// ...and this much of it ends up in the message
+const ERROR_BODY_CHARS: usize = 500;
+
+// "Adyen API error: 401 Unauthorized — {...}" with the start of the response body,
+// which usually says what's actually wrong
+pub async fn api_error(what: &str, mut resp: reqwest::Response) -> anyhow::Error {
+    let status = resp.status();
+    let mut body = Vec::new();
+    while body.len() < MAX_ERROR_BODY_BYTES {
+        match resp.chunk().await {
+            Ok(Some(chunk)) => body.extend_from_slice(&chunk),
+            _ => break,
+        }
+    }
+    let detail = error_detail(&String::from_utf8_lossy(&body));
+    if detail.is_empty() {
+        anyhow::anyhow!("{}: {}", what, status)
+    } else {
+        anyhow::anyhow!("{}: {} — {}", what, status, detail)
+    }
+}
+
+// One line, secrets masked, cut to ERROR_BODY_CHARS
+fn error_detail(body: &str) -> String {
+    let line = body.split_whitespace().collect::<Vec<_>>().join(" ");
+    let redacted = redact_tokens(&line);
+    if redacted.chars().count() <= ERROR_BODY_CHARS {
+        return redacted;
+    }
+    let mut cut: String = redacted.chars().take(ERROR_BODY_CHARS).collect();
+    cut.push('…');
+    cut
+}
+
+// Long runs of letters and digits look like keys or tokens; dashes split them,
+// so UUIDs and request IDs stay readable
+fn redact_tokens(text: &str) -> String {
+    const MIN_SECRET_LEN: usize = 24;
+    let mut out = String::with_capacity(text.len());
+    let mut run = String::new();
+    let flush = |run: &mut String, out: &mut String| {
+        let secret = run.len() >= MIN_SECRET_LEN
+            && run.chars().any(|c| c.is_ascii_digit())
+            && run.chars().any(|c| c.is_ascii_alphabetic());
+        out.push_str(if secret { "[redacted]" } else { run.as_str() });
+        run.clear();
+    };
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            run.push(c);
+        } else {
+            flush(&mut run, &mut out);
+            out.push(c);
+        }
+    }
+    flush(&mut run, &mut out);
+    out
 }
 
 #[async_trait]
 pub trait PaymentProvider: Send + Sync {
     fn name(&self) -> &str;
     async fn fetch_recent_payments(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<Payment>>;
+
+    // Push sources hand out a stream instead; when this is Some the provider isn't polled
+    fn payment_stream(&self) -> Option<BoxStream<'static, Result<Payment>>> {
+        None
+    }
+
+    // The quota reported with the latest fetch, for providers whose APIs send one
+    fn rate_limit(&self) -> Option<RateLimit> {
+        None
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PspConfig {
     pub provider: String,
     pub api_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub merchant_account: Option<String>,
+    // Second secret for providers that authenticate with an id/secret pair
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_secret: Option<String>,
+    // Payment statuses that count as revenue; the provider's default when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub statuses: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee: Option<FeeModel>,
+    // Tells apart several accounts of the same provider, e.g. "EU" and "US"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    // Overrides the global poll_interval_secs for this provider
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll_interval_secs: Option<u64>,
+}
+
+// Provider fee per payment, e.g. 1.4% + 25c is { percent: 1.4, fixed_cents: 25 }
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct FeeModel {
+    #[serde(default)]
+    pub percent: f64,
+    #[serde(default)]
+    pub fixed_cents: i64,
+}
+
+impl FeeModel {
+    // Never more than the payment itself, never negative
+    pub fn fee_cents(&self, amount_cents: i64) -> i64 {
+        if amount_cents <= 0 {
+            return 0;
+        }
+        // Float-to-int casts saturate, so a huge percentage can't wrap
+        let variable = (amount_cents as f64 * self.percent / 100.0).round() as i64;
+        variable.saturating_add(self.fixed_cents).clamp(0, amount_cents)
+    }
+}
+
+impl PspConfig {
+    pub fn display_name(&self) -> String {
+        display_name(&self.provider, self.label.as_deref())
+    }
+}
+
+// "Adyen (EU)" for labelled accounts, plain "Adyen" otherwise
+pub fn display_name(provider: &str, label: Option<&str>) -> String {
+    match label.map(str::trim).filter(|l| !l.is_empty()) {
+        Some(label) => format!("{} ({})", provider, label),
+        None => provider.to_string(),
+    }
+}
+
+// Reports a labelled account under its own name, in payments too, so
+// breakdowns and dedup keep the accounts apart
+pub struct LabeledProvider {
+    inner: std::sync::Arc<dyn PaymentProvider>,
+    name: String,
+}
+
+impl LabeledProvider {
+    pub fn new(inner: std::sync::Arc<dyn PaymentProvider>, name: String) -> Self {
+        Self { inner, name }
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for LabeledProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn fetch_recent_payments(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<Payment>> {
+        let mut payments = self.inner.fetch_recent_payments(since).await?;
+        for p in &mut payments {
+            p.provider = self.name.clone();
+        }
+        Ok(payments)
+    }
+
+    fn rate_limit(&self) -> Option<RateLimit> {
+        self.inner.rate_limit()
+    }
+
+    fn payment_stream(&self) -> Option<BoxStream<'static, Result<Payment>>> {
+        let name = self.name.clone();
+        let stream = self.inner.payment_stream()?;
+        Some(
+            stream
+                .map(move |item| {
+                    item.map(|mut p| {
+                        p.provider = name.clone();
+                        p
+                    })
+                })
+                .boxed(),
+        )
+    }
+}
+
+// Plays back one scripted result per fetch, then nothing; for driving the poller in tests
+#[cfg(test)]
+pub(crate) struct ScriptedProvider {
+    name: String,
+    script: std::sync::Mutex<std::collections::VecDeque<Result<Vec<Payment>, String>>>,
+    // The `since` of every fetch so far
+    pub(crate) fetched_since: std::sync::Mutex<Vec<chrono::DateTime<chrono::Utc>>>,
+}
+
+#[cfg(test)]
+impl ScriptedProvider {
+    pub(crate) fn new(name: &str, script: Vec<Result<Vec<Payment>, String>>) -> Self {
+        Self {
+            name: name.to_string(),
+            script: std::sync::Mutex::new(script.into()),
+            fetched_since: std::sync::Mutex::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn payment(provider: &str, id: &str, amount_cents: i64, created_at: chrono::DateTime<chrono::Utc>) -> Payment {
+        Payment {
+            id: id.to_string(),
+            amount_cents,
+            currency: "EUR".to_string(),
+            status: "succeeded".to_string(),
+            created_at,
+            provider: provider.to_string(),
+            converted_cents: None,
+            settled: true,
+        }
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl PaymentProvider for ScriptedProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn fetch_recent_payments(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<Payment>> {
+        self.fetched_since.lock().unwrap().push(since);
+        match self.script.lock().unwrap().pop_front() {
+            Some(Ok(payments)) => Ok(payments),
+            Some(Err(message)) => Err(anyhow::anyhow!(message)),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn decimal_amounts_parse_to_hundredths() {
+        assert_eq!(parse_decimal_cents("19.99"), Some(1999));
+        assert_eq!(parse_decimal_cents("19.9"), Some(1990));
+        assert_eq!(parse_decimal_cents(" 20 "), Some(2000));
+        assert_eq!(parse_decimal_cents("-0.50"), Some(-50));
+        for malformed in ["", ".5", "1.2.3", "1,50", "abc", "--1"] {
+            assert_eq!(parse_decimal_cents(malformed), None, "{:?}", malformed);
+        }
+    }
+
+    #[test]
+    fn extra_decimals_round_to_the_hundredth() {
+        assert_eq!(parse_decimal_cents("1.234"), Some(123));
+        assert_eq!(parse_decimal_cents("1.235"), Some(124));
+        assert_eq!(parse_decimal_cents("0.99999999"), Some(100));
+        assert_eq!(parse_decimal_cents("-1.235"), Some(-124));
+        assert_eq!(parse_decimal_cents("0.00012345"), Some(0));
+    }
+
+    #[test]
+    fn fees_round_to_the_cent_and_stay_within_the_payment() {
+        let stripe = FeeModel { percent: 1.4, fixed_cents: 25 };
+        assert_eq!(stripe.fee_cents(10_000), 165);
+        // 1.4% of €0.75 is 1.05c, so one cent
+        assert_eq!(stripe.fee_cents(75), 26);
+        // 1.4% of €1.25 is 1.75c, rounded up to two
+        assert_eq!(stripe.fee_cents(125), 27);
+        // A fee can't exceed a small payment or apply to a refund
+        assert_eq!(stripe.fee_cents(10), 10);
+        assert_eq!(stripe.fee_cents(-500), 0);
+        assert_eq!(FeeModel { percent: 2.9, fixed_cents: 0 }.fee_cents(999), 29);
+        assert_eq!(FeeModel { percent: 0.0, fixed_cents: -50 }.fee_cents(1_000), 0);
+        assert_eq!(FeeModel { percent: 1e30, fixed_cents: 0 }.fee_cents(1_000), 1_000);
+    }
+
+    #[test]
+    fn an_out_of_order_batch_is_ingested_oldest_first() {
+        let t = |secs: i64| chrono::DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap();
+        let mut batch = vec![
+            ScriptedProvider::payment("PayPal", "c", 300, t(30)),
+            ScriptedProvider::payment("Adyen", "b", 200, t(10)),
+            ScriptedProvider::payment("PayPal", "a", 100, t(10)),
+            ScriptedProvider::payment("Adyen", "a", 400, t(0)),
+            ScriptedProvider::payment("Adyen", "b", 200, t(10)),
+        ];
+        sort_chronologically(&mut batch);
+
+        crate::config::use_temp_config_dir();
+        let mut app = crate::app::App::new();
+        for p in batch {
+            app.add_payment(p);
+        }
+        // Ties break on provider then ID, and the duplicate is still counted once
+        let ledger: Vec<(&str, &str)> = app.session_payments.iter().map(|p| (p.provider.as_str(), p.id.as_str())).collect();
+        assert_eq!(ledger, [("Adyen", "a"), ("Adyen", "b"), ("PayPal", "a"), ("PayPal", "c")]);
+        assert_eq!(app.total_cents, 1_000);
+    }
+
+    // Answers one connection per (status, extra headers, body) on a local port, in order,
+    // counting the requests served
+    async fn stub_server(responses: Vec<(&'static str, &'static str, String)>) -> (String, Arc<AtomicUsize>) {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/payments", listener.local_addr().unwrap());
+        let served = Arc::new(AtomicUsize::new(0));
+        let count = served.clone();
+        tokio::spawn(async move {
+            for (status, headers, body) in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let (read, mut write) = stream.split();
+                // The request's headers end at the first blank line
+                let mut lines = tokio::io::BufReader::new(read).lines();
+                while lines.next_line().await.unwrap().is_some_and(|line| !line.is_empty()) {}
+                let response = format!(
+                    "HTTP/1.1 {}\r\n{}content-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    status,
+                    headers,
+                    body.len(),
+                    body
+                );
+                count.fetch_add(1, Ordering::SeqCst);
+                write.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (url, served)
+    }
+
+    async fn error_for(status: &'static str, body: String) -> String {
+        let (url, _) = stub_server(vec![(status, "", body)]).await;
+        let resp = reqwest::get(url).await.unwrap();
+        format!("{:#}", api_error("Adyen API error", resp).await)
+    }
+
+    #[tokio::test]
+    async fn api_errors_carry_the_reason_from_the_body() {
+        let body = serde_json::json!({
+            "status": 400,
+            "errorCode": "901",
+            "message": "Invalid Merchant Account",
+            "errorType": "validation",
+            "pspReference": "AQE1hmfxKo3NaxZDw2xQa3k9a8B8c7",
+        });
+        let message = error_for("400 Bad Request", body.to_string()).await;
+        assert!(message.starts_with("Adyen API error: 400 Bad Request — {"), "{}", message);
+        assert!(message.contains(r#""message":"Invalid Merchant Account""#), "{}", message);
+        // Anything that looks like a key is masked
+        assert!(message.contains(r#""pspReference":"[redacted]""#), "{}", message);
+
+        // A huge error page is cut short
+        let message = error_for("502 Bad Gateway", format!("<html>{}</html>", "upstream down ".repeat(5_000))).await;
+        assert!(message.ends_with('…'));
+        assert!(message.chars().count() < ERROR_BODY_CHARS + 50);
+
+        assert_eq!(error_for("401 Unauthorized", String::new()).await, "Adyen API error: 401 Unauthorized");
+    }
+
+    async fn quota_from(headers: &'static str) -> Option<RateLimit> {
+        let (url, _) = stub_server(vec![("200 OK", headers, "[]".to_string())]).await;
+        RateLimit::from_headers(reqwest::get(url).await.unwrap().headers())
+    }
+
+    #[tokio::test]
+    async fn rate_limit_headers_land_in_the_provider_status() {
+        let quota = quota_from("RateLimit-Limit: 300\r\nRateLimit-Remaining: 240;w=60\r\n").await.unwrap();
+        assert_eq!(quota, RateLimit { remaining: Some(240), limit: Some(300), retry_after: None });
+        assert!(!quota.is_low());
+
+        crate::config::use_temp_config_dir();
+        let mut app = crate::App::new();
+        app.record_poll_success("Adyen");
+        app.record_rate_limit("Adyen", quota);
+        let status = app.provider_statuses.iter().find(|s| s.name == "Adyen").unwrap();
+        assert_eq!(status.rate_limit, Some(quota));
+
+        // The X- spelling and a Retry-After, which always means slow down
+        let quota = quota_from("X-RateLimit-Remaining: 0\r\nRetry-After: 30\r\n").await.unwrap();
+        assert_eq!(quota, RateLimit { remaining: Some(0), limit: None, retry_after: Some(std::time::Duration::from_secs(30)) });
+        assert!(quota.is_low());
+
+        // Garbled or missing headers read as nothing rather than as zero
+        assert_eq!(quota_from("RateLimit-Remaining: lots\r\nRetry-After: Wed, 21 Oct 2026 07:28:00 GMT\r\n").await, None);
+        assert_eq!(quota_from("").await, None);
+        let partial = quota_from("RateLimit-Limit: 300\r\nRateLimit-Remaining: -1\r\n").await.unwrap();
+        assert_eq!(partial, RateLimit { remaining: None, limit: Some(300), retry_after: None });
+    }
+
+    #[test]
+    fn a_bad_proxy_or_ca_bundle_fails_the_client_with_a_reason() {
+        assert!(http_client(None, None).is_ok());
+        assert!(http_client(Some("http://proxy.corp.example:3128"), None).is_ok());
+        let error = http_client(Some("not a url"), None).unwrap_err();
+        assert!(format!("{:#}", error).starts_with("invalid proxy URL \"not a url\""), "{:#}", error);
+
+        let dir = std::env::temp_dir().join(format!("profit-cli-psp-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let missing = dir.join("missing.pem");
+        let error = http_client(None, Some(&missing)).unwrap_err();
+        assert!(format!("{:#}", error).starts_with(&format!("failed to read CA bundle {}", missing.display())), "{:#}", error);
+        let garbage = dir.join("garbage.pem");
+        std::fs::write(&garbage, "-----BEGIN CERTIFICATE-----\nnot base64\n-----END CERTIFICATE-----\n").unwrap();
+        let error = http_client(None, Some(&garbage)).unwrap_err();
+        assert!(format!("{:#}", error).contains("is not a valid PEM certificate"), "{:#}", error);
+    }
+
+    #[tokio::test]
+    async fn transient_failures_are_retried_and_auth_failures_are_not() {
+        let client = reqwest::Client::new();
+        let unavailable = || ("503 Service Unavailable", "", String::new());
+        let (url, served) = stub_server(vec![unavailable(), unavailable(), ("200 OK", "", "[]".to_string())]).await;
+        let resp = send_with_retry(client.get(url)).await.unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::OK);
+        assert_eq!(resp.text().await.unwrap(), "[]");
+        assert_eq!(served.load(Ordering::SeqCst), 3);
+
+        let (url, served) = stub_server(vec![("401 Unauthorized", "", String::new()), ("200 OK", "", "[]".to_string())]).await;
+        let resp = send_with_retry(client.get(url)).await.unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::UNAUTHORIZED);
+        assert_eq!(served.load(Ordering::SeqCst), 1);
+    }
 }
@@ -0,0 +1,254 @@
+use super::{api_error, parse_decimal_cents, send_with_retry, Payment, PaymentProvider, RateLimit, RateLimitCell};
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+const BASE_URL: &str = "https://api-m.paypal.com";
+
+// Refresh a little early so a token never expires mid-request
+const TOKEN_MARGIN: Duration = Duration::from_secs(60);
+
+// PayPal caps reporting queries; a session never gets near this many pages
+const MAX_PAGES: u32 = 10;
+
+pub struct PayPalProvider {
+    client_id: String,
+    client_secret: String,
+    client: reqwest::Client,
+    token: Mutex<Option<CachedToken>>,
+    limits: RateLimitCell,
+}
+
+struct CachedToken {
+    access_token: String,
+    expires_at: Instant,
+}
+
+impl CachedToken {
+    fn usable_at(&self, now: Instant) -> bool {
+        now + TOKEN_MARGIN < self.expires_at
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+#[derive(Deserialize)]
+struct TransactionPage {
+    #[serde(default)]
+    transaction_details: Vec<TransactionDetail>,
+    #[serde(default)]
+    page: u32,
+    #[serde(default)]
+    total_pages: u32,
+}
+
+#[derive(Deserialize)]
+struct TransactionDetail {
+    transaction_info: TransactionInfo,
+}
+
+#[derive(Deserialize)]
+struct TransactionInfo {
+    transaction_id: String,
+    transaction_amount: PayPalAmount,
+    #[serde(default)]
+    transaction_status: String,
+    transaction_initiation_date: String,
+}
+
+#[derive(Deserialize)]
+struct PayPalAmount {
+    currency_code: String,
+    value: String,
+}
+
+impl PayPalProvider {
+    pub fn new(client_id: String, client_secret: String, client: reqwest::Client) -> Self {
+        Self {
+            client_id,
+            client_secret,
+            client,
+            token: Mutex::new(None),
+            limits: RateLimitCell::default(),
+        }
+    }
+
+    // Client-credentials token, cached until shortly before it expires
+    async fn access_token(&self) -> Result<String> {
+        let mut cached = self.token.lock().await;
+        if let Some(token) = cached.as_ref().filter(|t| t.usable_at(Instant::now())) {
+            return Ok(token.access_token.clone());
+        }
+
+        let resp = send_with_retry(
+            self.client
+                .post(format!("{}/v1/oauth2/token", BASE_URL))
+                .basic_auth(&self.client_id, Some(&self.client_secret))
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .body("grant_type=client_credentials"),
+        )
+        .await?;
+
+        if !resp.status().is_success() {
+            return Err(api_error("PayPal auth error", resp).await);
+        }
+
+        let token: TokenResponse = resp.json().await?;
+        let access_token = token.access_token.clone();
+        *cached = Some(CachedToken {
+            access_token: token.access_token,
+            expires_at: Instant::now() + Duration::from_secs(token.expires_in),
+        });
+        Ok(access_token)
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for PayPalProvider {
+    fn name(&self) -> &str {
+        "PayPal"
+    }
+
+    fn rate_limit(&self) -> Option<RateLimit> {
+        self.limits.get()
+    }
+
+    async fn fetch_recent_payments(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<Payment>> {
+        let token = self.access_token().await?;
+        let start = since.format("%Y-%m-%dT%H:%M:%S%z").to_string();
+        let end = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%z").to_string();
+        let mut payments = Vec::new();
+
+        for page in 1..=MAX_PAGES {
+            let page_param = page.to_string();
+            let resp = send_with_retry(
+                self.client
+                    .get(format!("{}/v1/reporting/transactions", BASE_URL))
+                    .bearer_auth(&token)
+                    .query(&[
+                        ("start_date", start.as_str()),
+                        ("end_date", end.as_str()),
+                        ("fields", "transaction_info"),
+                        ("page_size", "100"),
+                        ("page", page_param.as_str()),
+                    ]),
+            )
+            .await?;
+            self.limits.record(&resp);
+
+            if resp.status() == reqwest::StatusCode::UNAUTHORIZED {
+                // Token revoked early; drop it so the next poll fetches a fresh one
+                *self.token.lock().await = None;
+            }
+            if !resp.status().is_success() {
+                return Err(api_error("PayPal API error", resp).await);
+            }
+
+            let list: TransactionPage = resp.json().await?;
+            payments.extend(completed_payments(list.transaction_details, since));
+
+            if list.page >= list.total_pages {
+                break;
+            }
+        }
+
+        Ok(payments)
+    }
+}
+
+// Completed, positive transactions from `since` on
+fn completed_payments(details: Vec<TransactionDetail>, since: chrono::DateTime<chrono::Utc>) -> Vec<Payment> {
+    let mut payments = Vec::new();
+    for detail in details {
+        let info = detail.transaction_info;
+        // S = completed; pending, denied and reversed don't count
+        if info.transaction_status != "S" {
+            continue;
+        }
+        // Negative amounts are refunds and payouts
+        let Some(amount_cents) = parse_decimal_cents(&info.transaction_amount.value).filter(|c| *c > 0) else {
+            continue;
+        };
+        let Ok(created) = chrono::DateTime::parse_from_str(&info.transaction_initiation_date, "%Y-%m-%dT%H:%M:%S%z") else {
+            tracing::warn!("PayPal transaction {} has an unreadable date {:?}, skipped", info.transaction_id, info.transaction_initiation_date);
+            continue;
+        };
+        let created = created.with_timezone(&chrono::Utc);
+        if created < since {
+            continue;
+        }
+
+        payments.push(Payment {
+            id: info.transaction_id,
+            amount_cents,
+            currency: info.transaction_amount.currency_code,
+            status: "completed".to_string(),
+            created_at: created,
+            provider: "PayPal".to_string(),
+            converted_cents: None,
+            settled: true,
+        });
+    }
+    payments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_is_refreshed_shortly_before_it_expires() {
+        let now = Instant::now();
+        let token = |expires_in| CachedToken { access_token: "A21".to_string(), expires_at: now + expires_in };
+        assert!(token(Duration::from_secs(3600)).usable_at(now));
+        assert!(!token(TOKEN_MARGIN).usable_at(now));
+        assert!(!token(Duration::from_secs(30)).usable_at(now));
+    }
+
+    #[test]
+    fn completed_transactions_are_mapped() {
+        let page: TransactionPage = serde_json::from_value(serde_json::json!({
+            "transaction_details": [
+                { "transaction_info": {
+                    "transaction_id": "5TY05013RG002845M",
+                    "transaction_amount": { "currency_code": "USD", "value": "465.00" },
+                    "transaction_status": "S",
+                    "transaction_initiation_date": "2026-10-01T10:15:00+0000"
+                } },
+                { "transaction_info": {
+                    "transaction_id": "REFUND",
+                    "transaction_amount": { "currency_code": "USD", "value": "-20.00" },
+                    "transaction_status": "S",
+                    "transaction_initiation_date": "2026-10-01T10:16:00+0000"
+                } },
+                { "transaction_info": {
+                    "transaction_id": "PENDING",
+                    "transaction_amount": { "currency_code": "USD", "value": "5.00" },
+                    "transaction_status": "P",
+                    "transaction_initiation_date": "2026-10-01T10:17:00+0000"
+                } },
+                { "transaction_info": {
+                    "transaction_id": "EARLY",
+                    "transaction_amount": { "currency_code": "USD", "value": "5.00" },
+                    "transaction_status": "S",
+                    "transaction_initiation_date": "2026-09-30T23:59:59+0000"
+                } }
+            ],
+            "page": 1,
+            "total_pages": 1
+        }))
+        .unwrap();
+        let since = chrono::DateTime::parse_from_rfc3339("2026-10-01T00:00:00Z").unwrap().with_timezone(&chrono::Utc);
+        let payments = completed_payments(page.transaction_details, since);
+        assert_eq!(payments.len(), 1);
+        let p = &payments[0];
+        assert_eq!((p.id.as_str(), p.amount_cents, p.currency.as_str()), ("5TY05013RG002845M", 46_500, "USD"));
+        assert_eq!(p.created_at.to_rfc3339(), "2026-10-01T10:15:00+00:00");
+    }
+}
@@ -0,0 +1,196 @@
+use super::{PaymentProvider, PspConfig};
+use crate::config::AppConfig;
+use anyhow::{Context, Result};
+use std::sync::{Arc, OnceLock};
+
+// What a constructor may need beyond its own PspConfig entry
+pub struct BuildContext<'a> {
+    pub config: &'a AppConfig,
+    pub client: &'a reqwest::Client,
+}
+
+pub type Constructor = fn(&PspConfig, &BuildContext) -> Result<Arc<dyn PaymentProvider>>;
+
+struct Entry {
+    name: &'static str,
+    // Offered in the setup list; Replay only ever comes from --import
+    in_setup: bool,
+    build: Constructor,
+}
+
+#[derive(Default)]
+pub struct ProviderRegistry {
+    entries: Vec<Entry>,
+}
+
+impl ProviderRegistry {
+    pub fn register(&mut self, name: &'static str, build: Constructor) -> &mut Self {
+        self.entries.push(Entry { name, in_setup: true, build });
+        self
+    }
+
+    pub fn register_hidden(&mut self, name: &'static str, build: Constructor) -> &mut Self {
+        self.entries.push(Entry { name, in_setup: false, build });
+        self
+    }
+
+    // Setup list order is registration order
+    pub fn setup_names(&self) -> Vec<&'static str> {
+        self.entries.iter().filter(|e| e.in_setup).map(|e| e.name).collect()
+    }
+
+    pub fn build(&self, cfg: &PspConfig, ctx: &BuildContext) -> Result<Arc<dyn PaymentProvider>> {
+        let entry = self
+            .entries
+            .iter()
+            .find(|e| e.name == cfg.provider)
+            .with_context(|| format!("unknown provider name '{}'", cfg.provider))?;
+        (entry.build)(cfg, ctx)
+    }
+}
+
+pub type ProviderList = Vec<Arc<dyn PaymentProvider>>;
+
+// The providers that could be built, plus one message per config entry that
+// couldn't (unknown name, missing credential) so nothing is dropped silently
+pub fn build_providers(config: &AppConfig) -> Result<(ProviderList, Vec<String>)> {
+    let client = super::http_client(
+        config.proxy.as_deref(),
+        config.ca_bundle_path.as_deref().map(std::path::Path::new),
+    )?;
+    let ctx = BuildContext { config, client: &client };
+    let mut providers: ProviderList = Vec::new();
+    let mut problems = Vec::new();
+    for cfg in &config.providers {
+        let provider = match registry().build(cfg, &ctx) {
+            Ok(provider) => provider,
+            Err(e) => {
+                problems.push(format!("{}: {:#}", cfg.display_name(), e));
+                continue;
+            }
+        };
+        let name = cfg.display_name();
+        if name == provider.name() {
+            providers.push(provider);
+        } else {
+            providers.push(Arc::new(super::LabeledProvider::new(provider, name)));
+        }
+    }
+    Ok((providers, problems))
+}
+
+fn required<'a>(value: Option<&'a String>, what: &str) -> Result<&'a String> {
+    value.filter(|v| !v.is_empty()).with_context(|| format!("missing {}", what))
+}
+
+fn builtin() -> ProviderRegistry {
+    let mut registry = ProviderRegistry::default();
+    registry
+        .register("Mock", |cfg, ctx| {
+            let mock = super::mock::MockProvider::from_spec(&cfg.api_key)?;
+            Ok(Arc::new(match ctx.config.mock_seed {
+                Some(seed) => mock.seeded(seed),
+                None => mock,
+            }))
+        })
+        .register("Adyen", |cfg, ctx| {
+            Ok(Arc::new(super::adyen::AdyenProvider::try_new(
+                cfg.api_key.clone(),
+                cfg.merchant_account.clone().unwrap_or_default(),
+                cfg.statuses.clone(),
+                ctx.client.clone(),
+            )?))
+        })
+        .register("PayPal", |cfg, ctx| {
+            let secret = required(cfg.api_secret.as_ref(), "client secret")?;
+            Ok(Arc::new(super::paypal::PayPalProvider::new(cfg.api_key.clone(), secret.clone(), ctx.client.clone())))
+        })
+        .register("GoCardless", |cfg, ctx| {
+            Ok(Arc::new(super::gocardless::GoCardlessProvider::new(cfg.api_key.clone(), ctx.client.clone())))
+        })
+        .register("Coinbase", |cfg, ctx| {
+            Ok(Arc::new(super::coinbase::CoinbaseCommerceProvider::new(cfg.api_key.clone(), ctx.client.clone())))
+        })
+        .register("Braintree", |cfg, ctx| {
+            let private_key = required(cfg.api_secret.as_ref(), "private key")?;
+            Ok(Arc::new(super::braintree::BraintreeProvider::new(
+                cfg.api_key.clone(),
+                private_key.clone(),
+                ctx.client.clone(),
+            )))
+        })
+        .register_hidden("Replay", |_, ctx| {
+            let source = ctx.config.replay.as_ref().context("missing file to import")?;
+            Ok(Arc::new(super::replay::ReplayProvider::load(source)?))
+        });
+    registry
+}
+
+// Built once, on first use
+pub fn registry() -> &'static ProviderRegistry {
+    static REGISTRY: OnceLock<ProviderRegistry> = OnceLock::new();
+    REGISTRY.get_or_init(builtin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn adyen(label: &str, merchant: &str) -> PspConfig {
+        PspConfig {
+            provider: "Adyen".to_string(),
+            api_key: format!("key_{}", merchant),
+            merchant_account: Some(merchant.to_string()),
+            label: Some(label.to_string()),
+            ..PspConfig::default()
+        }
+    }
+
+    #[test]
+    fn two_accounts_of_one_provider_are_two_providers() {
+        let config = AppConfig { providers: vec![adyen("EU", "ShopEU"), adyen("US", "ShopUS")], ..AppConfig::default() };
+        let (providers, problems) = build_providers(&config).unwrap();
+        assert!(problems.is_empty(), "{:?}", problems);
+        let names: Vec<_> = providers.iter().map(|p| p.name()).collect();
+        assert_eq!(names, ["Adyen (EU)", "Adyen (US)"]);
+    }
+
+    #[test]
+    fn registered_providers_are_offered_and_built() {
+        let mut registry = ProviderRegistry::default();
+        registry
+            .register("Mock", |cfg, _| Ok(Arc::new(crate::psp::mock::MockProvider::from_spec(&cfg.api_key)?)))
+            .register_hidden("Replay", |_, _| anyhow::bail!("not built in this test"));
+        assert_eq!(registry.setup_names(), ["Mock"]);
+
+        let config = AppConfig::default();
+        let client = reqwest::Client::new();
+        let ctx = BuildContext { config: &config, client: &client };
+        let mock = PspConfig { provider: "Mock".to_string(), api_key: "1-1:USD".to_string(), ..PspConfig::default() };
+        assert_eq!(registry.build(&mock, &ctx).unwrap().name(), "Mock");
+
+        let typo = PspConfig { provider: "Adyne".to_string(), ..PspConfig::default() };
+        let error = registry.build(&typo, &ctx).err().unwrap();
+        assert_eq!(error.to_string(), "unknown provider name 'Adyne'");
+    }
+
+    #[test]
+    fn unbuildable_entries_are_reported_not_dropped() {
+        let providers = vec![
+            PspConfig { provider: "Mock".to_string(), ..PspConfig::default() },
+            PspConfig { provider: "Strpie".to_string(), api_key: "sk_test".to_string(), ..PspConfig::default() },
+            PspConfig { provider: "Adyen".to_string(), api_key: " ".to_string(), merchant_account: Some("Shop".to_string()), ..PspConfig::default() },
+            adyen("EU", ""),
+        ];
+        let (built, problems) = build_providers(&AppConfig { providers, ..AppConfig::default() }).unwrap();
+        assert_eq!(built.len(), 1);
+        assert_eq!(
+            problems,
+            [
+                "Strpie: unknown provider name 'Strpie'",
+                "Adyen: Adyen API key is empty",
+                "Adyen (EU): Adyen merchant account is empty; set merchant_account in the provider entry",
+            ]
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use super::{Payment, PaymentProvider};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures_util::stream::{self, BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Where --import or --play reads from and how much faster than real time to play it
+#[derive(Debug, Clone)]
+pub struct ReplaySource {
+    pub path: PathBuf,
+    pub speed: f64,
+    // A --record file, which carries its own timing, rather than a plain payment list
+    pub cast: bool,
+}
+
+// One line of a .profitcast file: a payment and how far into the session it arrived
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CastEntry {
+    pub offset_ms: u64,
+    pub payment: Payment,
+}
+
+// Plays back recorded payments, releasing each once its offset has passed since the
+// start. Imports take offsets from created_at, relative to the first payment
+pub struct ReplayProvider {
+    entries: Vec<(Duration, Payment)>,
+    started: Instant,
+    speed: f64,
+    next: Mutex<usize>,
+}
+
+impl ReplayProvider {
+    pub fn load(source: &ReplaySource) -> Result<Self> {
+        let read = if source.cast { read_cast(&source.path) } else { read_payments(&source.path).map(offsets_from_created) };
+        let mut entries = read.with_context(|| format!("failed to import {}", source.path.display()))?;
+        entries.sort_by_key(|(offset, _)| *offset);
+        Ok(Self {
+            entries,
+            started: Instant::now(),
+            speed: if source.speed > 0.0 { source.speed } else { 1.0 },
+            next: Mutex::new(0),
+        })
+    }
+
+    // Where a payment falls on the (sped up) playback clock; a speed slow enough to push it
+    // past what a Duration holds leaves it due never rather than panicking
+    fn due_at(&self, offset: Duration) -> Duration {
+        Duration::try_from_secs_f64(offset.as_secs_f64() / self.speed).unwrap_or(Duration::MAX)
+    }
+}
+
+// A JSON array of payments, or the CSV written by the export key
+fn read_payments(path: &Path) -> Result<Vec<Payment>> {
+    let is_csv = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("csv"));
+    if is_csv {
+        let mut rdr = csv::Reader::from_path(path)?;
+        Ok(rdr.deserialize().collect::<Result<Vec<Payment>, _>>()?)
+    } else {
+        let data = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&data)?)
+    }
+}
+
+fn offsets_from_created(payments: Vec<Payment>) -> Vec<(Duration, Payment)> {
+    let Some(first) = payments.iter().map(|p| p.created_at).min() else {
+        return Vec::new();
+    };
+    payments
+        .into_iter()
+        .map(|p| {
+            let offset = (p.created_at - first).to_std().unwrap_or_default();
+            (offset, p)
+        })
+        .collect()
+}
+
+// JSON lines of CastEntry; blank lines are skipped
+fn read_cast(path: &Path) -> Result<Vec<(Duration, Payment)>> {
+    let file = std::io::BufReader::new(std::fs::File::open(path)?);
+    let mut entries = Vec::new();
+    for (n, line) in file.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: CastEntry = serde_json::from_str(&line).with_context(|| format!("line {}", n + 1))?;
+        entries.push((Duration::from_millis(entry.offset_ms), entry.payment));
+    }
+    Ok(entries)
+}
+
+#[async_trait]
+impl PaymentProvider for ReplayProvider {
+    fn name(&self) -> &str {
+        "Replay"
+    }
+
+    async fn fetch_recent_payments(&self, _since: chrono::DateTime<chrono::Utc>) -> Result<Vec<Payment>> {
+        let elapsed = self.started.elapsed();
+        let mut next = self.next.lock().unwrap_or_else(|e| e.into_inner());
+        let due = self.entries[*next..]
+            .iter()
+            .take_while(|(offset, _)| self.due_at(*offset) <= elapsed)
+            .count();
+        let batch = self.entries[*next..*next + due].iter().map(|(_, p)| p.clone()).collect();
+        *next += due;
+        Ok(batch)
+    }
+
+    // Each payment arrives at its own offset rather than on the next poll
+    fn payment_stream(&self) -> Option<BoxStream<'static, Result<Payment>>> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        let started = tokio::time::Instant::from_std(self.started);
+        let entries: Vec<(Duration, Payment)> = self.entries.iter().map(|(offset, p)| (self.due_at(*offset), p.clone())).collect();
+        Some(
+            stream::iter(entries)
+                .then(move |(due, p)| async move {
+                    match started.checked_add(due) {
+                        Some(at) => tokio::time::sleep_until(at).await,
+                        None => std::future::pending().await,
+                    }
+                    Ok(p)
+                })
+                .boxed(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::psp::ScriptedProvider;
+
+    fn import(name: &str, payments: &[Payment], speed: f64) -> ReplayProvider {
+        let path = std::env::temp_dir().join(format!("profit-cli-replay-{}-{}.json", std::process::id(), name));
+        std::fs::write(&path, serde_json::to_string(payments).unwrap()).unwrap();
+        let replay = ReplayProvider::load(&ReplaySource { path: path.clone(), speed, cast: false }).unwrap();
+        std::fs::remove_file(path).unwrap();
+        replay
+    }
+
+    #[tokio::test]
+    async fn imported_payments_play_in_chronological_order() {
+        let start = chrono::Utc::now() - chrono::Duration::hours(3);
+        let at = |hours| start + chrono::Duration::hours(hours);
+        let payments = [
+            ScriptedProvider::payment("Replay", "third", 300, at(2)),
+            ScriptedProvider::payment("Replay", "first", 100, at(0)),
+            ScriptedProvider::payment("Replay", "second", 200, at(1)),
+        ];
+
+        // Fast enough that three hours have passed by the first fetch
+        let replay = import("fast", &payments, 1e9);
+        let ids: Vec<_> = replay.fetch_recent_payments(start).await.unwrap().into_iter().map(|p| p.id).collect();
+        assert_eq!(ids, ["first", "second", "third"]);
+        assert!(replay.fetch_recent_payments(start).await.unwrap().is_empty());
+
+        // In real time only the earliest is due yet
+        let replay = import("slow", &payments, 1.0);
+        let ids: Vec<_> = replay.fetch_recent_payments(start).await.unwrap().into_iter().map(|p| p.id).collect();
+        assert_eq!(ids, ["first"]);
+    }
+
+    #[tokio::test]
+    async fn a_recorded_session_plays_back_in_order_and_on_time() {
+        let now = chrono::Utc::now();
+        let recorded = [
+            (Duration::ZERO, ScriptedProvider::payment("Adyen", "a", 100, now)),
+            (Duration::from_millis(150), ScriptedProvider::payment("PayPal", "b", 200, now)),
+            (Duration::from_millis(300), ScriptedProvider::payment("Adyen", "c", 300, now)),
+        ];
+        let path = std::env::temp_dir().join(format!("profit-cli-replay-{}.profitcast", std::process::id()));
+        let mut out = Vec::new();
+        for (offset, payment) in &recorded {
+            crate::export::write_cast_line(&mut out, *offset, payment).unwrap();
+        }
+        std::fs::write(&path, out).unwrap();
+        let source = ReplaySource { path: path.clone(), speed: 1.0, cast: true };
+
+        let replay = ReplayProvider::load(&source).unwrap();
+        let started = Instant::now();
+        let mut played = Vec::new();
+        let mut stream = replay.payment_stream().unwrap();
+        while let Some(payment) = stream.next().await {
+            played.push((started.elapsed(), payment.unwrap()));
+        }
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(played.len(), recorded.len());
+        for ((offset, expected), (at, payment)) in recorded.iter().zip(&played) {
+            assert_eq!((&payment.provider, &payment.id, payment.amount_cents), (&expected.provider, &expected.id, expected.amount_cents));
+            // Never early; late only by as much as a busy test machine oversleeps
+            let on_time = *at + Duration::from_millis(10) >= *offset && *at < *offset + Duration::from_millis(250);
+            assert!(on_time, "{} due at {:?}, played at {:?}", payment.id, offset, at);
+        }
+    }
+
+    #[tokio::test]
+    async fn a_vanishingly_slow_speed_never_plays_the_later_payments() {
+        let start = chrono::Utc::now() - chrono::Duration::hours(1);
+        let payments = [
+            ScriptedProvider::payment("Replay", "first", 100, start),
+            ScriptedProvider::payment("Replay", "later", 200, start + chrono::Duration::seconds(1)),
+        ];
+        let replay = import("tiny", &payments, 1e-300);
+        assert_eq!(replay.due_at(Duration::from_secs(1)), Duration::MAX);
+        let ids: Vec<_> = replay.fetch_recent_payments(start).await.unwrap().into_iter().map(|p| p.id).collect();
+        assert_eq!(ids, ["first"]);
+
+        // Streamed, the first arrives and the later one just never does
+        let mut stream = import("tiny-stream", &payments, 1e-300).payment_stream().unwrap();
+        assert_eq!(stream.next().await.unwrap().unwrap().id, "first");
+        assert!(tokio::time::timeout(Duration::from_millis(50), stream.next()).await.is_err());
+    }
+}
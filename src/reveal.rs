@@ -0,0 +1,13 @@
+use std::path::Path;
+
+// Opens a folder in the system file manager, compiled in with the `open-config` feature
+#[cfg(feature = "open-config")]
+pub fn open_dir(path: &Path) -> anyhow::Result<()> {
+    opener::open(path)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "open-config"))]
+pub fn open_dir(_path: &Path) -> anyhow::Result<()> {
+    anyhow::bail!("built without open-config support")
+}
@@ -0,0 +1,97 @@
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const SERVICE: &str = "profit-cli";
+
+// Written to config.json in place of a key that lives in the keyring
+pub const KEYRING_PLACEHOLDER: &str = "<keyring>";
+
+static KEYRING_ENABLED: AtomicBool = AtomicBool::new(true);
+
+pub trait SecretStore {
+    fn get(&self, account: &str) -> Result<Option<String>>;
+    fn set(&self, account: &str, secret: &str) -> Result<()>;
+    // True if there was a secret to remove
+    fn delete(&self, account: &str) -> Result<bool>;
+}
+
+pub struct KeyringStore;
+
+impl SecretStore for KeyringStore {
+    fn get(&self, account: &str) -> Result<Option<String>> {
+        match keyring::Entry::new(SERVICE, account)?.get_password() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn set(&self, account: &str, secret: &str) -> Result<()> {
+        keyring::Entry::new(SERVICE, account)?.set_password(secret)?;
+        Ok(())
+    }
+
+    fn delete(&self, account: &str) -> Result<bool> {
+        match keyring::Entry::new(SERVICE, account)?.delete_credential() {
+            Ok(()) => Ok(true),
+            Err(keyring::Error::NoEntry) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+// Keeps secrets in config.json, as before keyring support
+pub struct PlaintextStore;
+
+impl SecretStore for PlaintextStore {
+    fn get(&self, _account: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn set(&self, _account: &str, _secret: &str) -> Result<()> {
+        anyhow::bail!("keyring disabled")
+    }
+
+    fn delete(&self, _account: &str) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+// Keeps secrets in memory only, e.g. for tests
+#[derive(Default)]
+pub struct InMemorySecretStore {
+    secrets: std::sync::Mutex<std::collections::HashMap<String, String>>,
+}
+
+impl InMemorySecretStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SecretStore for InMemorySecretStore {
+    fn get(&self, account: &str) -> Result<Option<String>> {
+        Ok(self.secrets.lock().unwrap_or_else(|e| e.into_inner()).get(account).cloned())
+    }
+
+    fn set(&self, account: &str, secret: &str) -> Result<()> {
+        self.secrets.lock().unwrap_or_else(|e| e.into_inner()).insert(account.to_string(), secret.to_string());
+        Ok(())
+    }
+
+    fn delete(&self, account: &str) -> Result<bool> {
+        Ok(self.secrets.lock().unwrap_or_else(|e| e.into_inner()).remove(account).is_some())
+    }
+}
+
+pub fn disable_keyring() {
+    KEYRING_ENABLED.store(false, Ordering::Relaxed);
+}
+
+pub fn active_store() -> Box<dyn SecretStore> {
+    if KEYRING_ENABLED.load(Ordering::Relaxed) {
+        Box::new(KeyringStore)
+    } else {
+        Box::new(PlaintextStore)
+    }
+}
@@ -0,0 +1,138 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::app::{BillAnimation, PendingBill};
+use crate::config::{profile_file, write_atomic};
+use crate::psp::Payment;
+
+// The running session, saved on quit so the next start picks it back up
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionState {
+    pub start_time: chrono::DateTime<chrono::Utc>,
+    // Time the app was actually open for this session; older files only have start_time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_secs: Option<u64>,
+    pub total_cents: i64,
+    #[serde(default)]
+    pub net_cents: i64,
+    // Missing from older state files, which counted everything as settled
+    #[serde(default)]
+    pub settled_cents: Option<i64>,
+    // "provider:id" → created_at, as in App::seen_ids
+    #[serde(default, deserialize_with = "seen_ids_or_list")]
+    pub seen_ids: HashMap<String, chrono::DateTime<chrono::Utc>>,
+    // As in App::seen_floor; dedup for anything older relies on it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seen_floor: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(default)]
+    pub newest_seen: HashMap<String, chrono::DateTime<chrono::Utc>>,
+    #[serde(default)]
+    pub session_payments: Vec<Payment>,
+    #[serde(default)]
+    pub payment_count: usize,
+    #[serde(default)]
+    pub provider_totals: Vec<(String, i64, usize)>,
+    // The stack on screen, bottom first, and the bills still waiting to drop
+    #[serde(default)]
+    pub bills: Vec<BillAnimation>,
+    #[serde(default)]
+    pub pending_bills: Vec<PendingBill>,
+}
+
+// Older state files kept a bare list of IDs; without their age, keep them
+// until the poll windows have moved past the moment they were loaded
+fn seen_ids_or_list<'de, D>(deserializer: D) -> Result<HashMap<String, chrono::DateTime<chrono::Utc>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum SeenIds {
+        Dated(HashMap<String, chrono::DateTime<chrono::Utc>>),
+        Bare(Vec<String>),
+    }
+    Ok(match SeenIds::deserialize(deserializer)? {
+        SeenIds::Dated(ids) => ids,
+        SeenIds::Bare(ids) => {
+            let now = chrono::Utc::now();
+            ids.into_iter().map(|id| (id, now)).collect()
+        }
+    })
+}
+
+fn state_path() -> PathBuf {
+    profile_file("state")
+}
+
+pub fn load_state() -> Option<SessionState> {
+    let data = std::fs::read_to_string(state_path()).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+pub fn save_state(state: &SessionState) -> Result<()> {
+    let data = serde_json::to_string_pretty(state)?;
+    write_atomic(&state_path(), data.as_bytes())
+}
+
+pub fn journal_path() -> PathBuf {
+    profile_file("payments").with_extension("jsonl")
+}
+
+// Every payment counted this session, one JSON line each. The app only keeps the most
+// recent ones in memory, so exports read the full list back from here
+pub struct Journal {
+    path: PathBuf,
+    file: Option<std::fs::File>,
+    // Whether the lines already in the file belong to this session
+    resumed: bool,
+}
+
+impl Journal {
+    // A session that wasn't resumed starts the file over at its first payment
+    pub fn new(path: PathBuf, resumed: bool) -> Self {
+        Self { path, file: None, resumed }
+    }
+
+    pub fn append(&mut self, payment: &Payment) -> Result<()> {
+        let file = match self.file.as_mut() {
+            Some(file) => file,
+            None => {
+                let mut options = std::fs::OpenOptions::new();
+                options.create(true);
+                if self.resumed {
+                    options.append(true);
+                } else {
+                    options.write(true).truncate(true);
+                }
+                self.resumed = true;
+                self.file.insert(options.open(&self.path)?)
+            }
+        };
+        let mut line = serde_json::to_vec(payment)?;
+        line.push(b'\n');
+        file.write_all(&line)?;
+        Ok(())
+    }
+
+    // Lines that don't parse, e.g. one cut short by a crash, are skipped
+    pub fn load(&self) -> Result<Vec<Payment>> {
+        if !self.resumed {
+            return Ok(Vec::new());
+        }
+        let data = match std::fs::read_to_string(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(data.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+    }
+
+    // A new round starts the list over
+    pub fn restart(&mut self) {
+        self.file = None;
+        self.resumed = false;
+    }
+}
@@ -0,0 +1,88 @@
+use anyhow::Result;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::config::{profile_file, write_atomic};
+
+// Revenue across sessions, kept next to config.json
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Stats {
+    #[serde(default)]
+    pub all_time_cents: i64,
+    #[serde(default)]
+    pub payment_count: u64,
+    // Keyed by the local date the payment arrived on
+    #[serde(default)]
+    pub daily: BTreeMap<NaiveDate, i64>,
+}
+
+impl Stats {
+    pub fn record(&mut self, cents: i64, day: NaiveDate) {
+        self.all_time_cents = self.all_time_cents.saturating_add(cents);
+        self.payment_count += 1;
+        let daily = self.daily.entry(day).or_insert(0);
+        *daily = daily.saturating_add(cents);
+    }
+
+    pub fn day_total(&self, day: NaiveDate) -> i64 {
+        self.daily.get(&day).copied().unwrap_or(0)
+    }
+
+    // Oldest first, ending with `today`; days without payments are zero
+    pub fn last_days(&self, today: NaiveDate, days: u64) -> Vec<i64> {
+        (0..days)
+            .rev()
+            .map(|back| today - chrono::Days::new(back))
+            .map(|day| self.day_total(day))
+            .collect()
+    }
+}
+
+fn stats_path() -> PathBuf {
+    profile_file("stats")
+}
+
+// A missing or unreadable file starts the counters from zero
+pub fn load_stats() -> Stats {
+    std::fs::read_to_string(stats_path())
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_stats(stats: &Stats) -> Result<()> {
+    let data = serde_json::to_string_pretty(stats)?;
+    write_atomic(&stats_path(), data.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tz::DisplayTz;
+    use chrono::TimeZone;
+
+    fn day(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn payments_bucket_by_local_day() {
+        let (amsterdam, _) = DisplayTz::from_config(Some("Europe/Amsterdam"));
+        let (utc, _) = DisplayTz::from_config(Some("UTC"));
+        // Half past midnight in Amsterdam is still the day before in UTC
+        let late = chrono::Utc.with_ymd_and_hms(2026, 10, 15, 22, 30, 0).unwrap();
+        assert_eq!(amsterdam.date(late), day("2026-10-16"));
+        assert_eq!(utc.date(late), day("2026-10-15"));
+
+        let mut stats = Stats::default();
+        stats.record(1_000, amsterdam.date(late - chrono::Duration::hours(1)));
+        stats.record(250, amsterdam.date(late));
+        stats.record(500, amsterdam.date(late));
+        assert_eq!(stats.day_total(day("2026-10-15")), 1_000);
+        assert_eq!(stats.day_total(day("2026-10-16")), 750);
+        assert_eq!(stats.last_days(day("2026-10-17"), 4), [0, 1_000, 750, 0]);
+        assert_eq!((stats.all_time_cents, stats.payment_count), (1_750, 3));
+    }
+}
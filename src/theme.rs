@@ -0,0 +1,121 @@
+use ratatui::style::Color;
+
+// Colors the UI draws with, picked by the `theme` config field
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: &'static str,
+    // Borders, titles and good news
+    pub header: Color,
+    // Totals, prompts and falling bills
+    pub accent: Color,
+    pub text: Color,
+    pub dim: Color,
+    pub error: Color,
+    pub gauge_background: Color,
+    // Cycled by the celebration border and scattered as confetti
+    pub celebration: &'static [Color],
+    // Off for palettes that can't spare a color per provider
+    pub provider_colors: bool,
+}
+
+pub const THEMES: &[Theme] = &[
+    Theme {
+        name: "default",
+        header: Color::Green,
+        accent: Color::Yellow,
+        text: Color::White,
+        dim: Color::DarkGray,
+        error: Color::Red,
+        gauge_background: Color::Black,
+        celebration: &[Color::Green, Color::Yellow, Color::Cyan, Color::Magenta, Color::Red, Color::Blue],
+        provider_colors: true,
+    },
+    // The terminal's own colors only, so emphasis comes from bold alone
+    Theme {
+        name: "monochrome",
+        header: Color::Reset,
+        accent: Color::Reset,
+        text: Color::Reset,
+        dim: Color::Reset,
+        error: Color::Reset,
+        gauge_background: Color::Reset,
+        celebration: &[Color::Reset],
+        provider_colors: false,
+    },
+    // Avoids leaning on red against green, for colorblind users
+    Theme {
+        name: "high-contrast",
+        header: Color::LightBlue,
+        accent: Color::LightYellow,
+        text: Color::White,
+        dim: Color::Gray,
+        error: Color::LightMagenta,
+        gauge_background: Color::Black,
+        celebration: &[Color::LightYellow, Color::LightBlue, Color::White],
+        provider_colors: true,
+    },
+    Theme {
+        name: "cyberpunk",
+        header: Color::Rgb(255, 0, 170),
+        accent: Color::Rgb(0, 240, 255),
+        text: Color::Rgb(230, 230, 255),
+        dim: Color::Rgb(100, 90, 150),
+        error: Color::Rgb(255, 80, 80),
+        gauge_background: Color::Rgb(20, 0, 40),
+        celebration: &[Color::Rgb(255, 0, 170), Color::Rgb(0, 240, 255), Color::Rgb(250, 240, 0), Color::Rgb(160, 60, 255)],
+        provider_colors: true,
+    },
+];
+
+impl Theme {
+    // Unknown names fall back to the default palette
+    pub fn named(name: &str) -> &'static Theme {
+        THEMES.iter().find(|t| t.name.eq_ignore_ascii_case(name)).unwrap_or(&THEMES[0])
+    }
+
+    // Without color everything draws as monochrome, whatever the config says
+    pub fn resolve(name: &str, use_color: bool) -> &'static Theme {
+        if use_color {
+            Self::named(name)
+        } else {
+            Self::named("monochrome")
+        }
+    }
+
+    pub fn celebration_color(&self, tick: u32) -> Color {
+        self.celebration[tick as usize % self.celebration.len()]
+    }
+}
+
+// Off when NO_COLOR is set (https://no-color.org) or the terminal is "dumb"; checked once
+pub fn color_enabled() -> bool {
+    static USE_COLOR: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+    *USE_COLOR.get_or_init(|| {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        let dumb = std::env::var("TERM").is_ok_and(|t| t == "dumb");
+        !no_color && !dumb
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_theme_name_resolves_to_its_own_palette() {
+        for (i, theme) in THEMES.iter().enumerate() {
+            assert_eq!(Theme::named(theme.name), theme);
+            assert_eq!(Theme::named(&theme.name.to_uppercase()).name, theme.name);
+            for other in &THEMES[i + 1..] {
+                assert_ne!(theme, other);
+            }
+        }
+        assert!(["default", "monochrome", "high-contrast", "cyberpunk"].iter().all(|n| Theme::named(n).name == *n));
+    }
+
+    #[test]
+    fn unknown_theme_falls_back_to_default() {
+        assert_eq!(Theme::named("solarized").name, "default");
+        assert_eq!(Theme::named("").name, "default");
+    }
+}
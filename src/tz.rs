@@ -0,0 +1,81 @@
+use chrono::{DateTime, Local, NaiveDate, Timelike, Utc};
+use chrono_tz::Tz;
+
+// Zone used for every user-facing timestamp
+#[derive(Debug, Clone, Copy)]
+pub enum DisplayTz {
+    Local,
+    Named(Tz),
+}
+
+impl DisplayTz {
+    // Unset means system local time; an unknown name falls back to UTC with a warning
+    pub fn from_config(name: Option<&str>) -> (Self, Option<String>) {
+        match name {
+            None => (DisplayTz::Local, None),
+            Some(name) => match name.parse::<Tz>() {
+                Ok(tz) => (DisplayTz::Named(tz), None),
+                Err(_) => (
+                    DisplayTz::Named(Tz::UTC),
+                    Some(format!("unknown timezone '{}', showing times in UTC", name)),
+                ),
+            },
+        }
+    }
+
+    pub fn format(&self, at: DateTime<Utc>, fmt: &str) -> String {
+        match self {
+            DisplayTz::Local => at.with_timezone(&Local).format(fmt).to_string(),
+            DisplayTz::Named(tz) => at.with_timezone(tz).format(fmt).to_string(),
+        }
+    }
+
+    // Calendar day in this zone, so days roll over at local midnight
+    pub fn date(&self, at: DateTime<Utc>) -> NaiveDate {
+        match self {
+            DisplayTz::Local => at.with_timezone(&Local).date_naive(),
+            DisplayTz::Named(tz) => at.with_timezone(tz).date_naive(),
+        }
+    }
+
+    // Hour of the day in this zone, 0-23
+    pub fn hour(&self, at: DateTime<Utc>) -> u32 {
+        match self {
+            DisplayTz::Local => at.with_timezone(&Local).hour(),
+            DisplayTz::Named(tz) => at.with_timezone(tz).hour(),
+        }
+    }
+
+    pub fn rfc3339(&self, at: DateTime<Utc>) -> String {
+        match self {
+            DisplayTz::Local => at.with_timezone(&Local).to_rfc3339(),
+            DisplayTz::Named(tz) => at.with_timezone(tz).to_rfc3339(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_utc_instant_reads_in_each_configured_zone() {
+        let at: DateTime<Utc> = "2026-10-16T22:30:00Z".parse().unwrap();
+        let zone = |name| DisplayTz::from_config(Some(name)).0;
+
+        let amsterdam = zone("Europe/Amsterdam");
+        assert_eq!(amsterdam.format(at, "%Y-%m-%d %H:%M"), "2026-10-17 00:30");
+        assert_eq!((amsterdam.date(at), amsterdam.hour(at)), (NaiveDate::from_ymd_opt(2026, 10, 17).unwrap(), 0));
+        assert_eq!(amsterdam.rfc3339(at), "2026-10-17T00:30:00+02:00");
+
+        let new_york = zone("America/New_York");
+        assert_eq!(new_york.format(at, "%Y-%m-%d %H:%M"), "2026-10-16 18:30");
+        assert_eq!(zone("Asia/Kolkata").format(at, "%H:%M"), "04:00");
+
+        // An unknown zone shows UTC and says why
+        let (fallback, warning) = DisplayTz::from_config(Some("Mars/Olympus_Mons"));
+        assert_eq!(fallback.format(at, "%H:%M"), "22:30");
+        assert_eq!(warning.as_deref(), Some("unknown timezone 'Mars/Olympus_Mons', showing times in UTC"));
+        assert!(matches!(DisplayTz::from_config(None), (DisplayTz::Local, None)));
+    }
+}
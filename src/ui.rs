@@ -1,22 +1,96 @@
 use ratatui::{
-    layout::{Constraint, Direction, Layout, Rect},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Paragraph, Clear},
+    widgets::{Block, Borders, Clear, Gauge, List, ListItem, Paragraph, Sparkline, Wrap},
     Frame,
 };
 
 use crate::app::*;
+use crate::bigtext::{render_big_number, BIG_ROWS};
+use crate::config::{backup_path, config_path, secret_tail};
+use crate::ledger;
+use crate::milestones::Milestone;
+use crate::money::{abbreviate_money, format_money, with_symbol, MoneyFormat};
+use crate::psp::RateLimit;
+use crate::theme::{Theme, THEMES};
+use crate::vat::vat_component;
+use unicode_width::UnicodeWidthStr;
+
+// Below this the celebration box and bills no longer fit
+const MIN_WIDTH: u16 = 40;
+const MIN_HEIGHT: u16 = 15;
+
+// Largest payments listed in the stats overlay
+const TOP_PAYMENTS: usize = 3;
+
+// Narrower than this, the celebration's per-provider timelines say nothing
+const MIN_SPARK_WIDTH: usize = 8;
 
 pub fn draw(f: &mut Frame, app: &App) {
+    let theme = app.theme();
+    let area = f.area();
+    if app.phase != AppPhase::Setup && app.is_compact(area.height) {
+        draw_one_line(f, theme, app, area);
+        return;
+    }
+    if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+        draw_too_small(f, theme, area);
+        return;
+    }
     match app.phase {
-        AppPhase::Setup => draw_setup(f, app),
-        AppPhase::Running => draw_running(f, app),
-        AppPhase::Celebration => draw_celebration(f, app),
+        AppPhase::Setup => draw_setup(f, theme, app),
+        AppPhase::Running => draw_running(f, theme, app),
+        AppPhase::Celebration => draw_celebration(f, theme, app),
     }
 }
 
-fn draw_setup(f: &mut Frame, app: &App) {
+fn draw_too_small(f: &mut Frame, theme: &Theme, area: Rect) {
+    f.render_widget(Clear, area);
+    let msg = Paragraph::new(vec![
+        Line::from(Span::styled("Terminal too small", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD))),
+        Line::from(format!("please resize (min {}×{})", MIN_WIDTH, MIN_HEIGHT)),
+    ])
+    .alignment(Alignment::Center)
+    .wrap(Wrap { trim: true });
+    let top = area.height.saturating_sub(2) / 2;
+    f.render_widget(msg, Rect::new(area.x, area.y + top, area.width, area.height - top));
+}
+
+const SPINNER: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+// Total, payments, rate and session time on the top row; the celebration flashes it
+fn draw_one_line(f: &mut Frame, theme: &Theme, app: &App, area: Rect) {
+    f.render_widget(Clear, area);
+    let format = app.config.money_format();
+    let elapsed = app.session_elapsed().as_secs();
+    let lead = if app.paused {
+        "‖".to_string()
+    } else {
+        spinner(app.waiting_tick).to_string()
+    };
+    let mut spans = vec![
+        Span::styled(format!("{} ", lead), Style::default().fg(theme.dim)),
+        Span::styled(
+            format_money(app.displayed_total_cents.round() as i64, &format),
+            Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(format!(" │ {} payments", app.payment_count)),
+        Span::raw(format!(" │ {}/h", format_money(app.rate_per_hour(), &format))),
+        Span::raw(format!(" │ {}m {}s", elapsed / 60, elapsed % 60)),
+    ];
+    let mut style = Style::default().fg(theme.text);
+    if app.phase == AppPhase::Celebration {
+        spans.push(Span::raw(" │ Enter to continue"));
+        if (app.celebration_tick / 5).is_multiple_of(2) {
+            style = style.fg(theme.celebration_color(app.celebration_tick / 5)).add_modifier(Modifier::REVERSED);
+        }
+    }
+    let row = Rect::new(area.x, area.y, area.width, area.height.min(1));
+    f.render_widget(Paragraph::new(Line::from(spans)).style(style), row);
+}
+
+fn draw_setup(f: &mut Frame, theme: &Theme, app: &App) {
     let area = f.area();
     f.render_widget(Clear, area);
 
@@ -31,49 +105,98 @@ fn draw_setup(f: &mut Frame, app: &App) {
 
     // Title
     let title = Paragraph::new(Line::from(vec![
-        Span::styled("  profit-cli ", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+        Span::styled("  profit-cli ", Style::default().fg(theme.header).add_modifier(Modifier::BOLD)),
         Span::raw("— Setup"),
     ]))
-    .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(Color::Green)));
+    .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(theme.header)));
     f.render_widget(title, chunks[0]);
 
     match app.setup_step {
-        SetupStep::Currency => draw_currency_select(f, app, chunks[1]),
-        SetupStep::ProviderSelect => draw_provider_select(f, app, chunks[1]),
-        SetupStep::ProviderApiKey => draw_api_key_input(f, app, chunks[1]),
-        SetupStep::ProviderMerchantAccount => draw_merchant_input(f, app, chunks[1]),
-        SetupStep::Confirm => draw_confirm(f, app, chunks[1]),
+        SetupStep::Welcome => draw_welcome(f, theme, chunks[1]),
+        SetupStep::Currency => draw_currency_select(f, theme, app, chunks[1]),
+        SetupStep::CustomCurrency => draw_custom_currency_input(f, theme, app, chunks[1]),
+        SetupStep::Theme => draw_theme_select(f, theme, app, chunks[1]),
+        SetupStep::ProviderSelect => draw_provider_select(f, theme, app, chunks[1]),
+        SetupStep::ProviderLabel => draw_label_input(f, theme, app, chunks[1]),
+        SetupStep::ProviderApiKey => draw_api_key_input(f, theme, app, chunks[1]),
+        SetupStep::ProviderSecondCredential => draw_second_credential_input(f, theme, app, chunks[1]),
+        SetupStep::Confirm => draw_confirm(f, theme, app, chunks[1]),
     }
 
     // Help
     let help_text = match app.setup_step {
+        SetupStep::Welcome => "Enter continue  q quit",
         SetupStep::Currency => "↑↓ select  Enter confirm  q quit",
-        SetupStep::ProviderSelect => "↑↓ select  Space toggle  Enter continue  q quit",
-        SetupStep::ProviderApiKey | SetupStep::ProviderMerchantAccount => "Type API key  Enter confirm  Esc back",
-        SetupStep::Confirm => "Enter start  Esc back",
+        SetupStep::CustomCurrency => "Type CODE [SYMBOL] [DECIMALS] [prefix|suffix]  Enter confirm  Esc back  Ctrl-C quit",
+        SetupStep::Theme => "↑↓ select  Enter confirm  Esc back  q quit",
+        SetupStep::ProviderSelect => "↑↓ select  Space toggle  a add account  Enter continue  Esc back  q quit",
+        SetupStep::ProviderLabel => "Type a label  Enter add  Esc back  Ctrl-C quit",
+        SetupStep::ProviderApiKey | SetupStep::ProviderSecondCredential => {
+            "Type credential  Tab show/hide  Enter confirm  Esc back  Ctrl-C quit"
+        }
+        SetupStep::Confirm => "←→ poll interval  ↑↓ goal  Enter start  Esc back  q quit",
+    };
+    let help_text = if app.setup_step == SetupStep::Currency && !app.setup_history.is_empty() {
+        help_text.replace("q quit", "Esc back  q quit")
+    } else if app.edit_backup.is_some() && app.setup_step == SetupStep::Currency {
+        help_text.replace("q quit", "Esc cancel  q quit")
+    } else {
+        help_text.to_string()
     };
     let help = Paragraph::new(help_text)
-        .style(Style::default().fg(Color::DarkGray))
+        .style(Style::default().fg(theme.dim))
         .block(Block::default().borders(Borders::ALL));
     f.render_widget(help, chunks[2]);
 }
 
-fn draw_currency_select(f: &mut Frame, app: &App, area: Rect) {
+fn draw_welcome(f: &mut Frame, theme: &Theme, area: Rect) {
+    let lines = vec![
+        Line::from(Span::styled("Welcome to profit-cli!", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD))),
+        Line::from(""),
+        Line::from("Watch your revenue come in as it happens: profit-cli polls your payment"),
+        Line::from("providers and drops a bill on the stack for every payment, with a running total."),
+        Line::from(""),
+        Line::from("Next you'll pick a currency, a color theme and the providers to watch."),
+        Line::from(Span::styled(
+            "Try the Mock provider to see it in action without any API keys.",
+            Style::default().fg(theme.dim),
+        )),
+    ];
+    let p = Paragraph::new(lines).wrap(Wrap { trim: true }).block(Block::default().borders(Borders::ALL));
+    f.render_widget(p, area);
+}
+
+fn draw_currency_select(f: &mut Frame, theme: &Theme, app: &App, area: Rect) {
     let mut lines = vec![
-        Line::from(Span::styled("Select your currency:", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
+        Line::from(Span::styled("Select your currency:", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD))),
         Line::from(""),
     ];
 
-    for (i, (code, symbol)) in CURRENCIES.iter().enumerate() {
+    let options = app.currency_options();
+    let rows = options
+        .iter()
+        .map(|c| format!("{} ({})", c.code, c.symbol))
+        .chain(std::iter::once("Custom…".to_string()));
+    for (i, row) in rows.enumerate() {
         let marker = if i == app.setup_currency_idx { "▸ " } else { "  " };
         let style = if i == app.setup_currency_idx {
-            Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)
+            Style::default().fg(theme.header).add_modifier(Modifier::BOLD)
+        } else if i == options.len() {
+            Style::default().fg(theme.dim)
         } else {
-            Style::default().fg(Color::White)
+            Style::default().fg(theme.text)
         };
+        lines.push(Line::from(Span::styled(format!("{}{}", marker, row), style)));
+    }
+
+    if let Some(ref err) = app.config_load_error {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(err.clone(), Style::default().fg(theme.error))));
+        let backup = backup_path();
+        let name = backup.file_name().unwrap_or_default().to_string_lossy();
         lines.push(Line::from(Span::styled(
-            format!("{}{} ({})", marker, code, symbol),
-            style,
+            format!("Finishing setup moves it to {}; press q to quit and fix it by hand.", name),
+            Style::default().fg(theme.dim),
         )));
     }
 
@@ -81,9 +204,36 @@ fn draw_currency_select(f: &mut Frame, app: &App, area: Rect) {
     f.render_widget(p, area);
 }
 
-fn draw_provider_select(f: &mut Frame, app: &App, area: Rect) {
+// The screen is already drawn in the highlighted theme, so it doubles as the preview
+fn draw_theme_select(f: &mut Frame, theme: &Theme, app: &App, area: Rect) {
+    let selected = Theme::named(&app.config.theme).name;
+    let mut lines = vec![
+        Line::from(Span::styled("Select a color theme:", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD))),
+        Line::from(""),
+    ];
+    for t in THEMES {
+        let (marker, style) = if t.name == selected {
+            ("▸ ", Style::default().fg(theme.header).add_modifier(Modifier::BOLD))
+        } else {
+            ("  ", Style::default().fg(theme.text))
+        };
+        lines.push(Line::from(Span::styled(format!("{}{}", marker, t.name), style)));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::styled("■ total ", Style::default().fg(theme.accent)),
+        Span::styled("■ border ", Style::default().fg(theme.header)),
+        Span::styled("■ error ", Style::default().fg(theme.error)),
+        Span::styled("■ hint", Style::default().fg(theme.dim)),
+    ]));
+
+    let p = Paragraph::new(lines).block(Block::default().borders(Borders::ALL));
+    f.render_widget(p, area);
+}
+
+fn draw_provider_select(f: &mut Frame, theme: &Theme, app: &App, area: Rect) {
     let mut lines = vec![
-        Line::from(Span::styled("Select payment providers:", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
+        Line::from(Span::styled("Select payment providers:", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD))),
         Line::from(""),
     ];
 
@@ -91,12 +241,12 @@ fn draw_provider_select(f: &mut Frame, app: &App, area: Rect) {
         let marker = if i == app.setup_cursor { "▸ " } else { "  " };
         let check = if prov.enabled { "[✓]" } else { "[ ]" };
         let style = if i == app.setup_cursor {
-            Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)
+            Style::default().fg(theme.header).add_modifier(Modifier::BOLD)
         } else {
-            Style::default().fg(Color::White)
+            Style::default().fg(theme.text)
         };
         lines.push(Line::from(Span::styled(
-            format!("{}{} {}", marker, check, prov.name),
+            format!("{}{} {}", marker, check, prov.display_name()),
             style,
         )));
     }
@@ -105,140 +255,499 @@ fn draw_provider_select(f: &mut Frame, app: &App, area: Rect) {
     f.render_widget(p, area);
 }
 
-fn draw_api_key_input(f: &mut Frame, app: &App, area: Rect) {
+fn draw_api_key_input(f: &mut Frame, theme: &Theme, app: &App, area: Rect) {
     let prov = &app.provider_configs[app.current_provider_idx];
-    let masked: String = if app.setup_input.is_empty() {
-        String::new()
-    } else {
-        let len = app.setup_input.len();
-        if len <= 4 {
-            "*".repeat(len)
-        } else {
-            format!("{}{}",  "*".repeat(len - 4), &app.setup_input[len-4..])
-        }
-    };
+    let label = credential_prompts(&prov.name).first;
+    draw_credential_input(f, theme, app, area, &format!("Enter {} for {}:", label, prov.display_name()), &mask_secret(&app.setup_input, app.reveal_input));
+}
 
-    let lines = vec![
+fn draw_second_credential_input(f: &mut Frame, theme: &Theme, app: &App, area: Rect) {
+    let prov = &app.provider_configs[app.current_provider_idx];
+    let (label, masked) = credential_prompts(&prov.name).second.unwrap_or(("credential", false));
+    let shown = if masked { mask_secret(&app.setup_input, app.reveal_input) } else { app.setup_input.clone() };
+    draw_credential_input(f, theme, app, area, &format!("Enter {} for {}:", label, prov.display_name()), &shown);
+}
+
+fn draw_label_input(f: &mut Frame, theme: &Theme, app: &App, area: Rect) {
+    let prov = &app.provider_configs[app.setup_cursor];
+    let mut lines = vec![
         Line::from(Span::styled(
-            format!("Enter API key for {}:", prov.name),
-            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            format!("Label for another {} account (e.g. EU):", prov.name),
+            Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
         )),
         Line::from(""),
         Line::from(Span::styled(
-            format!("▸ {}_", masked),
-            Style::default().fg(Color::Green),
+            format!("▸ {}_", app.setup_input),
+            Style::default().fg(theme.header),
         )),
     ];
 
+    if let Some(ref err) = app.error_message {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(err.clone(), Style::default().fg(theme.error))));
+    }
+
     let p = Paragraph::new(lines).block(Block::default().borders(Borders::ALL));
     f.render_widget(p, area);
 }
 
-fn draw_merchant_input(f: &mut Frame, app: &App, area: Rect) {
-    let prov = &app.provider_configs[app.current_provider_idx];
-    let lines = vec![
+fn draw_custom_currency_input(f: &mut Frame, theme: &Theme, app: &App, area: Rect) {
+    let mut lines = vec![
         Line::from(Span::styled(
-            format!("Enter Merchant Account for {}:", prov.name),
-            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            "Custom currency (e.g. NOK kr 2 suffix):",
+            Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
         )),
         Line::from(""),
         Line::from(Span::styled(
             format!("▸ {}_", app.setup_input),
-            Style::default().fg(Color::Green),
+            Style::default().fg(theme.header),
+        )),
+    ];
+
+    if let Some(ref err) = app.error_message {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(err.clone(), Style::default().fg(theme.error))));
+    }
+
+    let p = Paragraph::new(lines).block(Block::default().borders(Borders::ALL));
+    f.render_widget(p, area);
+}
+
+fn draw_credential_input(f: &mut Frame, theme: &Theme, app: &App, area: Rect, prompt: &str, shown: &str) {
+    let mut lines = vec![
+        Line::from(Span::styled(
+            prompt.to_string(),
+            Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        Line::from(Span::styled(
+            format!("▸ {}_", shown),
+            Style::default().fg(theme.header),
         )),
     ];
 
+    if let Some(ref err) = app.error_message {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(err.clone(), Style::default().fg(theme.error))));
+    }
+
     let p = Paragraph::new(lines).block(Block::default().borders(Borders::ALL));
     f.render_widget(p, area);
 }
 
-fn draw_confirm(f: &mut Frame, app: &App, area: Rect) {
+// Everything but the last four characters hidden, unless revealed
+fn mask_secret(input: &str, reveal: bool) -> String {
+    let len = input.chars().count();
+    if reveal {
+        input.to_string()
+    } else if len <= 4 {
+        "*".repeat(len)
+    } else {
+        let tail: String = input.chars().skip(len - 4).collect();
+        format!("{}{}", "*".repeat(len - 4), tail)
+    }
+}
+
+// What was captured for a provider, to check nothing landed in the wrong field
+fn captured_credentials(p: &ProviderSetupState) -> String {
+    if p.name == "Mock" {
+        return "no credentials needed".to_string();
+    }
+    let mut parts = vec![format!("key {}", secret_tail(&p.api_key))];
+    if p.name == "Adyen" {
+        parts.push(format!("merchant {}", if p.second_credential.is_empty() { "(none)" } else { &p.second_credential }));
+    } else if SECRET_PROVIDERS.contains(&p.name.as_str()) {
+        parts.push(format!("secret {}", secret_tail(&p.second_credential)));
+    }
+    parts.join("  ")
+}
+
+fn draw_confirm(f: &mut Frame, theme: &Theme, app: &App, area: Rect) {
     let enabled: Vec<&ProviderSetupState> = app.provider_configs.iter().filter(|p| p.enabled).collect();
     let mut lines = vec![
-        Line::from(Span::styled("Ready to go!", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD))),
+        Line::from(Span::styled("Ready to go!", Style::default().fg(theme.header).add_modifier(Modifier::BOLD))),
         Line::from(""),
         Line::from(format!("Currency: {} ({})", app.config.currency, app.config.currency_symbol)),
-        Line::from(format!("Providers: {}", enabled.iter().map(|p| p.name.as_str()).collect::<Vec<_>>().join(", "))),
-        Line::from(""),
-        Line::from(Span::styled("Press Enter to start watching payments!", Style::default().fg(Color::Yellow))),
+        // How amounts will look, separators and symbol placement included
+        Line::from(Span::styled(
+            format!("  Amounts show as {}", format_money(123456, &app.config.money_format())),
+            Style::default().fg(theme.dim),
+        )),
+        Line::from("Providers:"),
     ];
+    for p in &enabled {
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {}", pad_right(&p.display_name(), 16)), Style::default().fg(provider_color(&p.name, theme))),
+            Span::styled(captured_credentials(p), Style::default().fg(theme.dim)),
+        ]));
+    }
+    lines.extend([
+        Line::from(format!("Poll every: {}s  (←→ to adjust)", app.config.poll_interval_secs)),
+        Line::from(match app.config.goal_cents {
+            Some(goal) => format!("Session goal: {}  (↑↓ to adjust)", format_money(goal, &app.config.money_format())),
+            None => "Session goal: none  (↑ to set)".to_string(),
+        }),
+        Line::from(Span::styled(
+            format!("Saved to: {}", config_path().display()),
+            Style::default().fg(theme.dim),
+        )),
+        Line::from(""),
+        Line::from(Span::styled("Press Enter to start watching payments!", Style::default().fg(theme.accent))),
+    ]);
+
+    if let Some(ref msg) = app.status_message {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(msg.clone(), Style::default().fg(theme.dim))));
+    }
 
     if let Some(ref err) = app.error_message {
         lines.push(Line::from(""));
-        lines.push(Line::from(Span::styled(err.clone(), Style::default().fg(Color::Red))));
+        lines.push(Line::from(Span::styled(err.clone(), Style::default().fg(theme.error))));
     }
 
     let p = Paragraph::new(lines).block(Block::default().borders(Borders::ALL));
     f.render_widget(p, area);
 }
 
-fn draw_running(f: &mut Frame, app: &App) {
+fn draw_running(f: &mut Frame, theme: &Theme, app: &App) {
     let area = f.area();
     f.render_widget(Clear, area);
 
+    let goal = app.config.goal_cents.filter(|g| *g > 0);
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(3),
+            Constraint::Length(if app.config.big_total { BIG_ROWS as u16 + 2 } else { 3 }),
+            Constraint::Length(if goal.is_some() { 3 } else { 0 }),
             Constraint::Min(5),
             Constraint::Length(3),
         ])
         .split(area);
 
     // Header with total
-    let dur = app.session_duration();
-    let minutes = dur.num_minutes();
-    let seconds = dur.num_seconds() % 60;
-    let total_display = format_money(app.total_cents, &app.config.currency_symbol);
+    let elapsed = app.session_elapsed().as_secs() as i64;
+    let minutes = elapsed / 60;
+    let seconds = elapsed % 60;
+    let total_display = format_money(app.displayed_total_cents.round() as i64, &app.config.money_format());
+
+    if app.config.big_total {
+        // Session details move into the border title
+        let title = format!(
+            " profit-cli │ {}m {}s │ {} payments │ {}/min ",
+            minutes,
+            seconds,
+            app.payment_count,
+            app.payments_per_minute()
+        );
+        let rows: Vec<Line> = render_big_number(&total_display)
+            .into_iter()
+            .map(|row| Line::from(Span::styled(row, Style::default().fg(theme.accent).add_modifier(Modifier::BOLD))))
+            .collect();
+        let header = Paragraph::new(rows)
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(theme.header)).title(title));
+        f.render_widget(header, chunks[0]);
+    } else {
+        draw_compact_header(f, theme, app, chunks[0], total_display, minutes, seconds);
+    }
+
+    // Progress toward the session goal
+    if let (Some(goal), Some(ratio)) = (goal, app.goal_progress()) {
+        let remaining = goal.saturating_sub(app.total_cents).max(0);
+        let label = format!(
+            "{:.0}% of {} · {} to go",
+            ratio * 100.0,
+            format_money(goal, &app.config.money_format()),
+            format_money(remaining, &app.config.money_format()),
+        );
+        let gauge = Gauge::default()
+            .block(Block::default().borders(Borders::ALL).title(" Goal "))
+            .gauge_style(Style::default().fg(theme.header).bg(theme.gauge_background))
+            .ratio(ratio)
+            .label(label);
+        f.render_widget(gauge, chunks[1]);
+    }
+
+    // Bill stacking area
+    draw_bills(f, theme, app, chunks[2]);
+
+    // Status bar with a color legend per provider
+    let dim = Style::default().fg(theme.dim);
+    let mut spans = vec![Span::raw(" ")];
+    let now = chrono::Utc::now();
+    for (i, status) in app.provider_statuses.iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::styled(" + ", dim));
+        }
+        if status.muted {
+            spans.push(Span::styled(
+                format!("■ {}", status.name),
+                Style::default().fg(theme.dim).add_modifier(Modifier::CROSSED_OUT),
+            ));
+            continue;
+        }
+        spans.push(Span::styled(
+            format!("■ {}", status.name),
+            Style::default().fg(provider_color(&status.name, theme)),
+        ));
+        let glyph = status.health_glyph(now, app.config.stale_after(&status.name));
+        let detail = match (&status.last_error, status.last_success) {
+            (Some(err), _) if status.consecutive_failures > 0 => {
+                format!(" {} {}", glyph, err.chars().take(20).collect::<String>())
+            }
+            (_, Some(at)) => format!(" {} {}", glyph, format_age(now - at)),
+            _ => format!(" {}", glyph),
+        };
+        let health_color = match glyph {
+            "✓" => theme.header,
+            "✗" => theme.error,
+            _ => theme.dim,
+        };
+        spans.push(Span::styled(detail, Style::default().fg(health_color)));
+    }
+    let pending = app.pending_bills.len();
+    if app.paused {
+        spans.push(Span::styled(" │ ⏸ PAUSED", Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)));
+        if !app.held_payments.is_empty() {
+            spans.push(Span::styled(format!(" ({} held)", app.held_payments.len()), dim));
+        }
+    } else if let Some((_, end)) = app.config.quiet_hours.filter(|_| app.config.is_quiet_at(app.tz, now)) {
+        spans.push(Span::styled(format!(" │ Closed — polling paused until {}:00", end), Style::default().fg(theme.accent)));
+        if pending > 0 {
+            spans.push(Span::styled(format!(" (+{} incoming)", pending), dim));
+        }
+    } else if pending > 0 {
+        spans.push(Span::styled(format!(" │ +{} incoming ", pending), dim));
+        spans.push(Span::styled(backlog_bar(pending, app.pending_peak), Style::default().fg(theme.accent)));
+    } else {
+        spans.push(Span::styled(" │ Watching for payments...", dim));
+    }
+    if let (Some(eta), Some(goal)) = (app.eta_to_goal(), goal) {
+        if app.total_cents < goal {
+            spans.push(Span::styled(
+                format!(" │ ~{} to {} at current rate", format_eta(eta), format_money(goal, &app.config.money_format())),
+                dim,
+            ));
+        }
+    }
+    if let Some(ref err) = app.error_message {
+        spans.push(Span::styled(format!(" │ {}", err), Style::default().fg(theme.error)));
+    } else if let Some(err) = app.recent_errors.back() {
+        let mut message: String = err.message.chars().take(60).collect();
+        if err.message.chars().count() > 60 {
+            message.push('…');
+        }
+        let count = if app.recent_errors.len() > 1 {
+            format!(" (+{} more, d to dismiss)", app.recent_errors.len() - 1)
+        } else {
+            " (d to dismiss)".to_string()
+        };
+        spans.push(Span::styled(
+            format!(" │ ⚠ {} {}: {}{}", app.tz.format(err.at, "%H:%M:%S"), err.provider, message, count),
+            Style::default().fg(theme.error),
+        ));
+    } else if let Some(ref msg) = app.status_message {
+        spans.push(Span::styled(format!(" │ {}", msg), Style::default().fg(theme.header)));
+    }
+    let status = Paragraph::new(Line::from(spans))
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(status, chunks[3]);
+
+    if app.show_ledger {
+        draw_ledger(f, theme, app, chunks[2]);
+    }
+    if app.show_stats {
+        draw_stats(f, theme, app, chunks[2]);
+    }
+    if !app.toasts.is_empty() && !app.show_ledger && !app.show_stats {
+        draw_toasts(f, theme, app, chunks[2]);
+    }
+}
 
+fn milestone_text(milestone: Milestone, money: &MoneyFormat) -> String {
+    match milestone {
+        Milestone::FirstPayment => "First payment of the session!".to_string(),
+        Milestone::Total(cents) => format!("{} reached!", format_money(cents, money)),
+        Milestone::Count(count) => format!("{} payments!", count),
+        Milestone::Largest(cents) => format!("New largest payment: {}", format_money(cents, money)),
+    }
+}
+
+// Stacked in the top right corner of the bill area, dimming as they run out
+fn draw_toasts(f: &mut Frame, theme: &Theme, app: &App, area: Rect) {
+    let money = app.config.money_format();
+    let texts: Vec<(String, u32)> = app.toasts.iter().map(|t| (milestone_text(t.milestone, &money), t.ticks_left)).collect();
+    let widest = texts.iter().map(|(text, _)| text_width(text)).max().unwrap_or(0);
+    let width = (widest as u16 + 4).min(area.width);
+    let height = (texts.len() as u16 + 2).min(area.height);
+    let toast_area = Rect::new(area.right().saturating_sub(width + 1).max(area.x), area.y + 1, width, height);
+    let lines: Vec<Line> = texts
+        .into_iter()
+        .map(|(text, ticks_left)| {
+            let style = if ticks_left <= TOAST_FADE_TICKS {
+                Style::default().fg(theme.dim)
+            } else {
+                Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)
+            };
+            Line::from(Span::styled(format!(" {}", text), style))
+        })
+        .collect();
+    f.render_widget(Clear, toast_area);
+    f.render_widget(
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(theme.header))),
+        toast_area,
+    );
+}
+
+fn draw_stats(f: &mut Frame, theme: &Theme, app: &App, area: Rect) {
+    let money = app.config.money_format();
+    let today = app.tz.date(chrono::Utc::now());
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.header))
+        .title(" Stats — s close ")
+        .title_bottom(format!(" Config: {} — o open ", config_path().display()));
+    let inner = block.inner(area);
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
+
+    let mut lines = vec![
+        Line::from(format!("  Today:        {}", format_money(app.stats.day_total(today), &money))),
+        Line::from(format!("  This session: {}{}", format_money(app.total_cents, &money), vat_note(app, app.total_cents))),
+        Line::from(format!("  All-time:     {}", format_money(app.stats.all_time_cents, &money))),
+        Line::from(format!("  Payments:     {}", app.stats.payment_count)),
+    ];
+    for (i, p) in app.top_payments(TOP_PAYMENTS).into_iter().enumerate() {
+        lines.push(Line::from(format!(
+            "  {:<14}{} ({}, {})",
+            if i == 0 { "Biggest:" } else { "" },
+            format_money(p.display_cents(), &money),
+            p.provider,
+            app.tz.format(p.created_at, "%H:%M"),
+        )));
+    }
+    let quotas = app.provider_statuses.iter().filter_map(|s| Some((&s.name, s.rate_limit?)));
+    for (i, (name, limit)) in quotas.enumerate() {
+        lines.push(Line::from(format!("  {:<14}{}: {}", if i == 0 { "Quota:" } else { "" }, name, quota_text(limit))));
+    }
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(lines.len() as u16 + 1), Constraint::Min(3)])
+        .split(inner);
+    f.render_widget(Paragraph::new(lines).style(Style::default().fg(theme.accent)), rows[0]);
+
+    let week: Vec<u64> = app.stats.last_days(today, 7).into_iter().map(|c| c.max(0) as u64).collect();
+    let sparkline = Sparkline::default()
+        .block(Block::default().borders(Borders::TOP).title(" Last 7 days "))
+        .data(&week)
+        .style(Style::default().fg(theme.header));
+    f.render_widget(sparkline, rows[1]);
+}
+
+fn draw_compact_header(f: &mut Frame, theme: &Theme, app: &App, area: Rect, total_display: String, minutes: i64, seconds: i64) {
     let header = Paragraph::new(Line::from(vec![
-        Span::styled("  profit-cli ", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+        Span::styled("  profit-cli ", Style::default().fg(theme.header).add_modifier(Modifier::BOLD)),
         Span::raw("│ "),
-        Span::styled(total_display, Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        Span::styled(total_display, Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)),
+        Span::styled(
+            if app.has_fees() {
+                format!(" gross / {} net", format_money(app.net_cents, &app.config.money_format()))
+            } else {
+                String::new()
+            },
+            Style::default().fg(theme.header),
+        ),
+        Span::styled(
+            if app.settled_cents != app.total_cents {
+                format!(" │ settled {}", format_money(app.settled_cents, &app.config.money_format()))
+            } else {
+                String::new()
+            },
+            Style::default().fg(theme.header),
+        ),
         Span::raw(format!(" │ {}m {}s", minutes, seconds)),
-        Span::raw(format!(" │ {} payments", app.session_payments.len())),
+        Span::raw(format!(" │ {} payments", app.payment_count)),
+        Span::styled(format!(" │ {}/min", app.payments_per_minute()), Style::default().fg(theme.dim)),
     ]))
-    .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(Color::Green)));
-    f.render_widget(header, chunks[0]);
+    .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(theme.header)));
+    f.render_widget(header, area);
+}
 
-    // Bill stacking area
-    draw_bills(f, app, chunks[1]);
+fn draw_ledger(f: &mut Frame, theme: &Theme, app: &App, area: Rect) {
+    let shown = ledger::filter_payments(&app.session_payments, &app.ledger_query);
+    let items: Vec<ListItem> = shown
+        .iter()
+        .rev()
+        .map(|p| {
+            let mut spans = vec![
+                Span::styled(app.tz.format(p.created_at, "%H:%M:%S "), Style::default().fg(theme.dim)),
+                Span::styled(pad_right(&p.provider, 8), Style::default().fg(provider_color(&p.provider, theme))),
+                Span::styled(
+                    pad_left(&format_money(p.display_cents(), &app.config.money_format()), 12),
+                    Style::default().fg(theme.accent),
+                ),
+            ];
+            if p.converted_cents.is_some() {
+                spans.push(Span::styled(
+                    format!(" ({} {:.2})", p.currency, p.amount_cents as f64 / 100.0),
+                    Style::default().fg(theme.dim),
+                ));
+            }
+            spans.push(Span::raw(format!("  {}", p.status)));
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
 
-    // Status bar
-    let providers: Vec<String> = app.config.providers.iter().map(|p| p.provider.clone()).collect();
-    let pending = app.pending_bills.len();
-    let status_text = if pending > 0 {
-        format!(" {} │ +{} incoming", providers.join(" + "), pending)
+    let count = if app.ledger_query.is_empty() {
+        format!("{}", shown.len())
     } else {
-        format!(" {} │ Watching for payments...", providers.join(" + "))
+        format!("{}/{}", shown.len(), app.session_payments.len())
     };
-    let status = Paragraph::new(status_text)
-        .style(Style::default().fg(Color::DarkGray))
-        .block(Block::default().borders(Borders::ALL));
-    f.render_widget(status, chunks[2]);
+    let filter = if app.ledger_filter_editing {
+        format!(" Filter: {}_ — provider, >50, <50, status:paid; Enter done, Esc clear ", app.ledger_query)
+    } else if app.ledger_query.is_empty() {
+        " / filter ".to_string()
+    } else {
+        format!(" Filter: {} — / edit ", app.ledger_query)
+    };
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.header))
+                .title(format!(" Payments ({}) — ↑↓ PgUp PgDn scroll, l close ", count))
+                .title_bottom(filter),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    let mut state = app.payment_list_scroll.clone();
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
 }
 
-fn draw_bills(f: &mut Frame, app: &App, area: Rect) {
+fn draw_bills(f: &mut Frame, theme: &Theme, app: &App, area: Rect) {
     let block = Block::default()
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::DarkGray));
+        .border_style(Style::default().fg(theme.dim));
     let inner = block.inner(area);
     f.render_widget(block, area);
 
     if app.bills.is_empty() && app.pending_bills.is_empty() {
+        let checked = match app.last_checked() {
+            Some(at) => format!(" · last checked {}s ago", (chrono::Utc::now() - at).num_seconds().max(0)),
+            None => String::new(),
+        };
         let waiting = Paragraph::new(Line::from(vec![
-            Span::styled("  Waiting for payments", Style::default().fg(Color::DarkGray)),
-            Span::styled(
-                dots_animation(app.celebration_tick),
-                Style::default().fg(Color::DarkGray),
-            ),
+            Span::styled(format!("{} ", spinner(app.waiting_tick)), Style::default().fg(theme.accent)),
+            Span::styled(format!("Waiting for payments{}", checked), Style::default().fg(theme.dim)),
         ]));
-        let centered = centered_rect(40, 3, inner);
+        let centered = centered_rect(48, 3, inner);
         f.render_widget(waiting, centered);
         return;
     }
 
-    let sym = &app.config.currency_symbol;
+    let money = app.config.money_format();
     for bill in &app.bills {
         let y = bill.y_pos as u16;
         if y >= inner.height || y < inner.y {
@@ -250,19 +759,36 @@ fn draw_bills(f: &mut Frame, app: &App, area: Rect) {
             continue;
         }
 
-        let glow = if !bill.settled { Color::Yellow } else if bill.age_ticks < 10 { Color::Green } else { Color::DarkGray };
-        let bill_style = Style::default().fg(glow);
+        // Bills that just landed flash bold; authorised-only ones stay dim
+        let landing = bill.just_settled_ticks > 0;
+        let bill_style = if landing {
+            Style::default().fg(bill_color(bill, theme)).add_modifier(Modifier::BOLD)
+        } else if bill.unsettled {
+            Style::default().fg(bill_color(bill, theme)).add_modifier(Modifier::DIM)
+        } else {
+            Style::default().fg(bill_color(bill, theme))
+        };
 
         let bill_width = 22u16.min(inner.width);
         let x = inner.x + (inner.width.saturating_sub(bill_width)) / 2;
 
         let bill_area = Rect::new(x, bill_y, bill_width, 2);
 
-        let top = format!("┌{}┐", "─".repeat((bill_width - 2) as usize));
-        let mid = format!("│{} {}1{} │",
-            " ".repeat(1),
-            sym,
-            " ".repeat((bill_width as usize).saturating_sub(sym.len() + 6)),
+        let (left, edge, right, side) = if landing {
+            ("┏", "━", "┓", "┃")
+        } else if bill.unsettled {
+            ("┌", "╌", "┐", "╎")
+        } else {
+            ("┌", "─", "┐", "│")
+        };
+        let top = format!("{left}{}{right}", edge.repeat((bill_width - 2) as usize));
+        let mut label = with_symbol((bill.amount_cents / 100).to_string(), &money);
+        if bill.count > 1 {
+            label.push_str(&format!(" ×{}", bill.count));
+        }
+        let mid = format!("{side}  {}{} {side}",
+            label,
+            " ".repeat((bill_width as usize).saturating_sub(text_width(&label) + 5)),
         );
 
         let bill_text = Paragraph::new(vec![
@@ -276,20 +802,13 @@ fn draw_bills(f: &mut Frame, app: &App, area: Rect) {
     }
 }
 
-fn draw_celebration(f: &mut Frame, app: &App) {
+fn draw_celebration(f: &mut Frame, theme: &Theme, app: &App) {
     let area = f.area();
     f.render_widget(Clear, area);
 
     let tick = app.celebration_tick;
     let sparkle = if tick % 4 < 2 { "✨" } else { "🎉" };
-    let border_color = match tick % 6 {
-        0 => Color::Green,
-        1 => Color::Yellow,
-        2 => Color::Cyan,
-        3 => Color::Magenta,
-        4 => Color::Red,
-        _ => Color::Blue,
-    };
+    let border_color = theme.celebration_color(tick);
 
     let chunks = Layout::default()
         .direction(Direction::Vertical)
@@ -300,99 +819,635 @@ fn draw_celebration(f: &mut Frame, app: &App) {
         ])
         .split(area);
 
-    let dur = app.session_duration();
-    let total = format_money(app.total_cents, &app.config.currency_symbol);
-    let avg = if !app.session_payments.is_empty() {
-        format_money(app.total_cents / app.session_payments.len() as i64, &app.config.currency_symbol)
-    } else {
-        format_money(0, &app.config.currency_symbol)
+    // Full amounts unless they'd push the box past the screen edge
+    let room = (chunks[1].width as usize).saturating_sub(4);
+    let mut rows = celebration_rows(app, theme, false);
+    if box_width(&rows) > room {
+        rows = celebration_rows(app, theme, true);
+    }
+    let inner = box_width(&rows) - 2;
+    let rule = |left: &str, right: &str| {
+        Line::from(Span::styled(format!("  {}{}{}", left, "═".repeat(inner), right), Style::default().fg(theme.accent)))
     };
 
-    let celebration_art = vec![
+    let mut celebration_art = vec![
         Line::from(""),
         Line::from(Span::styled(
             format!("  {} SCREEN FULL! {} ", sparkle, sparkle),
             Style::default().fg(border_color).add_modifier(Modifier::BOLD),
         )),
         Line::from(""),
-        Line::from(Span::styled(
-            "  ╔══════════════════════════════╗",
-            Style::default().fg(Color::Yellow),
-        )),
-        Line::from(Span::styled(
-            format!("  ║   Total: {:>17}  ║", total),
-            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
-        )),
-        Line::from(Span::styled(
-            "  ╠══════════════════════════════╣",
-            Style::default().fg(Color::Yellow),
-        )),
-        Line::from(Span::styled(
-            format!("  ║   Payments: {:>14}  ║", app.session_payments.len()),
-            Style::default().fg(Color::Green),
-        )),
-        Line::from(Span::styled(
-            format!("  ║   Average: {:>15}  ║", avg),
-            Style::default().fg(Color::Green),
-        )),
-        Line::from(Span::styled(
-            format!("  ║   Duration: {:>11}m {:>2}s  ║", dur.num_minutes(), dur.num_seconds() % 60),
-            Style::default().fg(Color::Green),
-        )),
-        Line::from(Span::styled(
-            format!("  ║   Rate: {:>13}/min  ║",
-                if dur.num_minutes() > 0 {
-                    format_money(app.total_cents / dur.num_minutes(), &app.config.currency_symbol)
-                } else {
-                    total.clone()
-                }),
-            Style::default().fg(Color::Green),
-        )),
-        Line::from(Span::styled(
-            "  ╚══════════════════════════════╝",
-            Style::default().fg(Color::Yellow),
-        )),
+        rule("╔", "╗"),
+    ];
+    for row in &rows {
+        celebration_art.push(match row {
+            BoxRow::Rule => rule("╠", "╣"),
+            BoxRow::Stat(label, value, style) => {
+                let gap = inner.saturating_sub(text_width(label) + text_width(value) + 5);
+                Line::from(Span::styled(format!("  ║   {}{}{}  ║", label, " ".repeat(gap), value), *style))
+            }
+        });
+    }
+    celebration_art.push(rule("╚", "╝"));
+
+    // When each provider's money came in, one row apiece under the box
+    let label_width = app.session_breakdown().iter().map(|(name, _, _)| text_width(name)).max().unwrap_or(0);
+    let spark_width = (inner + 2).saturating_sub(label_width + 1);
+    let spark_row = celebration_art.len();
+    let timelines = if spark_width >= MIN_SPARK_WIDTH { app.provider_timelines(spark_width) } else { Vec::new() };
+    for (name, _) in &timelines {
+        let pad = " ".repeat(label_width - text_width(name));
+        celebration_art.push(Line::from(Span::styled(
+            format!("  {}{} ", name, pad),
+            Style::default().fg(provider_color(name, theme)),
+        )));
+    }
+
+    celebration_art.extend([
         Line::from(""),
         Line::from(Span::styled(
-            "  Resetting in a moment...",
-            Style::default().fg(Color::DarkGray),
+            match app.celebration_ticks_left() {
+                Some(ticks) => format!(
+                    "  Resetting in {}s — Enter to continue now",
+                    (TICK * ticks).as_secs_f64().ceil() as u64
+                ),
+                None => "  Press Enter to continue".to_string(),
+            },
+            Style::default().fg(theme.dim),
         )),
-    ];
+    ]);
+    if let Some(ref msg) = app.status_message {
+        celebration_art.push(Line::from(Span::styled(format!("  {}", msg), Style::default().fg(theme.header))));
+    }
+
+    // Confetti goes down first; the stats box clears its own area on top
+    draw_particles(f, app, area);
+    f.render_widget(Clear, chunks[1]);
 
     let p = Paragraph::new(celebration_art)
         .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(border_color)));
     f.render_widget(p, chunks[1]);
+
+    let inside = chunks[1].inner(ratatui::layout::Margin::new(1, 1));
+    for (i, (name, bins)) in timelines.iter().enumerate() {
+        let y = inside.y + (spark_row + i) as u16;
+        if y >= inside.y + inside.height {
+            break;
+        }
+        let x = inside.x + 2 + label_width as u16 + 1;
+        let width = (spark_width as u16).min((inside.x + inside.width).saturating_sub(x));
+        let sparkline = Sparkline::default().data(bins).style(Style::default().fg(provider_color(name, theme)));
+        f.render_widget(sparkline, Rect::new(x, y, width, 1));
+    }
 }
 
-fn format_money(cents: i64, symbol: &str) -> String {
-    let whole = cents / 100;
-    let frac = (cents % 100).abs();
-    // Add thousand separators
-    let whole_str = {
-        let s = whole.to_string();
-        let mut result = String::new();
-        for (i, c) in s.chars().rev().enumerate() {
-            if i > 0 && i % 3 == 0 && c != '-' {
-                result.push(',');
-            }
-            result.push(c);
+// A line of the celebration box: a divider, or a label with its right-aligned value
+enum BoxRow {
+    Rule,
+    Stat(String, String, Style),
+}
+
+// Terminal columns, so wide glyphs (¥, CJK) count double and combining marks not at all
+fn text_width(text: &str) -> usize {
+    UnicodeWidthStr::width(text)
+}
+
+// `text` padded with spaces to `width` columns, on the right or (for amounts) the left
+fn pad_right(text: &str, width: usize) -> String {
+    format!("{}{}", text, " ".repeat(width.saturating_sub(text_width(text))))
+}
+
+fn pad_left(text: &str, width: usize) -> String {
+    format!("{}{}", " ".repeat(width.saturating_sub(text_width(text))), text)
+}
+
+// Outer width of the box that fits every row, borders included; never narrower than the classic 32
+fn box_width(rows: &[BoxRow]) -> usize {
+    let widest = rows
+        .iter()
+        .map(|row| match row {
+            BoxRow::Rule => 0,
+            BoxRow::Stat(label, value, _) => text_width(label) + 1 + text_width(value),
+        })
+        .max()
+        .unwrap_or(0);
+    (widest + 7).max(32)
+}
+
+fn celebration_rows(app: &App, theme: &Theme, abbreviate: bool) -> Vec<BoxRow> {
+    let format = app.config.money_format();
+    let money = |cents: i64| if abbreviate { abbreviate_money(cents, &format) } else { format_money(cents, &format) };
+    let stat = |label: &str, value: String, style: Style| BoxRow::Stat(label.to_string(), value, style);
+    let accent = Style::default().fg(theme.accent);
+    let header = Style::default().fg(theme.header);
+
+    let elapsed = app.session_elapsed().as_secs() as i64;
+    let minutes = elapsed / 60;
+    let avg = if app.payment_count > 0 { app.total_cents / app.payment_count as i64 } else { 0 };
+
+    let mut rows = vec![stat("Total:", money(app.total_cents), accent.add_modifier(Modifier::BOLD))];
+    if app.has_fees() {
+        rows.push(stat("Net:", money(app.net_cents), accent));
+    }
+    if let Some(bps) = app.config.vat_rate_bps {
+        rows.push(stat("incl. VAT:", money(vat_component(app.total_cents, bps)), accent));
+    }
+    rows.extend([
+        BoxRow::Rule,
+        stat("Payments:", app.payment_count.to_string(), header),
+        stat("Average:", money(avg), header),
+        stat("Duration:", format!("{}m {:>2}s", minutes, elapsed % 60), header),
+        stat(
+            "Rate:",
+            format!("{}/min", money(if minutes > 0 { app.rate_per_minute() } else { app.total_cents })),
+            header,
+        ),
+        stat("Rate:", format!("{}/h", money(app.rate_per_hour())), header),
+    ]);
+
+    // One row per provider that contributed this session
+    let breakdown = app.session_breakdown();
+    if !breakdown.is_empty() {
+        rows.push(BoxRow::Rule);
+        for (name, cents, count) in breakdown {
+            let style = Style::default().fg(provider_color(&name, theme));
+            rows.push(stat(&format!("{}:", name), format!("{} ({})", money(cents), count), style));
         }
-        result.chars().rev().collect::<String>()
+    }
+    rows
+}
+
+fn draw_particles(f: &mut Frame, app: &App, area: Rect) {
+    let buf = f.buffer_mut();
+    for p in &app.particles {
+        if p.y < 0.0 {
+            continue;
+        }
+        let x = area.x + (p.x * area.width as f64) as u16;
+        let y = area.y + (p.y * area.height as f64) as u16;
+        if let Some(cell) = buf.cell_mut((x, y)) {
+            cell.set_symbol(p.glyph).set_fg(p.color);
+        }
+    }
+}
+
+// Settled bills hold their provider color for a while, then fade toward dim
+const FADE_START_TICKS: u32 = 200;
+const FADE_END_TICKS: u32 = 1_200;
+
+// Falling bills glow in the accent color; settled ones show their age
+fn bill_color(bill: &BillAnimation, theme: &Theme) -> Color {
+    if !bill.settled {
+        return theme.accent;
+    }
+    let from = provider_color(&bill.provider, theme);
+    let fade = bill.age_ticks.saturating_sub(FADE_START_TICKS) as f64 / (FADE_END_TICKS - FADE_START_TICKS) as f64;
+    if fade <= 0.0 {
+        return from;
+    }
+    match (rgb(from), rgb(theme.dim)) {
+        (Some(a), Some(b)) => {
+            let t = fade.min(1.0);
+            let mix = |x: u8, y: u8| (x as f64 + (y as f64 - x as f64) * t).round() as u8;
+            Color::Rgb(mix(a.0, b.0), mix(a.1, b.1), mix(a.2, b.2))
+        }
+        // Colors we can't blend switch over once fully faded
+        _ if fade >= 1.0 => theme.dim,
+        _ => from,
+    }
+}
+
+// Approximate RGB for the named terminal colors, for blending
+fn rgb(color: Color) -> Option<(u8, u8, u8)> {
+    Some(match color {
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Black => (0, 0, 0),
+        Color::Red => (205, 49, 49),
+        Color::Green => (13, 188, 121),
+        Color::Yellow => (229, 229, 16),
+        Color::Blue => (36, 114, 200),
+        Color::Magenta => (188, 63, 188),
+        Color::Cyan => (17, 168, 205),
+        Color::Gray => (204, 204, 204),
+        Color::DarkGray => (102, 102, 102),
+        Color::LightRed => (241, 76, 76),
+        Color::LightGreen => (35, 209, 139),
+        Color::LightYellow => (245, 245, 67),
+        Color::LightBlue => (59, 142, 234),
+        Color::LightMagenta => (214, 112, 214),
+        Color::LightCyan => (41, 184, 219),
+        Color::White => (229, 229, 229),
+        _ => return None,
+    })
+}
+
+pub fn provider_color(name: &str, theme: &Theme) -> Color {
+    if !theme.provider_colors {
+        return theme.text;
+    }
+    // Labelled accounts ("Adyen (EU)") share their provider's color
+    let base = name.split(" (").next().unwrap_or(name);
+    match base {
+        "Adyen" => Color::Green,
+        "PayPal" => Color::LightBlue,
+        "Braintree" => Color::LightCyan,
+        "GoCardless" => Color::LightMagenta,
+        "Coinbase" => Color::Blue,
+        "Mock" => Color::Gray,
+        // Unknown providers get a stable pick from a fixed palette
+        _ => {
+            const PALETTE: &[Color] = &[Color::Magenta, Color::Blue, Color::LightRed, Color::LightYellow];
+            let hash = name.bytes().fold(0usize, |acc, b| acc.wrapping_mul(31).wrapping_add(b as usize));
+            PALETTE[hash % PALETTE.len()]
+        }
+    }
+}
+
+// "240/300 remaining", with whatever parts the provider sent
+fn quota_text(limit: RateLimit) -> String {
+    let mut text = match (limit.remaining, limit.limit) {
+        (Some(remaining), Some(limit)) => format!("{}/{} remaining", remaining, limit),
+        (Some(remaining), None) => format!("{} remaining", remaining),
+        (None, Some(limit)) => format!("limit {}", limit),
+        (None, None) => "rate limited".to_string(),
     };
-    format!("{}{}.{:02}", symbol, whole_str, frac)
+    if let Some(wait) = limit.retry_after {
+        text.push_str(&format!(", retry after {}s", wait.as_secs()));
+    }
+    text
 }
 
-fn dots_animation(tick: u32) -> String {
-    match tick % 4 {
-        0 => "".to_string(),
-        1 => ".".to_string(),
-        2 => "..".to_string(),
-        _ => "...".to_string(),
+fn format_eta(eta: chrono::Duration) -> String {
+    let minutes = eta.num_minutes();
+    if minutes >= 60 {
+        format!("{}h {}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{}m", minutes.max(1))
     }
 }
 
+fn format_age(age: chrono::Duration) -> String {
+    let secs = age.num_seconds().max(0);
+    if secs < 60 {
+        format!("{}s ago", secs)
+    } else if secs < 3600 {
+        format!("{}m ago", secs / 60)
+    } else {
+        format!("{}h ago", secs / 3600)
+    }
+}
+
+// " (incl. €1.23 VAT)" when a VAT rate is configured
+fn vat_note(app: &App, gross_cents: i64) -> String {
+    match app.config.vat_rate_bps {
+        Some(bps) => format!(" (incl. {} VAT)", format_money(vat_component(gross_cents, bps), &app.config.money_format())),
+        None => String::new(),
+    }
+}
+
+const BACKLOG_BAR_WIDTH: usize = 10;
+
+// How much of the burst is still queued, e.g. "████░░░░░░"
+fn backlog_bar(pending: usize, peak: usize) -> String {
+    let filled = (pending * BACKLOG_BAR_WIDTH).div_ceil(peak.max(pending).max(1));
+    format!("{}{}", "█".repeat(filled), "░".repeat(BACKLOG_BAR_WIDTH - filled))
+}
+
+// One frame per two ticks
+fn spinner(tick: u32) -> char {
+    SPINNER[(tick / 2) as usize % SPINNER.len()]
+}
+
 fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
     let x = area.x + (area.width.saturating_sub(width)) / 2;
     let y = area.y + (area.height.saturating_sub(height)) / 2;
     Rect::new(x, y, width.min(area.width), height.min(area.height))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::psp::ScriptedProvider;
+    use ratatui::backend::TestBackend;
+    use ratatui::buffer::Buffer;
+    use ratatui::Terminal;
+
+    fn render(app: &App, width: u16, height: u16) -> Buffer {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|f| draw(f, app)).unwrap();
+        terminal.backend().buffer().clone()
+    }
+
+    // A session with bills in flight and landed, a healthy and a failing provider, and an error
+    fn running_app(theme: &str) -> App {
+        crate::config::use_temp_config_dir();
+        let mut app = App::new();
+        app.config.theme = theme.to_string();
+        app.phase = AppPhase::Running;
+        app.record_poll_success("Adyen");
+        app.record_poll_failure("PayPal", "401 Unauthorized");
+        app.push_error("PayPal".to_string(), "401 Unauthorized".to_string());
+        let now = chrono::Utc::now();
+        for (i, provider) in ["Adyen", "PayPal", "Adyen"].into_iter().enumerate() {
+            app.add_payment(ScriptedProvider::payment(provider, &format!("p{}", i), 1_500, now));
+        }
+        for tick in 0..40 {
+            if tick % 3 == 0 {
+                app.spawn_next_bill(app.stack_height(30));
+            }
+            app.tick_animations();
+        }
+        app
+    }
+
+    #[test]
+    fn without_color_nothing_is_drawn_in_a_color() {
+        assert_eq!(Theme::resolve("cyberpunk", false).name, "monochrome");
+        let mut app = running_app("monochrome");
+        let mut screens = vec![render(&app, 100, 30)];
+        app.start_celebration();
+        app.celebration_tick = 7;
+        screens.push(render(&app, 100, 30));
+        for screen in screens {
+            let text: String = screen.content().iter().map(|c| c.symbol()).collect();
+            assert!(text.contains("€45.00"), "nothing drawn: {}", text.trim());
+            for cell in screen.content() {
+                assert_eq!(cell.fg, Color::Reset, "{:?} is colored", cell.symbol());
+                assert_eq!(cell.bg, Color::Reset, "{:?} has a background", cell.symbol());
+            }
+        }
+    }
+
+    fn screen_text(screen: &Buffer) -> Vec<String> {
+        let width = screen.area.width as usize;
+        let symbols: Vec<&str> = screen.content().iter().map(|c| c.symbol()).collect();
+        symbols.chunks(width).map(|row| row.concat()).collect()
+    }
+
+    #[test]
+    fn backlog_bar_shows_how_much_of_the_burst_is_left() {
+        let mut app = running_app("default");
+        let now = chrono::Utc::now();
+        while app.pending_bills.len() < 10 {
+            let n = app.payment_count;
+            app.add_payment(ScriptedProvider::payment("Mock", &format!("burst{}", n), 100, now));
+        }
+        let peak = app.pending_peak;
+        assert!(peak >= 10);
+        while app.pending_bills.len() * 10 > peak * 4 {
+            app.spawn_next_bill(app.stack_height(30));
+        }
+        let pending = app.pending_bills.len();
+        let bar = backlog_bar(pending, peak);
+        let filled = (pending * BACKLOG_BAR_WIDTH).div_ceil(peak);
+        assert_eq!(bar, format!("{}{}", "█".repeat(filled), "░".repeat(BACKLOG_BAR_WIDTH - filled)));
+        assert!(filled <= 4 && filled > 0);
+
+        let rows = screen_text(&render(&app, 140, 30));
+        let status = rows.iter().find(|r| r.contains("incoming")).expect("no backlog in the status bar");
+        assert!(status.contains(&format!("+{} incoming {}", pending, bar)), "{}", status);
+
+        assert_eq!(backlog_bar(10, 10), "█".repeat(10));
+        assert_eq!(backlog_bar(1, 3), "████░░░░░░");
+        // A queue that outgrew the peak it was measured against fills the bar, no more
+        assert_eq!(backlog_bar(12, 10), "█".repeat(10));
+    }
+
+    fn bill(provider: &str, settled: bool, age_ticks: u32) -> BillAnimation {
+        BillAnimation {
+            amount_cents: 2_000,
+            y_pos: 0.0,
+            target_y: 0.0,
+            settled,
+            age_ticks,
+            provider: provider.to_string(),
+            count: 1,
+            unsettled: false,
+            just_settled_ticks: 0,
+        }
+    }
+
+    #[test]
+    fn settled_bills_fade_toward_dim_with_age() {
+        let theme = Theme::named("cyberpunk");
+        assert_eq!(bill_color(&bill("PayPal", false, 5_000), theme), theme.accent);
+        assert_eq!(bill_color(&bill("PayPal", true, 0), theme), Color::LightBlue);
+        assert_eq!(bill_color(&bill("PayPal", true, FADE_START_TICKS), theme), Color::LightBlue);
+        assert_eq!(bill_color(&bill("PayPal", true, 700), theme), Color::Rgb(80, 116, 192));
+        assert_eq!(bill_color(&bill("PayPal", true, FADE_END_TICKS), theme), theme.dim);
+        assert_eq!(bill_color(&bill("PayPal", true, 50_000), theme), theme.dim);
+
+        // Named colors blend through their RGB approximation
+        let default = Theme::named("default");
+        assert_eq!(bill_color(&bill("Adyen", true, 0), default), Color::Green);
+        assert!(matches!(bill_color(&bill("Adyen", true, 450), default), Color::Rgb(..)));
+        assert_eq!(bill_color(&bill("Adyen", true, FADE_END_TICKS), default), Color::Rgb(102, 102, 102));
+
+        // Without colors to blend nothing changes until the fade is over
+        let mono = Theme::named("monochrome");
+        for age in [0, 700, FADE_END_TICKS, 50_000] {
+            assert_eq!(bill_color(&bill("PayPal", true, age), mono), Color::Reset);
+        }
+    }
+
+    #[test]
+    fn secrets_show_only_their_tail_unless_revealed() {
+        assert_eq!(mask_secret("sk_live_123456", false), "**********3456");
+        assert_eq!(mask_secret("sk_live_123456", true), "sk_live_123456");
+        // Short inputs give nothing away, and multi-byte characters count as one
+        assert_eq!(mask_secret("abcd", false), "****");
+        assert_eq!(mask_secret("", false), "");
+        assert_eq!(mask_secret("ключ-1234", false), "*****1234");
+        assert_eq!(mask_secret("ключ", true), "ключ");
+    }
+
+    #[test]
+    fn a_one_row_pane_gets_a_single_summary_line() {
+        let mut app = running_app("default");
+        let screen = render(&app, 120, 1);
+        let line = &screen_text(&screen)[0];
+        assert!(line.contains("€45.00 │ 3 payments │ "), "{}", line);
+        assert!(line.contains("/h │ 0m "), "{}", line);
+
+        app.start_celebration();
+        assert!(screen_text(&render(&app, 120, 1))[0].contains("Enter to continue"));
+        // The layout math holds up in panes too small for the whole line
+        for (width, height) in [(1, 1), (10, 1), (120, 4), (40, 2)] {
+            render(&app, width, height);
+        }
+    }
+
+    fn confirm_screen() -> Vec<String> {
+        crate::config::use_temp_config_dir();
+        let mut app = App::new();
+        app.setup_step = SetupStep::Confirm;
+        for p in &mut app.provider_configs {
+            match p.name.as_str() {
+                "Adyen" => {
+                    p.enabled = true;
+                    p.api_key = "AQEyhmfxLI3MaBFLw0m/n3Q5qf3VaY9UCJ14XWZE03G7k".to_string();
+                    p.second_credential = "AcmeCorpECOM".to_string();
+                }
+                "PayPal" => {
+                    p.enabled = true;
+                    p.api_key = "AZDxjDScFpQtjWTOUtWKbyN_bDt4OgqaF4eYXlewfBP4".to_string();
+                    p.second_credential = "EGnHDxD_qRPdaLdZz8iCr8N7_MzF-YHPTkjs6NKYQvQSBngp4PTTVWkPZRbL".to_string();
+                }
+                _ => {}
+            }
+        }
+        screen_text(&render(&app, 100, 30))
+    }
+
+    #[test]
+    fn confirm_shows_masked_key_tails_and_the_merchant_account() {
+        let screen = confirm_screen();
+        let row = |name: &str| screen.iter().find(|l| l.starts_with(&format!("│  {} ", name))).cloned().unwrap_or_default();
+        let adyen = row("Adyen");
+        assert!(adyen.contains("key ****3G7k  merchant AcmeCorpECOM"), "{}", adyen);
+        assert!(row("PayPal").contains("key ****fBP4  secret ****ZRbL"), "{}", row("PayPal"));
+        // Nothing more of either secret than its tail
+        let text = screen.concat();
+        assert!(!text.contains("AQEyhmfx") && !text.contains("EGnHDxD") && !text.contains("AZDxjD"));
+    }
+
+    #[test]
+    fn the_celebration_box_grows_to_its_widest_row_or_abbreviates() {
+        crate::config::use_temp_config_dir();
+        let mut app = App::new();
+        app.phase = AppPhase::Celebration;
+        app.total_cents = 123_456_789_012_345;
+        app.payment_count = 3;
+        let box_rows = |screen: &Buffer| -> Vec<String> {
+            screen_text(screen).into_iter().filter(|row| row.contains('║') || row.contains('╔')).collect()
+        };
+        // From the box's left border to its right one
+        let width = |row: &str| {
+            let (left, right) = (row.find(['║', '╔']).unwrap(), row.rfind(['║', '╗']).unwrap());
+            text_width(&row[left..right]) + 1
+        };
+
+        // Wide enough for the full total, the border moves out to fit it
+        let rows = box_rows(&render(&app, 100, 40));
+        assert!(rows.iter().any(|r| r.contains("€1,234,567,890,123.45")));
+        assert!(rows.iter().all(|r| width(r) == width(&rows[0])), "{:#?}", rows);
+        assert!(width(&rows[0]) > 32);
+
+        // Too narrow for it, amounts shrink so the box still closes inside the screen
+        app.total_cents = 12_345_678_901_234_567;
+        let rows = box_rows(&render(&app, 40, 40));
+        assert!(rows.iter().any(|r| r.contains("€123456.8B")), "{:#?}", rows);
+        assert!(rows.iter().all(|r| width(r) == width(&rows[0]) && width(r) <= 36), "{:#?}", rows);
+
+        // A small session keeps the classic 32 columns
+        app.total_cents = 4_500;
+        let rows = box_rows(&render(&app, 100, 40));
+        assert_eq!(width(&rows[0]), 32);
+    }
+
+    #[test]
+    fn wide_currency_symbols_keep_bills_and_the_box_aligned() {
+        crate::config::use_temp_config_dir();
+        let mut app = App::new();
+        app.phase = AppPhase::Running;
+        app.config.currency_symbol = "円".to_string();
+        app.config.symbol_position = crate::config::SymbolPosition::Suffix;
+        let now = chrono::Utc::now();
+        for (i, cents) in [500, 12_000, 1_250_000].into_iter().enumerate() {
+            app.add_payment(ScriptedProvider::payment("Mock", &format!("p{}", i), cents, now));
+        }
+        for _ in 0..3 {
+            app.spawn_next_bill(18);
+        }
+        for _ in 0..200 {
+            app.tick_animations();
+        }
+        // Where each cell showing `symbol` sits, by row; a wide glyph takes its own cell and the next
+        let cells = |screen: &Buffer, symbol: &str| -> Vec<(u16, u16)> {
+            let area = screen.area;
+            (area.top()..area.bottom())
+                .flat_map(|y| (area.left()..area.right()).map(move |x| (x, y)))
+                .filter(|&(x, y)| screen[(x, y)].symbol() == symbol)
+                .collect()
+        };
+
+        let screen = render(&app, 60, 30);
+        // Bill corners, not the pane's in the last column
+        let tops: Vec<u16> = cells(&screen, "┐").into_iter().filter(|(x, _)| *x < 59).map(|(x, _)| x).collect();
+        let bill_rows: Vec<u16> = cells(&screen, "円").into_iter().map(|(_, y)| y).filter(|y| *y > 2).collect();
+        let sides: Vec<u16> = bill_rows
+            .iter()
+            .map(|row| cells(&screen, "│").into_iter().filter(|(x, y)| y == row && *x < 59).map(|(x, _)| x).max().unwrap())
+            .collect();
+        assert_eq!(tops.len(), 3);
+        assert_eq!(sides.len(), 3, "{:#?}", screen_text(&screen));
+        assert!(tops.iter().chain(&sides).all(|x| *x == tops[0]), "{:?} {:?}", tops, sides);
+
+        // The celebration box closes in one column too
+        app.start_celebration();
+        let screen = render(&app, 60, 30);
+        let rights: Vec<u16> =
+            ["╗", "║", "╝"].iter().flat_map(|s| cells(&screen, s)).filter(|(x, _)| *x > 30).map(|(x, _)| x).collect();
+        assert!(rights.len() > 5);
+        assert!(rights.iter().all(|x| *x == rights[0]), "{:#?}", screen_text(&screen));
+        assert!(screen_text(&screen).iter().any(|r| r.contains("12,625.00 円")));
+    }
+
+    #[test]
+    fn confirm_previews_amounts_in_the_chosen_currency() {
+        crate::config::use_temp_config_dir();
+        let mut app = App::new();
+        app.setup_step = SetupStep::Confirm;
+        let preview = |app: &App| {
+            let screen = screen_text(&render(app, 100, 30));
+            screen.iter().find_map(|r| r.split("Amounts show as ").nth(1).map(|s| s.trim_end_matches([' ', '│']).to_string()))
+        };
+        assert_eq!(preview(&app).as_deref(), Some("€1,234.56"));
+
+        let jpy = app.currency_options().into_iter().find(|c| c.code == "JPY").unwrap();
+        app.select_currency(&jpy);
+        assert_eq!(preview(&app).as_deref(), Some("¥1,235"));
+
+        app.add_custom_currency("NOK kr 2 suffix").unwrap();
+        assert_eq!(preview(&app).as_deref(), Some("1,234.56 kr"));
+        app.config.decimal_separator = ",".to_string();
+        app.config.thousands_separator = " ".to_string();
+        assert_eq!(preview(&app).as_deref(), Some("1 234,56 kr"));
+    }
+
+    #[test]
+    fn provider_colors_are_stable_and_shared_by_labelled_accounts() {
+        let theme = Theme::named("default");
+        assert_eq!(provider_color("Adyen", theme), Color::Green);
+        assert_eq!(provider_color("Adyen (EU)", theme), provider_color("Adyen", theme));
+        assert_eq!(provider_color("PayPal (US)", theme), Color::LightBlue);
+        // Unknown providers get the same pick every time, from a fixed palette
+        for name in ["Square", "Klarna", "Worldpay"] {
+            let color = provider_color(name, theme);
+            assert_eq!(provider_color(name, theme), color);
+            assert!([Color::Magenta, Color::Blue, Color::LightRed, Color::LightYellow].contains(&color), "{}", name);
+        }
+        // Distinct known providers don't share a color
+        let known = ["Adyen", "PayPal", "Braintree", "GoCardless", "Coinbase", "Mock"];
+        let mut colors: Vec<String> = known.iter().map(|n| format!("{:?}", provider_color(n, theme))).collect();
+        colors.sort();
+        colors.dedup();
+        assert_eq!(colors.len(), known.len());
+        // Without provider colors everything is plain text
+        let plain = Theme::named("monochrome");
+        assert_eq!(provider_color("Adyen", plain), plain.text);
+    }
+
+    #[test]
+    fn a_tiny_terminal_asks_to_be_resized() {
+        crate::config::use_temp_config_dir();
+        let setup = App::new();
+        let running = running_app("default");
+        for app in [&setup, &running] {
+            let text = screen_text(&render(app, 20, 8));
+            assert_eq!(text[3].trim(), "Terminal too small");
+            assert_eq!(format!("{} {}", text[4].trim(), text[5].trim()), "please resize (min 40×15)");
+        }
+        assert!(!screen_text(&render(&running, 40, 15)).concat().contains("Terminal too small"));
+    }
+}
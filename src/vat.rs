@@ -0,0 +1,31 @@
+// VAT split of VAT-inclusive amounts; rates are in basis points, so 21% is 2100
+
+// The VAT inside a gross amount, rounded half away from zero
+pub fn vat_component(amount_cents: i64, bps: u32) -> i64 {
+    let gross = amount_cents as i128;
+    let divisor = 10_000 + bps as i128;
+    let scaled = gross * bps as i128;
+    let rounded = (scaled.abs() + divisor / 2) / divisor;
+    (rounded * scaled.signum()) as i64
+}
+
+// What's left of a gross amount once its VAT is taken out
+pub fn net_of_vat(amount_cents: i64, bps: u32) -> i64 {
+    amount_cents - vat_component(amount_cents, bps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vat_at_21_percent_splits_gross_into_component_and_net() {
+        for (gross, vat) in [(12_100, 2_100), (1_000, 174), (99, 17), (1, 0), (0, 0), (-12_100, -2_100), (i64::MAX, 1_600_750_518_792_977_619)] {
+            assert_eq!(vat_component(gross, 2_100), vat, "{}", gross);
+            assert_eq!(vat_component(gross, 2_100) + net_of_vat(gross, 2_100), gross);
+        }
+        // Without a rate there's nothing to take out
+        assert_eq!(vat_component(12_100, 0), 0);
+        assert_eq!(net_of_vat(12_100, 0), 12_100);
+    }
+}
@@ -0,0 +1,40 @@
+// --check: one fetch per provider, a line each, and the failures as the exit code
+mod common;
+
+use common::{profit_cli, scratch};
+
+fn check(name: &str, providers: serde_json::Value) -> (Option<i32>, Vec<String>) {
+    let dir = scratch(name);
+    let config = serde_json::json!({ "currency": "EUR", "currency_symbol": "€", "poll_interval_secs": 5, "providers": providers });
+    std::fs::write(dir.join("config.json"), config.to_string()).unwrap();
+    let output = profit_cli(&dir).arg("--config-dir").arg(&dir).args(["--no-keyring", "--check"]).output().unwrap();
+    let mut lines: Vec<String> = String::from_utf8(output.stdout).unwrap().lines().map(str::to_string).collect();
+    lines.sort();
+    (output.status.code(), lines)
+}
+
+#[test]
+fn working_providers_pass_the_check() {
+    let (code, lines) = check("check-ok", serde_json::json!([{ "provider": "Mock", "api_key": "2-2:EUR" }]));
+    assert_eq!(code, Some(0));
+    assert_eq!(lines, ["OK   Mock: 1 payments"]);
+}
+
+#[test]
+fn each_failing_provider_adds_to_the_exit_code() {
+    let providers = serde_json::json!([
+        { "provider": "Mock", "api_key": "2-2:EUR", "label": "good" },
+        { "provider": "Mock", "api_key": "9-1:EUR", "label": "broken" },
+        { "provider": "Strpie", "api_key": "sk_test" }
+    ]);
+    let (code, lines) = check("check-mixed", providers);
+    assert_eq!(code, Some(2));
+    assert_eq!(
+        lines,
+        [
+            "FAIL Mock (broken): Mock range 9-1 must satisfy 1 <= min <= max",
+            "FAIL Strpie: unknown provider name 'Strpie'",
+            "OK   Mock (good): 1 payments",
+        ]
+    );
+}
@@ -0,0 +1,62 @@
+// Helpers for tests that run the binary
+#![allow(dead_code)]
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// A fresh directory per test, so parallel tests don't share files
+pub fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("profit-cli-it-{}-{}", std::process::id(), name));
+    std::fs::remove_dir_all(&dir).ok();
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// The binary with its platform config directory under `home`, so nothing reads
+// or writes the real one
+pub fn profit_cli(home: &Path) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_profit-cli"));
+    cmd.env_remove("PROFIT_CONFIG_DIR").env("XDG_CONFIG_HOME", home).env("HOME", home);
+    cmd
+}
+
+pub fn stdout(cmd: &mut Command) -> String {
+    let output = cmd.output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+// A config.json counting Mock payments of one unit in `currency`, every second
+pub fn write_mock_config(dir: &Path, file: &str, currency: &str) {
+    std::fs::create_dir_all(dir).unwrap();
+    let config = serde_json::json!({
+        "currency": currency,
+        "currency_symbol": currency,
+        "poll_interval_secs": 1,
+        "providers": [{ "provider": "Mock", "api_key": format!("1-1:{}", currency) }]
+    });
+    std::fs::write(dir.join(file), config.to_string()).unwrap();
+}
+
+// Runs headless until the first payment is counted, then interrupts it like Ctrl-C
+// and returns the --summary-json line it printed on the way out
+#[cfg(unix)]
+pub fn run_until_payment(cmd: &mut Command) -> serde_json::Value {
+    use std::io::{BufRead, BufReader};
+    let mut child = cmd
+        .args(["--headless", "--no-keyring", "--fresh", "--summary-json"])
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines().map(|l| l.unwrap());
+    lines.find(|l| l.starts_with("[Mock]")).expect("no payment before exit");
+    let killed = Command::new("kill").arg("-INT").arg(child.id().to_string()).status().unwrap();
+    assert!(killed.success());
+    let summary = lines.last().expect("no summary on exit");
+    assert!(child.wait().unwrap().success());
+    serde_json::from_str(&summary).unwrap()
+}
+
+pub fn read_json(path: &Path) -> serde_json::Value {
+    serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+}
@@ -0,0 +1,71 @@
+// Where the binary looks for its config
+mod common;
+
+#[cfg(unix)]
+use common::{read_json, run_until_payment, write_mock_config};
+use common::{profit_cli, scratch, stdout};
+
+#[cfg(target_os = "linux")]
+#[test]
+fn config_path_defaults_to_the_platform_config_dir() {
+    let home = scratch("default-path");
+    let path = stdout(profit_cli(&home).arg("--print-config-path"));
+    assert_eq!(path.trim_end(), home.join("profit-cli").join("config.json").display().to_string());
+    assert!(path.trim_end().ends_with("profit-cli/config.json"));
+}
+
+#[test]
+fn env_override_moves_the_config_path() {
+    let home = scratch("env-path");
+    let dir = home.join("shop");
+    let path = stdout(profit_cli(&home).env("PROFIT_CONFIG_DIR", &dir).arg("--print-config-path"));
+    assert_eq!(path.trim_end(), dir.join("config.json").display().to_string());
+}
+
+#[cfg(unix)]
+#[test]
+fn env_dir_is_where_the_config_is_read_and_the_session_saved() {
+    let home = scratch("env-dir");
+    let dir = home.join("shop");
+    write_mock_config(&dir, "config.json", "USD");
+
+    let summary = run_until_payment(profit_cli(&home).env("PROFIT_CONFIG_DIR", &dir));
+    assert_eq!(summary["currency"], "USD");
+    assert_eq!(read_json(&dir.join("state.json"))["total_cents"], summary["total_cents"]);
+    // Nothing landed in the platform directory
+    assert!(!home.join("profit-cli").join("state.json").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn config_dir_flag_wins_over_the_env_var() {
+    let home = scratch("flag-dir");
+    write_mock_config(&home.join("env"), "config.json", "USD");
+    write_mock_config(&home.join("flag"), "config.json", "EUR");
+
+    let summary = run_until_payment(profit_cli(&home).env("PROFIT_CONFIG_DIR", home.join("env")).arg("--config-dir").arg(home.join("flag")));
+    assert_eq!(summary["currency"], "EUR");
+    assert!(home.join("flag").join("state.json").exists());
+    assert!(!home.join("env").join("state.json").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn profiles_keep_their_own_config_and_session() {
+    let dir = scratch("profiles");
+    write_mock_config(&dir, "config-acme.json", "EUR");
+    write_mock_config(&dir, "config-globex.json", "USD");
+    let run = |profile: &str| run_until_payment(profit_cli(&dir).arg("--config-dir").arg(&dir).args(["--profile", profile]));
+
+    let acme = run("acme");
+    let globex = run("globex");
+    assert_eq!((acme["currency"].as_str(), globex["currency"].as_str()), (Some("EUR"), Some("USD")));
+    assert_eq!(read_json(&dir.join("state-acme.json"))["total_cents"], acme["total_cents"]);
+    assert_eq!(read_json(&dir.join("state-globex.json"))["total_cents"], globex["total_cents"]);
+    // Neither touched the default profile or the other's config
+    assert!(!dir.join("state.json").exists() && !dir.join("config.json").exists());
+    assert_eq!(read_json(&dir.join("config-acme.json"))["currency"], "EUR");
+
+    let path = stdout(profit_cli(&dir).arg("--config-dir").arg(&dir).args(["--profile", "acme", "--print-config-path"]));
+    assert_eq!(path.trim_end(), dir.join("config-acme.json").display().to_string());
+}
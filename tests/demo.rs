@@ -0,0 +1,17 @@
+// --demo: the mock provider straight away, with nothing written to the config directory
+#![cfg(unix)]
+mod common;
+
+use common::{profit_cli, run_until_payment, scratch};
+
+#[test]
+fn demo_runs_mock_alone_and_writes_nothing() {
+    let dir = scratch("demo");
+    let summary = run_until_payment(profit_cli(&dir).arg("--config-dir").arg(&dir).arg("--demo"));
+    assert!(summary["payment_count"].as_u64().unwrap() > 0);
+    let providers: Vec<&str> = summary["providers"].as_array().unwrap().iter().map(|p| p["provider"].as_str().unwrap()).collect();
+    assert_eq!(providers, ["Mock"]);
+
+    let written: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+    assert!(written.is_empty(), "{:?}", written);
+}
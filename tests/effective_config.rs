@@ -0,0 +1,51 @@
+// --print-effective-config: the config as it would run, env overrides applied and credentials masked
+mod common;
+
+use common::{profit_cli, scratch, stdout};
+
+#[test]
+fn env_overrides_show_up_and_credentials_are_masked() {
+    let dir = scratch("effective-config");
+    let config = serde_json::json!({
+        "currency": "EUR",
+        "currency_symbol": "€",
+        "poll_interval_secs": 5,
+        "providers": [
+            { "provider": "Adyen", "api_key": "AQEstoredkeyA1b2", "merchant_account": "StoredECOM" },
+            { "provider": "PayPal", "api_key": "paypal_client_W9x8", "api_secret": "paypal_secret_Z7y6" }
+        ]
+    });
+    std::fs::write(dir.join("config.json"), config.to_string()).unwrap();
+    let printed = stdout(
+        profit_cli(&dir)
+            .arg("--config-dir")
+            .arg(&dir)
+            .args(["--no-keyring", "--print-effective-config"])
+            .env("PROFIT_ADYEN_API_KEY", "AQEfromtheenvQ4r5")
+            .env("PROFIT_ADYEN_MERCHANT", "EnvECOM")
+            .env("PROFIT_GOCARDLESS_API_KEY", "live_gocardless_T3u4"),
+    );
+    let effective: serde_json::Value = serde_json::from_str(&printed).unwrap();
+    let providers = effective["providers"].as_array().unwrap();
+    let fields: Vec<(&str, &str, Option<&str>, Option<&str>)> = providers
+        .iter()
+        .map(|p| {
+            let (name, key) = (p["provider"].as_str().unwrap(), p["api_key"].as_str().unwrap());
+            (name, key, p["merchant_account"].as_str(), p["api_secret"].as_str())
+        })
+        .collect();
+    assert_eq!(
+        fields,
+        [
+            ("Adyen", "****Q4r5", Some("****ECOM"), None),
+            ("PayPal", "****W9x8", None, Some("****Z7y6")),
+            ("GoCardless", "****T3u4", None, None),
+        ]
+    );
+    // No credential makes it out whole
+    let secrets = ["AQEfromtheenvQ4r5", "AQEstoredkeyA1b2", "EnvECOM", "StoredECOM", "paypal_client_W9x8", "paypal_secret_Z7y6", "live_gocardless_T3u4"];
+    for secret in secrets {
+        assert!(!printed.contains(secret), "{} printed in full", secret);
+    }
+    assert_eq!(effective["poll_interval_secs"], 5);
+}
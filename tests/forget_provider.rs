@@ -0,0 +1,47 @@
+// --forget-provider: one provider out of the saved config, the rest left as they were
+mod common;
+
+use common::{profit_cli, read_json, scratch, stdout};
+
+#[test]
+fn forgetting_a_provider_keeps_the_others() {
+    let dir = scratch("forget-provider");
+    let adyen = |label: &str| {
+        let (key, merchant) = (format!("AQE{}key", label), format!("Shop{}", label));
+        serde_json::json!({ "provider": "Adyen", "label": label, "api_key": key, "merchant_account": merchant })
+    };
+    let paypal = serde_json::json!({ "provider": "PayPal", "api_key": "paypal_client", "api_secret": "paypal_secret" });
+    let config = serde_json::json!({
+        "currency": "EUR",
+        "currency_symbol": "€",
+        "poll_interval_secs": 5,
+        "providers": [adyen("EU"), adyen("US"), paypal]
+    });
+    std::fs::write(dir.join("config.json"), config.to_string()).unwrap();
+    let forget = |name: &str| {
+        let mut cmd = profit_cli(&dir);
+        cmd.arg("--config-dir").arg(&dir).args(["--no-keyring", "--forget-provider", name]);
+        cmd
+    };
+
+    // Matched by display name, ignoring case
+    let printed = stdout(&mut forget("adyen (eu)"));
+    assert!(printed.starts_with("Removed Adyen (EU) from "), "{}", printed);
+    let providers = read_json(&dir.join("config.json"))["providers"].clone();
+    let kept: Vec<(&str, &str)> = providers
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| (p["provider"].as_str().unwrap(), p["api_key"].as_str().unwrap()))
+        .collect();
+    assert_eq!(kept, [("Adyen", "AQEUSkey"), ("PayPal", "paypal_client")]);
+    assert_eq!(providers[0]["merchant_account"], "ShopUS");
+    assert_eq!(providers[1]["api_secret"], "paypal_secret");
+
+    // An unknown name changes nothing and says what there is
+    let output = forget("Stripe").output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("No provider named Stripe (configured: Adyen (US), PayPal)"), "{}", stderr);
+    assert_eq!(read_json(&dir.join("config.json"))["providers"], providers);
+}
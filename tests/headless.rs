@@ -0,0 +1,38 @@
+// --headless: payments logged one line each as the Mock provider produces them
+#![cfg(unix)]
+mod common;
+
+use common::{profit_cli, scratch, write_mock_config};
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+
+// "EUR1,234.50" → 123450
+fn cents(amount: &str) -> i64 {
+    amount.trim_start_matches(char::is_alphabetic).replace([',', '.'], "").parse().unwrap()
+}
+
+#[test]
+fn headless_logs_each_payment_with_the_running_total() {
+    let dir = scratch("headless");
+    write_mock_config(&dir, "config.json", "EUR");
+    let mut child = profit_cli(&dir)
+        .arg("--config-dir")
+        .arg(&dir)
+        .args(["--headless", "--no-keyring", "--fresh"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines().map(|l| l.unwrap());
+
+    // A couple of polls' worth, each line adding its amount to the total before it
+    let mut total = 0;
+    for _ in 0..3 {
+        let line = lines.find(|l| l.starts_with("[Mock] +")).expect("headless stopped logging");
+        let (amount, running) = line.trim_start_matches("[Mock] +").split_once(" total=").unwrap();
+        total += cents(amount);
+        assert_eq!(cents(running), total, "{}", line);
+    }
+    assert!(Command::new("kill").arg("-INT").arg(child.id().to_string()).status().unwrap().success());
+    assert!(child.wait().unwrap().success());
+}
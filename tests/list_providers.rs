@@ -0,0 +1,25 @@
+// --list-providers: one line per provider setup offers, with its credentials and their env vars
+mod common;
+
+use common::{profit_cli, scratch, stdout};
+
+#[test]
+fn every_registered_provider_is_listed_with_its_fields() {
+    let dir = scratch("list-providers");
+    let listing = stdout(profit_cli(&dir).arg("--list-providers"));
+    let lines: Vec<&str> = listing.lines().collect();
+
+    let names: Vec<&str> = lines.iter().map(|l| l.split(':').next().unwrap()).collect();
+    assert_eq!(names, profit_cli::psp::registry::registry().setup_names());
+    assert_eq!(
+        lines,
+        [
+            "Mock: none",
+            "Adyen: api_key, merchant_account; env PROFIT_ADYEN_API_KEY, PROFIT_ADYEN_MERCHANT",
+            "PayPal: api_key, api_secret; env PROFIT_PAYPAL_API_KEY, PROFIT_PAYPAL_SECRET",
+            "GoCardless: api_key; env PROFIT_GOCARDLESS_API_KEY",
+            "Coinbase: api_key; env PROFIT_COINBASE_API_KEY",
+            "Braintree: api_key, api_secret; env PROFIT_BRAINTREE_API_KEY, PROFIT_BRAINTREE_SECRET",
+        ]
+    );
+}
@@ -0,0 +1,103 @@
+// Drives the library the way an embedder would: a provider of its own, the public poller and App
+use async_trait::async_trait;
+use profit_cli::poll::{PollEvent, Poller};
+use profit_cli::{App, AppConfig, Payment, PaymentProvider, PspConfig};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, Notify};
+
+// One scripted batch per fetch, then nothing
+struct ScriptedProvider {
+    batches: Mutex<VecDeque<Vec<Payment>>>,
+}
+
+#[async_trait]
+impl PaymentProvider for ScriptedProvider {
+    fn name(&self) -> &str {
+        "Scripted"
+    }
+
+    async fn fetch_recent_payments(&self, _since: chrono::DateTime<chrono::Utc>) -> anyhow::Result<Vec<Payment>> {
+        Ok(self.batches.lock().unwrap().pop_front().unwrap_or_default())
+    }
+}
+
+fn payment(id: &str, amount_cents: i64, created_at: chrono::DateTime<chrono::Utc>) -> Payment {
+    Payment {
+        id: id.to_string(),
+        amount_cents,
+        currency: "EUR".to_string(),
+        status: "succeeded".to_string(),
+        created_at,
+        provider: "Scripted".to_string(),
+        converted_cents: None,
+        settled: true,
+    }
+}
+
+fn app(config: AppConfig) -> App {
+    let dir = std::env::temp_dir().join(format!("profit-cli-it-{}", std::process::id()));
+    profit_cli::config::set_config_dir(Some(&dir)).unwrap();
+    App::from_config(config)
+}
+
+fn ingest(app: &mut App, rx: &mut mpsc::UnboundedReceiver<PollEvent>) {
+    while let Ok(event) = rx.try_recv() {
+        if let PollEvent::Payments { payments, .. } = event {
+            for p in payments {
+                app.add_payment(p);
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn bounded_poll_totals_scripted_payments() {
+    let now = chrono::Utc::now();
+    let batches = vec![
+        vec![payment("a", 1_000, now), payment("b", 2_500, now)],
+        vec![],
+        // Seen again through the window overlap, plus one new
+        vec![payment("b", 2_500, now), payment("c", 499, now)],
+    ];
+    let provider: Arc<dyn PaymentProvider> = Arc::new(ScriptedProvider { batches: Mutex::new(batches.into()) });
+    let poller = Poller::new(vec![provider]);
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut windows = vec![now - chrono::Duration::minutes(5)];
+
+    let mut app = app(AppConfig::default());
+    for _ in 0..4 {
+        assert!(poller.poll_once(&tx, &mut windows, chrono::Duration::seconds(30)).await);
+        ingest(&mut app, &mut rx);
+    }
+    assert_eq!(app.total_cents, 3_999);
+    assert_eq!(app.payment_count, 3);
+}
+
+#[tokio::test]
+async fn polling_starts_from_a_config() {
+    let config = AppConfig {
+        providers: vec![PspConfig {
+            provider: "Mock".to_string(),
+            api_key: "5-5:EUR".to_string(),
+            ..PspConfig::default()
+        }],
+        ..AppConfig::default()
+    };
+    let mut app = app(config);
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let handle = app.start_polling(tx, Arc::new(Notify::new())).unwrap().expect("Mock should start");
+
+    // The first fetch is immediate and reports even when it found nothing
+    let event = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv()).await.unwrap();
+    let Some(PollEvent::Payments { provider, payments }) = event else {
+        panic!("expected payments, got {:?}", event);
+    };
+    assert_eq!(provider, "Mock");
+    let count = payments.len();
+    for p in payments {
+        assert!(app.add_payment(p));
+    }
+    assert_eq!(app.total_cents, 500 * count as i64);
+    handle.abort();
+}
@@ -0,0 +1,17 @@
+// Runs the binary headless and stops it the way a terminal or service manager would
+#![cfg(unix)]
+mod common;
+
+use common::{profit_cli, read_json, run_until_payment, scratch, write_mock_config};
+
+#[test]
+fn interrupted_session_saves_its_final_total() {
+    let dir = scratch("shutdown");
+    write_mock_config(&dir, "config.json", "EUR");
+
+    let summary = run_until_payment(profit_cli(&dir).arg("--config-dir").arg(&dir));
+    let state = read_json(&dir.join("state.json"));
+    assert!(summary["total_cents"].as_i64().unwrap() > 0);
+    assert_eq!(state["total_cents"], summary["total_cents"]);
+    assert_eq!(state["payment_count"], summary["payment_count"]);
+}